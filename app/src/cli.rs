@@ -10,8 +10,27 @@ pub fn init() -> Cli {
 #[clap(author, version, about, long_about = None)]
 #[clap(propagate_version = true)]
 pub struct Cli {
+    /// Control port of an already running Tor. Overrides the settings file.
     #[clap(short = 't', long, alias = "local-tor-port")]
     pub local_tor_control_port: Option<u16>,
     #[clap(short = 'b', long, alias = "base-dir")]
     pub base_dir: Option<PathBuf>,
+    /// Name of the profile to use. If omitted, a profile picker is shown on startup.
+    #[clap(short = 'p', long)]
+    pub profile: Option<String>,
+    /// Board theme to use instead of the one in the settings file
+    #[clap(long)]
+    pub theme: Option<String>,
+    /// Turn sound effects off
+    #[clap(long)]
+    pub mute: bool,
+    /// Turn desktop notifications off
+    #[clap(long)]
+    pub no_notifications: bool,
+    /// Seconds between syncing the open game with the opponent
+    #[clap(long)]
+    pub sync_interval: Option<u64>,
+    /// Peer to join the network through, as "<public key hex>::<address>". Can be given more than once.
+    #[clap(long = "seed-peer")]
+    pub seed_peers: Vec<String>,
 }
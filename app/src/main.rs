@@ -1,4 +1,5 @@
 mod cli;
+mod profile;
 
 use std::{env, fs::File, io::Read, path::Path, sync::Arc};
 
@@ -7,7 +8,15 @@ use multiaddr::multiaddr;
 use networking::{Networking, NetworkingConfig, NodeIdentity, PeerFeatures};
 use rand::rngs::OsRng;
 use tari_shutdown::Shutdown;
-use ui::{ChessUi, ScaleMode, WindowOptions};
+use ui::{
+    settings::{Settings, SettingsOverrides, SETTINGS_FILE},
+    ChessUi,
+    ProfilePicker,
+    ScaleMode,
+    WindowOptions,
+};
+
+use crate::profile::{Profiles, DEFAULT_PROFILE};
 
 const WINDOW_WIDTH: usize = 1024;
 const WINDOW_HEIGHT: usize = 90 * 8;
@@ -16,6 +25,7 @@ const WINDOW_HEIGHT: usize = 90 * 8;
 async fn main() -> anyhow::Result<()> {
     env_logger::init();
     let cli = cli::init();
+    let settings_overrides = settings_overrides(&cli);
     #[allow(deprecated)]
     let base_path = cli
         .base_dir
@@ -23,34 +33,64 @@ async fn main() -> anyhow::Result<()> {
         .or_else(|| env::home_dir())
         .map(|p| p.join(".p2pchess"))
         .ok_or_else(|| anyhow!("Unable to determine home directory. Use --base-dir to specify one."))?;
-    let node_identity = load_json(base_path.join("node-identity.json"))?
+    let window_opts = WindowOptions {
+        title: true,
+        // The UI lays itself out for the window size, so the buffer is always drawn 1:1
+        scale_mode: ScaleMode::UpperLeft,
+        resize: true,
+        ..Default::default()
+    };
+
+    let profiles = Profiles::new(&base_path);
+    profiles.migrate_legacy(&base_path)?;
+    let profile_name = match cli.profile {
+        Some(name) => name,
+        None => {
+            let existing = profiles.list()?;
+            if existing.is_empty() {
+                DEFAULT_PROFILE.to_string()
+            } else {
+                match ProfilePicker::new("Privacy Chess", WINDOW_WIDTH, WINDOW_HEIGHT, window_opts, existing).run()? {
+                    Some(name) => name,
+                    // Window closed without choosing a profile
+                    None => return Ok(()),
+                }
+            }
+        },
+    };
+    let profile_path = profiles.open(&profile_name)?;
+    log::info!("Using profile '{}'", profile_name);
+
+    let settings = settings_overrides.apply(&Settings::load(base_path.join(SETTINGS_FILE))?);
+
+    let node_identity = load_json(profile_path.join("node-identity.json"))?
         .map(Arc::new)
         .unwrap_or_else(create_node_identity);
     let shutdown = Shutdown::new();
     let signal = shutdown.to_signal();
 
-    let (channel1, channel2) = p2p_chess_channel::channel(10);
-    let ui = ChessUi::new(
+    // Starting a tournament round queues a pairing for every participant in the same frame
+    let (channel1, channel2) = p2p_chess_channel::channel(100);
+    let mut ui = ChessUi::new(
         "Privacy Chess",
         WINDOW_WIDTH,
         WINDOW_HEIGHT,
-        WindowOptions {
-            title: true,
-            scale_mode: ScaleMode::Center,
-            resize: true,
-            ..Default::default()
-        },
-        base_path.clone(),
+        window_opts,
+        profile_path.clone(),
         channel1,
         node_identity.public_key().clone(),
     );
+    ui.set_themes_path(base_path.join("themes"))
+        .set_settings_path(base_path.join(SETTINGS_FILE))
+        .set_settings_overrides(settings_overrides);
 
     let config = NetworkingConfig {
-        start_inprocess_tor: cli.local_tor_control_port.is_none(),
-        tor_control_port: cli.local_tor_control_port,
+        start_inprocess_tor: settings.network.tor_control_port.is_none(),
+        tor_control_port: settings.network.tor_control_port,
+        seed_peers: settings.network.seed_peers,
     };
     println!("Starting networking...");
-    let mut networking = Networking::start(config, node_identity, &base_path, channel2, signal).await?;
+    let mut networking = Networking::start(config, node_identity, &profile_path, channel2, signal).await?;
 
     loop {
         println!("Waiting for peer connections...");
@@ -66,6 +106,18 @@ async fn main() -> anyhow::Result<()> {
     Ok(())
 }
 
+/// Settings given as flags, which take precedence over the settings file
+fn settings_overrides(cli: &cli::Cli) -> SettingsOverrides {
+    SettingsOverrides {
+        theme: cli.theme.clone(),
+        is_sound_muted: if cli.mute { Some(true) } else { None },
+        show_notifications: if cli.no_notifications { Some(false) } else { None },
+        sync_interval_secs: cli.sync_interval,
+        tor_control_port: cli.local_tor_control_port,
+        seed_peers: cli.seed_peers.clone(),
+    }
+}
+
 fn load_json<T: serde::de::DeserializeOwned, P: AsRef<Path>>(path: P) -> anyhow::Result<Option<T>> {
     if !path.as_ref().exists() {
         return Ok(None);
@@ -0,0 +1,79 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::anyhow;
+use ui::profile_screen::is_valid_name;
+
+pub const DEFAULT_PROFILE: &str = "default";
+
+/// Files that lived directly in the base directory before profiles were introduced.
+const LEGACY_ENTRIES: &[&str] = &["node-identity.json", "tor.json", "p2pc-games.json", "db"];
+
+/// Each profile has its own directory under `<base_dir>/profiles` containing the node identity, games and peer db.
+#[derive(Debug, Clone)]
+pub struct Profiles {
+    profiles_path: PathBuf,
+}
+
+impl Profiles {
+    pub fn new<P: AsRef<Path>>(base_path: P) -> Self {
+        Self {
+            profiles_path: base_path.as_ref().join("profiles"),
+        }
+    }
+
+    /// Moves an identity created before profiles existed into the default profile.
+    pub fn migrate_legacy<P: AsRef<Path>>(&self, base_path: P) -> anyhow::Result<()> {
+        let base_path = base_path.as_ref();
+        if self.profiles_path.exists() || !base_path.join("node-identity.json").exists() {
+            return Ok(());
+        }
+
+        let default_path = self.profiles_path.join(DEFAULT_PROFILE);
+        fs::create_dir_all(&default_path)?;
+        for entry in LEGACY_ENTRIES {
+            let src = base_path.join(entry);
+            if src.exists() {
+                fs::rename(&src, default_path.join(entry))?;
+            }
+        }
+        log::info!("Migrated existing identity to profile '{}'", DEFAULT_PROFILE);
+        Ok(())
+    }
+
+    pub fn list(&self) -> anyhow::Result<Vec<String>> {
+        if !self.profiles_path.exists() {
+            return Ok(Vec::new());
+        }
+
+        let mut names = Vec::new();
+        for entry in fs::read_dir(&self.profiles_path)? {
+            let entry = entry?;
+            if !entry.file_type()?.is_dir() {
+                continue;
+            }
+            if let Some(name) = entry.file_name().to_str() {
+                if is_valid_name(name) {
+                    names.push(name.to_string());
+                }
+            }
+        }
+        names.sort();
+        Ok(names)
+    }
+
+    /// Returns the directory for the named profile, creating it if it does not exist yet.
+    pub fn open(&self, name: &str) -> anyhow::Result<PathBuf> {
+        if !is_valid_name(name) {
+            return Err(anyhow!(
+                "Invalid profile name '{}'. Only letters, numbers, '-' and '_' are allowed.",
+                name
+            ));
+        }
+        let path = self.profiles_path.join(name);
+        fs::create_dir_all(&path)?;
+        Ok(path)
+    }
+}
@@ -14,8 +14,64 @@ pub struct ChessOperation {
 
 #[derive(Debug, Clone)]
 pub enum OperationType {
-    NewGame { player: u8 },
-    MovePlayed { mv: u16, board: String },
+    NewGame {
+        player: u8,
+        /// Set if the game was paired by a tournament organiser rather than the opponent
+        pairing: Option<TournamentPairing>,
+    },
+    MovePlayed {
+        mv: u16,
+        board: String,
+    },
     Resign,
-    Sync { board: String },
+    Sync {
+        board: String,
+    },
+    /// Asks the opponent to approve a spectator for the game
+    SpectatorRequest {
+        spectator: CommsPublicKey,
+    },
+    SpectatorResponse {
+        spectator: CommsPublicKey,
+        accepted: bool,
+    },
+    /// Sent to a spectator that accepted an invite once the opponent approved, with the game between `white` and
+    /// `black`
+    SpectateGame {
+        white: CommsPublicKey,
+        black: CommsPublicKey,
+        board: String,
+    },
+    /// Latest tournament state sent by the organiser to every participant
+    TournamentUpdate {
+        state: String,
+    },
+    /// Result of a tournament game reported to the organiser
+    TournamentResult {
+        tournament_id: u32,
+        outcome: u8,
+    },
+    /// Asks the opponent to undo the last `plies` moves. `seq` is the move the request was made at.
+    TakebackRequest {
+        plies: u32,
+    },
+    /// Sent to the opponent, and to spectators if accepted, with the `seq` and `plies` of the request
+    TakebackResponse {
+        plies: u32,
+        accepted: bool,
+    },
+    /// Offers the opponent a draw. `seq` is the move the offer was made at.
+    DrawOffer,
+    /// Accepts the draw offered at `seq`, ending the game
+    DrawAccept,
+    /// Invites a spectator, who answers with `SpectateAccept` if they want to watch the game
+    SpectateInvite,
+    /// Sent to the inviting player by a spectator who wants to watch the game
+    SpectateAccept,
+}
+
+#[derive(Debug, Clone)]
+pub struct TournamentPairing {
+    pub tournament_id: u32,
+    pub opponent: CommsPublicKey,
 }
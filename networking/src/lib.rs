@@ -14,7 +14,7 @@ use std::{
 };
 
 use anyhow::anyhow;
-use p2p_chess_channel::{ChessOperation, MessageChannel, OperationType};
+use p2p_chess_channel::{ChessOperation, MessageChannel, OperationType, TournamentPairing};
 use rand::{rngs::OsRng, thread_rng, Rng, RngCore};
 // Re-exports
 pub use tari_comms::{
@@ -33,11 +33,31 @@ use tari_comms_dht::{
     outbound::OutboundEncryption,
     Dht,
 };
+use tari_crypto::tari_utilities::ByteArray;
 use tari_shutdown::ShutdownSignal;
 use tokio::{sync::mpsc, task};
 
 use crate::{
-    message::{Message, MessageType, MoveMsg, NewGameMsg, ProtoMessage, ResignMsg, SyncMsg},
+    message::{
+        DrawAcceptMsg,
+        DrawOfferMsg,
+        Message,
+        MessageType,
+        MoveMsg,
+        NewGameMsg,
+        ProtoMessage,
+        ResignMsg,
+        SpectateAcceptMsg,
+        SpectateGameMsg,
+        SpectateInviteMsg,
+        SpectatorRequestMsg,
+        SpectatorResponseMsg,
+        SyncMsg,
+        TakebackRequestMsg,
+        TakebackResponseMsg,
+        TournamentResultMsg,
+        TournamentUpdateMsg,
+    },
     tor_handle::TorHandle,
 };
 
@@ -48,9 +68,15 @@ pub struct Networking {
     node_identity: Arc<NodeIdentity>,
 }
 
+/// Peers used to join the network when none are configured
+pub const DEFAULT_SEED_PEERS: [&str; 1] = ["881d8742d4cdf5dc99def7271405e6a3fc56080ea3387cc568b9efdef1cdeb7b::/\
+                                            onion3/spxhershjnwrl5p366xx2lc44sjxrhu3kypjj6ws3jxe4yiwy5ocftqd:18141"];
+
 pub struct NetworkingConfig {
     pub start_inprocess_tor: bool,
     pub tor_control_port: Option<u16>,
+    /// Peers as "<public key hex>::<address>". The default seed peers are used if empty.
+    pub seed_peers: Vec<String>,
 }
 
 impl Networking {
@@ -63,14 +89,14 @@ impl Networking {
     ) -> anyhow::Result<NetworkingHandle> {
         fs::create_dir_all(base_path.as_ref())?;
         let tor_identity = load_json(base_path.as_ref().join("tor.json"))?;
-        // TODO
-        let seed_peers = [
-            "881d8742d4cdf5dc99def7271405e6a3fc56080ea3387cc568b9efdef1cdeb7b::/onion3/\
-             spxhershjnwrl5p366xx2lc44sjxrhu3kypjj6ws3jxe4yiwy5ocftqd:18141",
-        ]
-        .into_iter()
-        .map(|s| peer_from_str(s).unwrap())
-        .collect();
+        let seed_peers = if config.seed_peers.is_empty() {
+            DEFAULT_SEED_PEERS.iter().map(|s| s.to_string()).collect()
+        } else {
+            config.seed_peers.clone()
+        }
+        .iter()
+        .map(|s| peer_from_str(s).ok_or_else(|| anyhow!("Invalid seed peer '{}'", s)))
+        .collect::<anyhow::Result<Vec<_>>>()?;
 
         let control_port = config
             .tor_control_port
@@ -133,11 +159,16 @@ impl Networking {
     async fn handle_operation(&self, op: ChessOperation) -> anyhow::Result<()> {
         dbg!("sending", &op);
         match op.operation {
-            OperationType::NewGame { player } => {
+            OperationType::NewGame { player, pairing } => {
+                let (opponent, tournament_id) = pairing
+                    .map(|p| (p.opponent.as_bytes().to_vec(), p.tournament_id))
+                    .unwrap_or_default();
                 self.broadcast_msg(
                     op.to,
                     Message::new(op.game_id, op.seq, MessageType::NewGame, NewGameMsg {
                         player: player as u32,
+                        opponent,
+                        tournament_id,
                     }),
                 )
                 .await?;
@@ -163,6 +194,107 @@ impl Networking {
                 )
                 .await?;
             },
+            OperationType::SpectatorRequest { spectator } => {
+                self.broadcast_msg(
+                    op.to,
+                    Message::new(op.game_id, op.seq, MessageType::SpectatorRequest, SpectatorRequestMsg {
+                        spectator: spectator.as_bytes().to_vec(),
+                    }),
+                )
+                .await?;
+            },
+            OperationType::SpectatorResponse { spectator, accepted } => {
+                self.broadcast_msg(
+                    op.to,
+                    Message::new(
+                        op.game_id,
+                        op.seq,
+                        MessageType::SpectatorResponse,
+                        SpectatorResponseMsg {
+                            spectator: spectator.as_bytes().to_vec(),
+                            accepted,
+                        },
+                    ),
+                )
+                .await?;
+            },
+            OperationType::SpectateGame { white, black, board } => {
+                self.broadcast_msg(
+                    op.to,
+                    Message::new(op.game_id, op.seq, MessageType::SpectateGame, SpectateGameMsg {
+                        white: white.as_bytes().to_vec(),
+                        black: black.as_bytes().to_vec(),
+                        board,
+                    }),
+                )
+                .await?;
+            },
+            OperationType::TournamentUpdate { state } => {
+                self.broadcast_msg(
+                    op.to,
+                    Message::new(op.game_id, op.seq, MessageType::TournamentUpdate, TournamentUpdateMsg {
+                        state,
+                    }),
+                )
+                .await?;
+            },
+            OperationType::TournamentResult { tournament_id, outcome } => {
+                self.broadcast_msg(
+                    op.to,
+                    Message::new(op.game_id, op.seq, MessageType::TournamentResult, TournamentResultMsg {
+                        tournament_id,
+                        outcome: outcome as u32,
+                    }),
+                )
+                .await?;
+            },
+            OperationType::TakebackRequest { plies } => {
+                self.broadcast_msg(
+                    op.to,
+                    Message::new(op.game_id, op.seq, MessageType::TakebackRequest, TakebackRequestMsg {
+                        plies,
+                    }),
+                )
+                .await?;
+            },
+            OperationType::TakebackResponse { plies, accepted } => {
+                self.broadcast_msg(
+                    op.to,
+                    Message::new(op.game_id, op.seq, MessageType::TakebackResponse, TakebackResponseMsg {
+                        plies,
+                        accepted,
+                    }),
+                )
+                .await?;
+            },
+            OperationType::DrawOffer => {
+                self.broadcast_msg(
+                    op.to,
+                    Message::new(op.game_id, op.seq, MessageType::DrawOffer, DrawOfferMsg),
+                )
+                .await?;
+            },
+            OperationType::DrawAccept => {
+                self.broadcast_msg(
+                    op.to,
+                    Message::new(op.game_id, op.seq, MessageType::DrawAccept, DrawAcceptMsg),
+                )
+                .await?;
+            },
+            OperationType::SpectateInvite => {
+                self.broadcast_msg(
+                    op.to,
+                    Message::new(op.game_id, op.seq, MessageType::SpectateInvite, SpectateInviteMsg),
+                )
+                .await?;
+            },
+            OperationType::SpectateAccept => {
+                self.broadcast_msg(
+                    op.to,
+                    Message::new(op.game_id, op.seq, MessageType::SpectateAccept, SpectateAcceptMsg),
+                )
+                .await?;
+            },
         }
 
         Ok(())
@@ -182,6 +314,14 @@ impl Networking {
                 let op = match msg_type {
                     MessageType::NewGame => {
                         let msg = Message::<NewGameMsg>::try_from(msg)?;
+                        let pairing = if msg.payload.opponent.is_empty() {
+                            None
+                        } else {
+                            Some(TournamentPairing {
+                                tournament_id: msg.payload.tournament_id,
+                                opponent: public_key_from_bytes(&msg.payload.opponent)?,
+                            })
+                        };
                         ChessOperation {
                             game_id: msg.id,
                             seq: msg.seq,
@@ -189,6 +329,7 @@ impl Networking {
                             from: src_public_key,
                             operation: OperationType::NewGame {
                                 player: msg.payload.player as u8,
+                                pairing,
                             },
                         }
                     },
@@ -227,6 +368,135 @@ impl Networking {
                             },
                         }
                     },
+                    MessageType::SpectatorRequest => {
+                        let msg = Message::<SpectatorRequestMsg>::try_from(msg)?;
+                        ChessOperation {
+                            game_id: msg.id,
+                            seq: msg.seq,
+                            to: self.node_identity.public_key().clone(),
+                            from: src_public_key,
+                            operation: OperationType::SpectatorRequest {
+                                spectator: public_key_from_bytes(&msg.payload.spectator)?,
+                            },
+                        }
+                    },
+                    MessageType::SpectatorResponse => {
+                        let msg = Message::<SpectatorResponseMsg>::try_from(msg)?;
+                        ChessOperation {
+                            game_id: msg.id,
+                            seq: msg.seq,
+                            to: self.node_identity.public_key().clone(),
+                            from: src_public_key,
+                            operation: OperationType::SpectatorResponse {
+                                spectator: public_key_from_bytes(&msg.payload.spectator)?,
+                                accepted: msg.payload.accepted,
+                            },
+                        }
+                    },
+                    MessageType::SpectateGame => {
+                        let msg = Message::<SpectateGameMsg>::try_from(msg)?;
+                        ChessOperation {
+                            game_id: msg.id,
+                            seq: msg.seq,
+                            to: self.node_identity.public_key().clone(),
+                            from: src_public_key,
+                            operation: OperationType::SpectateGame {
+                                white: public_key_from_bytes(&msg.payload.white)?,
+                                black: public_key_from_bytes(&msg.payload.black)?,
+                                board: msg.payload.board,
+                            },
+                        }
+                    },
+                    MessageType::TournamentUpdate => {
+                        let msg = Message::<TournamentUpdateMsg>::try_from(msg)?;
+                        ChessOperation {
+                            game_id: msg.id,
+                            seq: msg.seq,
+                            to: self.node_identity.public_key().clone(),
+                            from: src_public_key,
+                            operation: OperationType::TournamentUpdate {
+                                state: msg.payload.state,
+                            },
+                        }
+                    },
+                    MessageType::TournamentResult => {
+                        let msg = Message::<TournamentResultMsg>::try_from(msg)?;
+                        ChessOperation {
+                            game_id: msg.id,
+                            seq: msg.seq,
+                            to: self.node_identity.public_key().clone(),
+                            from: src_public_key,
+                            operation: OperationType::TournamentResult {
+                                tournament_id: msg.payload.tournament_id,
+                                outcome: msg.payload.outcome as u8,
+                            },
+                        }
+                    },
+                    MessageType::TakebackRequest => {
+                        let msg = Message::<TakebackRequestMsg>::try_from(msg)?;
+                        ChessOperation {
+                            game_id: msg.id,
+                            seq: msg.seq,
+                            to: self.node_identity.public_key().clone(),
+                            from: src_public_key,
+                            operation: OperationType::TakebackRequest {
+                                plies: msg.payload.plies,
+                            },
+                        }
+                    },
+                    MessageType::TakebackResponse => {
+                        let msg = Message::<TakebackResponseMsg>::try_from(msg)?;
+                        ChessOperation {
+                            game_id: msg.id,
+                            seq: msg.seq,
+                            to: self.node_identity.public_key().clone(),
+                            from: src_public_key,
+                            operation: OperationType::TakebackResponse {
+                                plies: msg.payload.plies,
+                                accepted: msg.payload.accepted,
+                            },
+                        }
+                    },
+                    MessageType::DrawOffer => {
+                        let msg = Message::<DrawOfferMsg>::try_from(msg)?;
+                        ChessOperation {
+                            game_id: msg.id,
+                            seq: msg.seq,
+                            to: self.node_identity.public_key().clone(),
+                            from: src_public_key,
+                            operation: OperationType::DrawOffer,
+                        }
+                    },
+                    MessageType::DrawAccept => {
+                        let msg = Message::<DrawAcceptMsg>::try_from(msg)?;
+                        ChessOperation {
+                            game_id: msg.id,
+                            seq: msg.seq,
+                            to: self.node_identity.public_key().clone(),
+                            from: src_public_key,
+                            operation: OperationType::DrawAccept,
+                        }
+                    },
+                    MessageType::SpectateInvite => {
+                        let msg = Message::<SpectateInviteMsg>::try_from(msg)?;
+                        ChessOperation {
+                            game_id: msg.id,
+                            seq: msg.seq,
+                            to: self.node_identity.public_key().clone(),
+                            from: src_public_key,
+                            operation: OperationType::SpectateInvite,
+                        }
+                    },
+                    MessageType::SpectateAccept => {
+                        let msg = Message::<SpectateAcceptMsg>::try_from(msg)?;
+                        ChessOperation {
+                            game_id: msg.id,
+                            seq: msg.seq,
+                            to: self.node_identity.public_key().clone(),
+                            from: src_public_key,
+                            operation: OperationType::SpectateAccept,
+                        }
+                    },
                 };
 
                 self.channel.send(op).await?;
@@ -315,6 +585,10 @@ fn save_json<T: serde::Serialize, P: AsRef<Path>>(path: P, item: &T) -> anyhow::
     Ok(())
 }
 
+fn public_key_from_bytes(bytes: &[u8]) -> anyhow::Result<CommsPublicKey> {
+    CommsPublicKey::from_bytes(bytes).map_err(|_| anyhow!("Invalid public key in message"))
+}
+
 pub fn peer_from_str(s: &str) -> Option<Peer> {
     use tari_crypto::tari_utilities::hex::Hex;
     let mut split = s.splitn(2, "::");
@@ -18,6 +18,17 @@ pub enum MessageType {
     PlayMove = 1,
     Resign = 2,
     Sync = 3,
+    SpectatorRequest = 4,
+    SpectatorResponse = 5,
+    SpectateGame = 6,
+    TournamentUpdate = 7,
+    TournamentResult = 8,
+    TakebackRequest = 9,
+    TakebackResponse = 10,
+    DrawOffer = 11,
+    DrawAccept = 12,
+    SpectateInvite = 13,
+    SpectateAccept = 14,
 }
 
 impl ProtoMessage {
@@ -42,6 +53,17 @@ impl TryFrom<i32> for MessageType {
             1 => Ok(MessageType::PlayMove),
             2 => Ok(MessageType::Resign),
             3 => Ok(MessageType::Sync),
+            4 => Ok(MessageType::SpectatorRequest),
+            5 => Ok(MessageType::SpectatorResponse),
+            6 => Ok(MessageType::SpectateGame),
+            7 => Ok(MessageType::TournamentUpdate),
+            8 => Ok(MessageType::TournamentResult),
+            9 => Ok(MessageType::TakebackRequest),
+            10 => Ok(MessageType::TakebackResponse),
+            11 => Ok(MessageType::DrawOffer),
+            12 => Ok(MessageType::DrawAccept),
+            13 => Ok(MessageType::SpectateInvite),
+            14 => Ok(MessageType::SpectateAccept),
             _ => Err(anyhow!("Invalid message type {}", value)),
         }
     }
@@ -88,6 +110,11 @@ impl<T: prost::Message + Default> TryFrom<ProtoMessage> for Message<T> {
 pub struct NewGameMsg {
     #[prost(uint32, tag = "1")]
     pub player: u32,
+    /// Empty unless paired by a tournament organiser
+    #[prost(bytes, tag = "2")]
+    pub opponent: Vec<u8>,
+    #[prost(uint32, tag = "3")]
+    pub tournament_id: u32,
 }
 
 #[derive(Clone, prost::Message)]
@@ -101,8 +128,72 @@ pub struct MoveMsg {
 #[derive(Clone, prost::Message)]
 pub struct ResignMsg;
 
+#[derive(Clone, prost::Message)]
+pub struct DrawOfferMsg;
+
+#[derive(Clone, prost::Message)]
+pub struct DrawAcceptMsg;
+
+#[derive(Clone, prost::Message)]
+pub struct SpectateInviteMsg;
+
+#[derive(Clone, prost::Message)]
+pub struct SpectateAcceptMsg;
+
 #[derive(Clone, prost::Message)]
 pub struct SyncMsg {
     #[prost(string, tag = "1")]
     pub board: String,
 }
+
+#[derive(Clone, prost::Message)]
+pub struct SpectatorRequestMsg {
+    #[prost(bytes, tag = "1")]
+    pub spectator: Vec<u8>,
+}
+
+#[derive(Clone, prost::Message)]
+pub struct SpectatorResponseMsg {
+    #[prost(bytes, tag = "1")]
+    pub spectator: Vec<u8>,
+    #[prost(bool, tag = "2")]
+    pub accepted: bool,
+}
+
+#[derive(Clone, prost::Message)]
+pub struct SpectateGameMsg {
+    #[prost(bytes, tag = "1")]
+    pub white: Vec<u8>,
+    #[prost(bytes, tag = "2")]
+    pub black: Vec<u8>,
+    #[prost(string, tag = "3")]
+    pub board: String,
+}
+
+#[derive(Clone, prost::Message)]
+pub struct TournamentUpdateMsg {
+    #[prost(string, tag = "1")]
+    pub state: String,
+}
+
+#[derive(Clone, prost::Message)]
+pub struct TournamentResultMsg {
+    #[prost(uint32, tag = "1")]
+    pub tournament_id: u32,
+    #[prost(uint32, tag = "2")]
+    pub outcome: u32,
+}
+
+#[derive(Clone, prost::Message)]
+pub struct TakebackRequestMsg {
+    #[prost(uint32, tag = "1")]
+    pub plies: u32,
+}
+
+#[derive(Clone, prost::Message)]
+pub struct TakebackResponseMsg {
+    #[prost(uint32, tag = "1")]
+    pub plies: u32,
+    #[prost(bool, tag = "2")]
+    pub accepted: bool,
+}
@@ -1,46 +1,63 @@
 use std::mem::transmute;
 
-use tanton::{core::CastleType, BitMove, Board, File, Piece, Player, Rank, SQ};
+use tanton::{core::CastleType, BitMove, Board, File, Piece, PieceType, Player, Rank, SQ};
 
 use crate::{
     bitmap::Bitmap,
     color::Color,
-    drawable::{Drawable, FrameBuffer},
+    drawable::{DirtyRegion, Drawable, FrameBuffer, Widget},
+    font::{DEFAULT_SIZE, FONT},
+    game::GameResult,
     rect::{Frame, Rect},
-    sprite::SpriteSheet,
+    sprite::{ScaleFilter, SpriteSheet},
+    theme::BoardColours,
 };
 
 pub const INITIAL_BOARD: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+/// Alpha of the faded copy left on the square of a piece being dragged
+const TAKEN_PIECE_ALPHA: u8 = 0x60;
 
 #[derive(Debug)]
 pub struct ChessBoard {
     frame: Frame,
-    light_colour: Color,
-    dark_colour: Color,
+    colours: BoardColours,
     board: Board,
     sprite_sheet: SpriteSheet<&'static str, Bitmap>,
     player: Player,
+    /// The side shown at the bottom of the board, initially the player's
+    orientation: Player,
     taken_piece: Option<(SQ, Piece)>,
     last_move: Option<BitMove>,
+    premove: Option<(SQ, SQ)>,
+    /// The square clicked on to move its piece with a second click
+    selected: Option<SQ>,
+    /// Allows moving the pieces of whichever side is to move, used when preparing conditional moves
+    is_any_side: bool,
+    show_move_hints: bool,
+    dirty: DirtyRegion,
 }
 
 impl ChessBoard {
     pub fn new(
         frame: Frame,
-        light_colour: Color,
-        dark_colour: Color,
+        colours: BoardColours,
         sprite_sheet: SpriteSheet<&'static str, Bitmap>,
         player: Player,
     ) -> Self {
         Self {
             frame,
-            light_colour,
-            dark_colour,
+            colours,
             board: Board::from_fen(INITIAL_BOARD).unwrap(),
             sprite_sheet,
             player,
+            orientation: player,
             taken_piece: None,
             last_move: None,
+            premove: None,
+            selected: None,
+            is_any_side: false,
+            show_move_hints: true,
+            dirty: DirtyRegion::new(frame),
         }
     }
 
@@ -52,55 +69,148 @@ impl ChessBoard {
         self.frame.h
     }
 
+    pub fn square_size(&self) -> u32 {
+        self.frame.w / 8
+    }
+
+    /// Moves and resizes the board, e.g. to follow the window size
+    pub fn set_frame(&mut self, frame: Frame) -> &mut Self {
+        if frame != self.frame {
+            self.dirty.add(self.frame);
+            self.frame = frame;
+            self.dirty.add(frame);
+        }
+        self
+    }
+
+    pub fn orientation(&self) -> Player {
+        self.orientation
+    }
+
+    /// Turns the board around so the other side is shown at the bottom
+    pub fn flip(&mut self) -> &mut Self {
+        self.orientation = self.orientation.other_player();
+        self.dirty.add(self.frame);
+        self
+    }
+
     pub fn take_piece_at(&mut self, x: u32, y: u32) -> Option<tanton::Piece> {
         let sq = self.coords_to_sq(x, y)?;
         match self.board.piece_at_sq(sq) {
             Piece::None => None,
-            p => {
-                // Only can take your piece
-                if p.player().filter(|p| *p == self.player).is_some() {
-                    self.taken_piece = Some((sq, p));
-                    Some(p)
-                } else {
-                    None
-                }
+            // Only can take your piece
+            p if self.is_movable_piece(sq) => {
+                self.taken_piece = Some((sq, p));
+                self.dirty.add(self.frame);
+                Some(p)
             },
+            _ => None,
+        }
+    }
+
+    /// Returns true if the piece on `sq` belongs to the side we are moving for
+    pub fn is_movable_piece(&self, sq: SQ) -> bool {
+        let side = if self.is_any_side {
+            self.board.turn()
+        } else {
+            self.player
+        };
+        self.board.piece_at_sq(sq).player() == Some(side)
+    }
+
+    pub fn select(&mut self, sq: Option<SQ>) -> &mut Self {
+        if sq != self.selected {
+            self.selected = sq;
+            self.dirty.add(self.frame);
         }
+        self
+    }
+
+    pub fn selected(&self) -> Option<SQ> {
+        self.selected
     }
 
     pub fn return_taken_piece(&mut self) {
-        self.taken_piece = None;
+        if self.taken_piece.take().is_some() {
+            self.dirty.add(self.frame);
+        }
+    }
+
+    pub fn taken_square(&self) -> Option<SQ> {
+        self.taken_piece.map(|(sq, _)| sq)
     }
 
     pub fn get_move_to(&mut self, dest: SQ) -> Option<BitMove> {
-        if let Some((src, _)) = self.taken_piece {
-            let all_moves = self.board.generate_moves();
-            if let Some(mv) = all_moves.iter().find(|m| m.get_src() == src && m.get_dest() == dest) {
-                return Some(*mv);
-            } else {
-                if let Some(castle) = self.castle_move(src, dest) {
-                    return Some(castle);
-                }
-            }
+        let (src, _) = self.taken_piece?;
+        self.legal_move(src, dest)
+    }
+
+    /// Returns the legal move from `src` to `dest` for the side to move, if any
+    pub fn legal_move(&self, src: SQ, dest: SQ) -> Option<BitMove> {
+        let all_moves = self.board.generate_moves();
+        if let Some(mv) = all_moves.iter().find(|m| m.get_src() == src && m.get_dest() == dest) {
+            return Some(*mv);
         }
-        None
+        self.castle_move(src, dest)
+    }
+
+    /// Parses a move typed in coordinate ("e2e4", "e7e8n") or algebraic ("Nf3", "exd5", "O-O", "e8=N") notation into
+    /// its source and destination squares and the piece a pawn promotes to, if one is given. Algebraic moves are only
+    /// recognised if they are legal.
+    pub fn parse_move(&self, input: &str) -> Option<(SQ, SQ, Option<PieceType>)> {
+        parse_typed_move(&self.board, input)
+    }
+
+    pub fn is_our_turn(&self) -> bool {
+        self.board.turn() == self.player
+    }
+
+    pub fn set_any_side(&mut self, is_any_side: bool) -> &mut Self {
+        self.is_any_side = is_any_side;
+        self
+    }
+
+    pub fn set_theme(&mut self, colours: BoardColours, sprite_sheet: SpriteSheet<&'static str, Bitmap>) -> &mut Self {
+        self.colours = colours;
+        self.sprite_sheet = sprite_sheet;
+        self.dirty.add(self.frame);
+        self
+    }
+
+    /// Shows the legal destination squares of the piece that is picked up
+    pub fn set_show_move_hints(&mut self, show_move_hints: bool) -> &mut Self {
+        if show_move_hints != self.show_move_hints {
+            self.show_move_hints = show_move_hints;
+            self.dirty.add(self.frame);
+        }
+        self
+    }
+
+    pub fn set_premove(&mut self, premove: Option<(SQ, SQ)>) -> &mut Self {
+        if premove != self.premove {
+            self.premove = premove;
+            self.dirty.add(self.frame);
+        }
+        self
     }
 
     pub fn make_legal_move(&mut self, mv: BitMove) {
         self.board.apply_move(mv);
+        self.dirty.add(self.frame);
     }
 
     pub fn castle_move(&self, src: SQ, dest: SQ) -> Option<BitMove> {
         const CAPTURE: u16 = 1 << 13;
+        let player = self.board.turn();
         // TODO: I'm sure there's a compact way to do this
-        match self.player {
+        match player {
             Player::White => {
                 if src == SQ::E1 {
-                    if dest == SQ::C1 && self.board.can_castle(self.player, CastleType::QueenSide) {
+                    if dest == SQ::C1 && self.board.can_castle(player, CastleType::QueenSide) {
                         let rook = self.board.castling_rook_square(CastleType::QueenSide);
                         return Some(BitMove::make(BitMove::FLAG_QUEEN_CASTLE | CAPTURE, src, rook));
                     }
-                    if dest == SQ::G1 && self.board.can_castle(self.player, CastleType::KingSide) {
+                    if dest == SQ::G1 && self.board.can_castle(player, CastleType::KingSide) {
                         let rook = self.board.castling_rook_square(CastleType::KingSide);
                         return Some(BitMove::make(BitMove::FLAG_KING_CASTLE | CAPTURE, src, rook));
                     }
@@ -108,12 +218,12 @@ impl ChessBoard {
             },
             Player::Black => {
                 if src == SQ::E8 {
-                    if dest == SQ::C8 && self.board.can_castle(self.player, CastleType::QueenSide) {
+                    if dest == SQ::C8 && self.board.can_castle(player, CastleType::QueenSide) {
                         let rook = self.board.castling_rook_square(CastleType::QueenSide);
                         let mv = BitMove::make(BitMove::FLAG_QUEEN_CASTLE | CAPTURE, src, rook);
                         return Some(mv);
                     }
-                    if dest == SQ::G8 && self.board.can_castle(self.player, CastleType::KingSide) {
+                    if dest == SQ::G8 && self.board.can_castle(player, CastleType::KingSide) {
                         let rook = self.board.castling_rook_square(CastleType::KingSide);
                         return Some(BitMove::make(BitMove::FLAG_KING_CASTLE | CAPTURE, src, rook));
                     }
@@ -132,30 +242,72 @@ impl ChessBoard {
             for y in 0..8 {
                 let mut colour = if y % 2 == 0 {
                     if x % 2 == 0 {
-                        self.light_colour
+                        self.colours.light
                     } else {
-                        self.dark_colour
+                        self.colours.dark
                     }
                 } else {
                     if x % 2 == 0 {
-                        self.dark_colour
+                        self.colours.dark
                     } else {
-                        self.light_colour
+                        self.colours.light
                     }
                 };
-                let x = x * self.frame.w / 8;
-                let y = y * self.frame.h / 8;
+                let x = self.frame.x + x * self.square_size();
+                let y = self.frame.y + y * self.square_size();
 
-                if let Some(mv) = self.last_move {
-                    if let Some(sq) = self.coords_to_sq(x, y) {
+                if let Some(sq) = self.coords_to_sq(x, y) {
+                    if let Some(mv) = self.last_move {
                         if mv.get_src() == sq || mv.get_dest() == sq {
-                            colour = Color::yellow();
+                            colour = self.colours.last_move;
                         }
                     }
+                    if let Some((src, dest)) = self.premove {
+                        if src == sq || dest == sq {
+                            colour = self.colours.premove;
+                        }
+                    }
+                    if self.selected == Some(sq) {
+                        colour = self.colours.selected;
+                    }
+                    if self.board.in_check() && self.board.king_sq(self.board.turn()) == sq {
+                        colour = self.colours.check;
+                    }
                 }
 
-                Rect::new(x, y, self.frame.w / 8, self.frame.h / 8, colour).draw(buf);
+                Rect::new(x, y, self.square_size(), self.square_size(), colour).draw(buf);
+            }
+        }
+    }
+
+    /// Draws the files along the bottom edge and the ranks along the left edge, inside the edge squares in the colour
+    /// of the opposite squares
+    fn draw_coordinates(&self, buf: &mut FrameBuffer) {
+        let square_size = self.square_size();
+        let size = (square_size as f32 / 6.0).clamp(9.0, DEFAULT_SIZE);
+        let line_height = FONT.line_height(size);
+        // Squares are light where their column and row add up to an even number
+        let colour = |is_light: bool| {
+            if is_light {
+                self.colours.dark
+            } else {
+                self.colours.light
             }
+        };
+        for i in 0..8u32 {
+            let (file, rank) = if self.orientation == Player::White {
+                (i as u8, 7 - i as u8)
+            } else {
+                (7 - i as u8, i as u8)
+            };
+            let file = file_char(to_square(file, 0)).to_string();
+            let x = (self.frame.x + (i + 1) * square_size).saturating_sub(FONT.measure(&file, size) + 3);
+            let y = (self.frame.y + 8 * square_size).saturating_sub(line_height + 1);
+            FONT.draw_string(&file, x, y, size, colour((i + 7) % 2 == 0), buf);
+
+            let rank = rank_char(to_square(0, rank)).to_string();
+            let y = self.frame.y + i * square_size + 2;
+            FONT.draw_string(&rank, self.frame.x + 3, y, size, colour(i % 2 == 0), buf);
         }
     }
 
@@ -163,17 +315,60 @@ impl ChessBoard {
         let locations = self.board.get_piece_locations();
 
         for (sq, piece) in locations {
-            if self.taken_piece.map(|(taken_sq, _)| taken_sq == sq).unwrap_or(false) {
-                continue;
-            }
+            let alpha = if self.taken_square() == Some(sq) {
+                TAKEN_PIECE_ALPHA
+            } else {
+                0xff
+            };
             let (x, y) = self.sq_to_coords(sq);
-            self.draw_piece(piece, x, y, 0xff, buf);
+            self.draw_piece(piece, x, y, alpha, buf);
         }
     }
 
-    fn draw_piece(&self, piece: Piece, x: u32, y: u32, _: u8, buf: &mut FrameBuffer) {
+    /// Draws a dot on each empty square the picked up piece can move to and a ring around each piece it can capture
+    fn draw_move_hints(&self, buf: &mut FrameBuffer) {
+        let src = match self.taken_square().or(self.selected) {
+            Some(src) if self.show_move_hints => src,
+            _ => return,
+        };
+        let square_size = self.square_size();
+        let colour = Color::grey(0x60).to_rgba();
+        for mv in self.board.generate_moves().iter().filter(|m| m.get_src() == src) {
+            let dest = if mv.is_castle() {
+                // Castling moves are encoded as the king capturing its own rook
+                let file = if mv.is_king_castle() { File::G } else { File::C };
+                to_square(file as u8, src.rank() as u8)
+            } else {
+                mv.get_dest()
+            };
+            let (x, y) = self.sq_to_coords(dest);
+            let (cx, cy) = (x as i32 + square_size as i32 / 2, y as i32 + square_size as i32 / 2);
+            let (inner, outer) = if mv.is_capture() && !mv.is_castle() {
+                let outer = square_size as i32 / 2 - 1;
+                (outer - 5, outer)
+            } else {
+                (0, square_size as i32 / 7)
+            };
+            for py in (cy - outer)..=(cy + outer) {
+                for px in (cx - outer)..=(cx + outer) {
+                    let dist = (px - cx).pow(2) + (py - cy).pow(2);
+                    if dist <= outer.pow(2) && dist >= inner.pow(2) && px >= 0 && py >= 0 {
+                        buf.put_pixel(px as u32, py as u32, colour);
+                    }
+                }
+            }
+        }
+    }
+
+    fn draw_piece(&self, piece: Piece, x: u32, y: u32, alpha: u8, buf: &mut FrameBuffer) {
         let name = piece_to_sprite_name(piece);
-        self.sprite_sheet.get_sprite_drawable(&name, x, y).unwrap().draw(buf);
+        let size = self.square_size();
+        self.sprite_sheet
+            .get_sprite_drawable(&name, x, y)
+            .unwrap()
+            .with_scale(size, size, ScaleFilter::Bilinear)
+            .with_alpha(alpha)
+            .draw(buf);
     }
 
     pub fn draw_taken_piece(&self, x: u32, y: u32, buf: &mut FrameBuffer) {
@@ -200,56 +395,71 @@ impl ChessBoard {
     }
 
     fn sq_to_coords(&self, sq: SQ) -> (u32, u32) {
-        let x = if self.player == Player::White {
+        let x = if self.orientation == Player::White {
             sq.file() as u32
         } else {
             File::H as u32 - sq.file() as u32
         };
-        let y = if self.player == Player::White {
+        let y = if self.orientation == Player::White {
             Rank::R8 as u32 - sq.rank() as u32
         } else {
             sq.rank() as u32
         };
-        (x * 90, y * 90)
+        (
+            self.frame.x + x * self.square_size(),
+            self.frame.y + y * self.square_size(),
+        )
     }
 
-    fn coords_to_sq(&self, mut x: u32, mut y: u32) -> Option<SQ> {
-        x /= 90;
-        y /= 90;
+    fn coords_to_sq(&self, x: u32, y: u32) -> Option<SQ> {
+        let square_size = self.square_size().max(1);
+        let x = x.checked_sub(self.frame.x)? / square_size;
+        let y = y.checked_sub(self.frame.y)? / square_size;
         if x > 7 || y > 7 {
             return None;
         }
 
-        let file = if self.player == Player::White {
+        let file = if self.orientation == Player::White {
             x as u8
         } else {
             File::H as u8 - x as u8
         };
-        let rank = if self.player == Player::White {
+        let rank = if self.orientation == Player::White {
             Rank::R8 as u8 - y as u8
         } else {
             y as u8
         };
-        let sq = unsafe { transmute::<u8, SQ>(file + (rank << 3)) };
-        Some(sq)
+        Some(to_square(file, rank))
     }
 
     pub fn apply_move(&mut self, mv: BitMove) {
-        self.board.apply_move(mv)
+        self.board.apply_move(mv);
+        self.dirty.add(self.frame);
     }
 
     pub fn set_board_state(&mut self, fen: &str) -> &mut Self {
         if let Ok(b) = Board::from_fen(fen) {
             self.board = b;
+            self.dirty.add(self.frame);
         }
+        // The selected piece may have been captured
+        self.selected = self.selected.filter(|sq| self.is_movable_piece(*sq));
         self
     }
 
-    pub fn set_last_move(&mut self, mv: BitMove) -> &mut Self {
-        self.last_move = Some(mv);
+    pub fn set_last_move<T: Into<Option<BitMove>>>(&mut self, mv: T) -> &mut Self {
+        let mv = mv.into();
+        if mv.map(|m| m.get_raw()) != self.last_move.map(|m| m.get_raw()) {
+            self.last_move = mv;
+            self.dirty.add(self.frame);
+        }
         self
     }
 
+    pub fn last_move(&self) -> Option<BitMove> {
+        self.last_move
+    }
+
     pub fn to_fen(&self) -> String {
         self.board.fen()
     }
@@ -257,11 +467,229 @@ impl ChessBoard {
 
 impl Drawable for ChessBoard {
     fn draw(&mut self, buf: &mut FrameBuffer) {
+        if !buf.is_damaged(&self.frame) {
+            return;
+        }
+        buf.push_clip(self.frame);
         self.draw_squares(buf);
+        self.draw_coordinates(buf);
         self.draw_pieces(buf);
+        self.draw_move_hints(buf);
+        buf.pop_clip();
+    }
+}
+
+impl Widget for ChessBoard {
+    fn frame(&self) -> Frame {
+        self.frame
+    }
+
+    fn take_dirty(&mut self) -> Option<Frame> {
+        self.dirty.take()
+    }
+}
+
+/// Returns the result of the game for the given position, or `GameResult::None` if the game is still in progress
+pub fn game_result(fen: &str) -> GameResult {
+    match Board::from_fen(fen) {
+        Ok(board) if board.checkmate() => GameResult::Checkmate(board.turn().other_player()),
+        // Stalemate or 2 kings remaining
+        Ok(board) if board.stalemate() || board.count_all_pieces() <= 2 => GameResult::Draw,
+        _ => GameResult::None,
+    }
+}
+
+/// Returns true if both raw moves have the same source, destination and promotion
+pub fn is_same_move(a: u16, b: u16) -> bool {
+    let (a, b) = (BitMove::new(a), BitMove::new(b));
+    a.get_src() == b.get_src() &&
+        a.get_dest() == b.get_dest() &&
+        a.is_promo() == b.is_promo() &&
+        (!a.is_promo() || a.promo_piece() == b.promo_piece())
+}
+
+/// Plays `mv` if it is legal in the given position, returning the move as generated and the resulting position
+pub fn play_move(fen: &str, mv: BitMove) -> Option<(BitMove, String)> {
+    let mut board = Board::from_fen(fen).ok()?;
+    let legal_move = board
+        .generate_moves()
+        .iter()
+        .copied()
+        .find(|m| is_same_move(m.get_raw(), mv.get_raw()))?;
+    board.apply_move(legal_move);
+    Some((legal_move, board.fen()))
+}
+
+/// Returns the position after playing `moves` from `start_fen`, or None if any move is not legal
+pub fn replay(start_fen: &str, moves: &[u16]) -> Option<String> {
+    let mut fen = start_fen.to_string();
+    for mv in moves {
+        fen = play_move(&fen, BitMove::new(*mv))?.1;
+    }
+    Some(fen)
+}
+
+/// Returns the moves in standard algebraic notation, stopping at the first move that is not legal
+pub fn san_moves(start_fen: &str, moves: &[u16]) -> Vec<String> {
+    let mut board = match Board::from_fen(start_fen) {
+        Ok(board) => board,
+        Err(_) => return Vec::new(),
+    };
+    let mut sans = Vec::with_capacity(moves.len());
+    for mv in moves {
+        let legal_moves = board.generate_moves();
+        let mv = match legal_moves.iter().find(|m| is_same_move(m.get_raw(), *mv)) {
+            Some(mv) => *mv,
+            None => break,
+        };
+        let mut san = to_san(&board, &legal_moves, mv);
+        board.apply_move(mv);
+        if board.checkmate() {
+            san.push('#');
+        } else if board.in_check() {
+            san.push('+');
+        }
+        sans.push(san);
+    }
+    sans
+}
+
+/// Returns the full move number and side to move of a FEN position
+pub fn move_number(fen: &str) -> (u32, Player) {
+    let mut fields = fen.split_whitespace().skip(1);
+    let player = match fields.next() {
+        Some("b") => Player::Black,
+        _ => Player::White,
+    };
+    let number = fields.nth(3).and_then(|n| n.parse().ok()).unwrap_or(1);
+    (number, player)
+}
+
+fn to_san(board: &Board, legal_moves: &[BitMove], mv: BitMove) -> String {
+    if mv.is_king_castle() {
+        return "O-O".to_string();
+    }
+    if mv.is_queen_castle() {
+        return "O-O-O".to_string();
+    }
+
+    let (src, dest) = (mv.get_src(), mv.get_dest());
+    let piece = board.piece_at_sq(src).type_of();
+    let mut san = String::new();
+    match piece_letter(piece) {
+        Some(letter) => {
+            san.push(letter);
+            // Disambiguate between pieces of the same type that can move to the same square
+            let others = legal_moves
+                .iter()
+                .map(|m| m.get_src())
+                .filter(|sq| *sq != src && board.piece_at_sq(*sq).type_of() == piece)
+                .filter(|sq| legal_moves.iter().any(|m| m.get_src() == *sq && m.get_dest() == dest))
+                .collect::<Vec<_>>();
+            if !others.is_empty() {
+                if others.iter().all(|sq| sq.file() != src.file()) {
+                    san.push(file_char(src));
+                } else if others.iter().all(|sq| sq.rank() != src.rank()) {
+                    san.push(rank_char(src));
+                } else {
+                    san.push(file_char(src));
+                    san.push(rank_char(src));
+                }
+            }
+        },
+        None => {
+            if mv.is_capture() {
+                san.push(file_char(src));
+            }
+        },
+    }
+    if mv.is_capture() {
+        san.push('x');
+    }
+    san.push(file_char(dest));
+    san.push(rank_char(dest));
+    if mv.is_promo() {
+        san.push('=');
+        san.push(piece_letter(mv.promo_piece()).unwrap_or('Q'));
+    }
+    san
+}
+
+fn parse_typed_move(board: &Board, input: &str) -> Option<(SQ, SQ, Option<PieceType>)> {
+    let input = input.trim();
+    if let [f1 @ b'a'..=b'h', r1 @ b'1'..=b'8', f2 @ b'a'..=b'h', r2 @ b'1'..=b'8', rest @ ..] = input.as_bytes() {
+        let (src, dest) = (to_square(f1 - b'a', r1 - b'1'), to_square(f2 - b'a', r2 - b'1'));
+        match rest {
+            [] => return Some((src, dest, None)),
+            // Anything but a promotion piece after the squares is not a move
+            [piece] => return promotion_piece(*piece).map(|piece| (src, dest, Some(piece))),
+            _ => {},
+        }
+    }
+
+    let input = normalise_san(input);
+    let legal_moves = board.generate_moves().iter().copied().collect::<Vec<_>>();
+    legal_moves
+        .iter()
+        .find(|mv| normalise_san(&to_san(board, &legal_moves, **mv)) == input)
+        .map(|mv| (mv.get_src(), mv.get_dest(), mv.is_promo().then(|| mv.promo_piece())))
+}
+
+/// The piece for a promotion letter in either case
+fn promotion_piece(letter: u8) -> Option<PieceType> {
+    match letter.to_ascii_lowercase() {
+        b'q' => Some(PieceType::Q),
+        b'r' => Some(PieceType::R),
+        b'b' => Some(PieceType::B),
+        b'n' => Some(PieceType::N),
+        _ => None,
     }
 }
 
+/// Strips the parts of a SAN move that players commonly leave out or type differently
+fn normalise_san(san: &str) -> String {
+    let mut san = san
+        .chars()
+        .filter(|c| !matches!(c, '+' | '#' | 'x' | '=' | '-' | ' ' | '!' | '?'))
+        .map(|c| match c {
+            'o' | '0' => 'O',
+            c => c,
+        })
+        .collect::<String>();
+    // Allow a lower case promotion piece, e.g. "e8q"
+    if let Some(promo) = san.pop() {
+        if matches!(promo, 'q' | 'r' | 'b' | 'n') && san.ends_with(|c: char| c.is_ascii_digit()) {
+            san.push(promo.to_ascii_uppercase());
+        } else {
+            san.push(promo);
+        }
+    }
+    san
+}
+
+fn to_square(file: u8, rank: u8) -> SQ {
+    unsafe { transmute::<u8, SQ>(file + (rank << 3)) }
+}
+
+fn piece_letter(piece: PieceType) -> Option<char> {
+    match piece {
+        PieceType::N => Some('N'),
+        PieceType::B => Some('B'),
+        PieceType::R => Some('R'),
+        PieceType::Q => Some('Q'),
+        PieceType::K => Some('K'),
+        _ => None,
+    }
+}
+
+fn file_char(sq: SQ) -> char {
+    (b'a' + sq.file() as u8) as char
+}
+
+fn rank_char(sq: SQ) -> char {
+    (b'1' + sq.rank() as u8) as char
+}
+
 fn piece_to_sprite_name(piece: tanton::Piece) -> &'static str {
     use tanton::Piece::*;
     match piece {
@@ -299,3 +727,107 @@ fn piece_to_sprite_name(piece: tanton::Piece) -> &'static str {
 //         BlackKing => WhiteKing,
 //     }
 // }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Finds the legal moves given in coordinate notation, e.g. "e2e4", "e7e8q", or "e1g1" for castling
+    fn raw_moves(fen: &str, moves: &[&str]) -> Vec<u16> {
+        let mut board = Board::from_fen(fen).unwrap();
+        moves
+            .iter()
+            .map(|input| {
+                let b = input.as_bytes();
+                let src = to_square(b[0] - b'a', b[1] - b'1');
+                let dest = to_square(b[2] - b'a', b[3] - b'1');
+                let promo = b.get(4).map(|c| c.to_ascii_uppercase() as char);
+                let mv = board
+                    .generate_moves()
+                    .iter()
+                    .copied()
+                    .find(|m| {
+                        // Castles are encoded as the king taking its own rook
+                        let is_dest = match (m.is_king_castle(), m.is_queen_castle()) {
+                            (true, _) => file_char(dest) == 'g',
+                            (_, true) => file_char(dest) == 'c',
+                            _ => m.get_dest() == dest,
+                        };
+                        let is_promo = match promo {
+                            Some(promo) => m.is_promo() && piece_letter(m.promo_piece()) == Some(promo),
+                            None => !m.is_promo(),
+                        };
+                        m.get_src() == src && is_dest && is_promo
+                    })
+                    .unwrap_or_else(|| panic!("{} is not legal", input));
+                board.apply_move(mv);
+                mv.get_raw()
+            })
+            .collect()
+    }
+
+    fn sans(fen: &str, moves: &[&str]) -> Vec<String> {
+        san_moves(fen, &raw_moves(fen, moves))
+    }
+
+    #[test]
+    fn san_moves_pawn_and_piece_moves() {
+        assert_eq!(sans(INITIAL_BOARD, &["e2e4", "d7d5", "e4d5", "g8f6"]), vec![
+            "e4", "d5", "exd5", "Nf6"
+        ]);
+    }
+
+    #[test]
+    fn san_moves_disambiguates() {
+        // Knights on different files
+        assert_eq!(sans("4k3/8/8/8/8/8/8/1N1K1N2 w - - 0 1", &["b1d2"]), vec!["Nbd2"]);
+        // Knights on the same file
+        assert_eq!(sans("4k3/8/8/8/8/1N6/8/1N1K4 w - - 0 1", &["b1d2"]), vec!["N1d2"]);
+        // Queens sharing a file with one and a rank with another
+        assert_eq!(sans("7K/8/8/7k/8/Q7/8/Q1Q5 w - - 0 1", &["a1b2"]), vec!["Qa1b2"]);
+    }
+
+    #[test]
+    fn san_moves_castling() {
+        assert_eq!(sans("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1", &["e1g1", "e8c8"]), vec![
+            "O-O", "O-O-O"
+        ]);
+    }
+
+    #[test]
+    fn parses_typed_promotions() {
+        let board = Board::from_fen("k7/4P3/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        assert_eq!(
+            parse_typed_move(&board, "e7e8n"),
+            Some((SQ::E7, SQ::E8, Some(PieceType::N)))
+        );
+        assert_eq!(
+            parse_typed_move(&board, "e7e8R"),
+            Some((SQ::E7, SQ::E8, Some(PieceType::R)))
+        );
+        assert_eq!(parse_typed_move(&board, "e7e8"), Some((SQ::E7, SQ::E8, None)));
+        assert_eq!(parse_typed_move(&board, "e7e8k"), None);
+        assert_eq!(
+            parse_typed_move(&board, "e8=B"),
+            Some((SQ::E7, SQ::E8, Some(PieceType::B)))
+        );
+        assert_eq!(
+            parse_typed_move(&board, "e8n"),
+            Some((SQ::E7, SQ::E8, Some(PieceType::N)))
+        );
+    }
+
+    #[test]
+    fn san_moves_promotion() {
+        assert_eq!(sans("8/P6k/8/8/8/8/8/K7 w - - 0 1", &["a7a8q"]), vec!["a8=Q"]);
+        assert_eq!(sans("1n6/P2k4/8/8/8/8/8/K7 w - - 0 1", &["a7b8n"]), vec!["axb8=N+"]);
+    }
+
+    #[test]
+    fn san_moves_check_and_checkmate() {
+        assert_eq!(sans(INITIAL_BOARD, &["e2e4", "f7f6", "d1h5"]), vec!["e4", "f6", "Qh5+"]);
+        assert_eq!(sans(INITIAL_BOARD, &["f2f3", "e7e5", "g2g4", "d8h4"]), vec![
+            "f3", "e5", "g4", "Qh4#"
+        ]);
+    }
+}
@@ -36,6 +36,24 @@ impl Color {
         self.a = v;
         self
     }
+
+    /// A copy of this colour with alpha `v`, for use in expressions
+    pub fn with_alpha(mut self, v: u8) -> Self {
+        self.set_alpha(v);
+        self
+    }
+
+    /// Composites this colour over `dst` using this colour's alpha (source-over)
+    pub fn over(&self, dst: Color) -> Color {
+        let src_a = u32::from(self.a);
+        let dst_a = u32::from(dst.a) * (255 - src_a) / 255;
+        let out_a = src_a + dst_a;
+        if out_a == 0 {
+            return Color::new(0, 0, 0, 0);
+        }
+        let mix = |src: u8, dst: u8| ((u32::from(src) * src_a + u32::from(dst) * dst_a + out_a / 2) / out_a) as u8;
+        Color::new(mix(self.r, dst.r), mix(self.g, dst.g), mix(self.b, dst.b), out_a as u8)
+    }
 }
 
 impl Color {
@@ -139,4 +157,13 @@ mod test {
         let c1 = Color::from_rgba(c.to_rgba());
         assert_eq!(c1, c);
     }
+
+    #[test]
+    fn it_composites_source_over() {
+        let white = Color::new(0xff, 0xff, 0xff, 0xff);
+        assert_eq!(Color::red().over(white), Color::red());
+        assert_eq!(Color::red().with_alpha(0).over(white), white);
+        let grey = Color::black().with_alpha(0x80).over(white);
+        assert_eq!((grey.r, grey.g, grey.b, grey.a), (0x7f, 0x7f, 0x7f, 0xff));
+    }
 }
@@ -5,15 +5,21 @@ use minifb::{MouseButton, MouseMode, Window};
 use crate::{
     color::Color,
     components::handler::ClickHandler,
-    drawable::{Drawable, FrameBuffer},
-    letters::LETTERS,
-    rect::Rect,
+    drawable::{DirtyRegion, Drawable, FrameBuffer, Widget},
+    focus::Focusable,
+    font::{DEFAULT_SIZE, FONT},
+    rect::{Frame, Rect},
 };
 
 pub struct Button {
     rect: Rect,
     text: String,
     is_disabled: bool,
+    is_visible: bool,
+    is_focused: bool,
+    dirty: DirtyRegion,
+    /// Clicks are counted when the mouse is pressed, not for every frame it is held
+    was_mouse_down: bool,
     click: Option<()>,
     on_click: Option<Box<dyn ClickHandler>>,
 }
@@ -21,16 +27,48 @@ pub struct Button {
 impl Button {
     pub fn new(rect: Rect) -> Self {
         Self {
+            dirty: DirtyRegion::new(rect.frame()),
             rect,
             text: "Button".to_string(),
             is_disabled: false,
+            is_visible: true,
+            is_focused: false,
+            was_mouse_down: true,
             click: None,
             on_click: None,
         }
     }
 
     pub fn set_text<T: Into<String>>(&mut self, text: T) -> &mut Self {
-        self.text = text.into();
+        let text = text.into();
+        if text != self.text {
+            self.text = text;
+            self.dirty.add(self.rect.frame());
+        }
+        self
+    }
+
+    pub fn set_bg_color(&mut self, color: Color) -> &mut Self {
+        self.rect.set_bg_colour(color);
+        self.dirty.add(self.rect.frame());
+        self
+    }
+
+    pub fn set_position(&mut self, x: u32, y: u32) -> &mut Self {
+        if (x, y) != (self.rect.x(), self.rect.y()) {
+            self.dirty.add(self.rect.frame());
+            self.rect.set_position(x, y);
+            self.dirty.add(self.rect.frame());
+        }
+        self
+    }
+
+    pub fn set_frame(&mut self, frame: Frame) -> &mut Self {
+        if frame != self.rect.frame() {
+            self.dirty.add(self.rect.frame());
+            self.rect.set_frame(frame);
+            self.dirty.add(frame);
+        }
         self
     }
 
@@ -39,6 +77,15 @@ impl Button {
         self
     }
 
+    /// Hidden buttons are not drawn and can't be clicked
+    pub fn set_visible(&mut self, is_visible: bool) -> &mut Self {
+        if is_visible != self.is_visible {
+            self.is_visible = is_visible;
+            self.dirty.add(self.rect.frame());
+        }
+        self
+    }
+
     pub fn on_click<F: ClickHandler + 'static>(&mut self, handler: F) -> &mut Self {
         self.on_click = Some(Box::new(handler));
         self
@@ -49,34 +96,81 @@ impl Button {
     }
 
     pub fn update(&mut self, window: &Window) {
-        if self.is_disabled {
+        let is_mouse_down = window.get_mouse_down(MouseButton::Left);
+        let is_press = is_mouse_down && !self.was_mouse_down;
+        self.was_mouse_down = is_mouse_down;
+        if self.is_disabled || !self.is_visible {
             return;
         }
 
-        if window.get_mouse_down(MouseButton::Left) {
+        if is_press {
             if let Some((x, y)) = window.get_mouse_pos(MouseMode::Discard) {
                 if self.rect.is_in_boundary(x.round() as u32, y.round() as u32) {
-                    self.click = Some(());
-                    if let Some(ref mut handler) = self.on_click {
-                        handler.handle_click();
-                    }
+                    self.click();
                 }
             }
         }
     }
 
+    fn click(&mut self) {
+        self.click = Some(());
+        if let Some(ref mut handler) = self.on_click {
+            handler.handle_click();
+        }
+    }
+
     fn draw_text(&self, buf: &mut FrameBuffer) {
-        let half_text_w = self.text.len() as u32 * 11 / 2;
-        let x = self.rect.x() + (self.rect.w() / 2) - half_text_w;
-        let y = self.rect.y() + (self.rect.h() / 2) - 8;
-        LETTERS.draw_string(&self.text, x, y, Color::black(), buf);
+        let text_w = FONT.measure(&self.text, DEFAULT_SIZE);
+        let x = self.rect.x() + self.rect.w().saturating_sub(text_w) / 2;
+        let y = self.rect.y() + self.rect.h().saturating_sub(FONT.line_height(DEFAULT_SIZE)) / 2;
+        FONT.draw_string(&self.text, x, y, DEFAULT_SIZE, Color::black(), buf);
     }
 }
 
 impl Drawable for Button {
     fn draw(&mut self, buf: &mut FrameBuffer) {
+        if !self.is_visible || !buf.is_damaged(&self.rect.frame()) {
+            return;
+        }
         self.rect.draw(buf);
+        if self.is_focused {
+            self.rect.frame().draw_border(2, Color::dark_blue(), buf);
+        }
+        buf.push_clip(self.rect.frame());
         self.draw_text(buf);
+        buf.pop_clip();
+    }
+}
+
+impl Widget for Button {
+    fn frame(&self) -> Frame {
+        self.rect.frame()
+    }
+
+    fn take_dirty(&mut self) -> Option<Frame> {
+        self.dirty.take()
+    }
+}
+
+impl Focusable for Button {
+    fn is_focused(&self) -> bool {
+        self.is_focused
+    }
+
+    fn set_focused(&mut self, is_focused: bool) {
+        if is_focused != self.is_focused {
+            self.is_focused = is_focused;
+            self.dirty.add(self.rect.frame());
+        }
+    }
+
+    /// Clicks the button
+    fn activate(&mut self) {
+        self.click();
+    }
+
+    fn can_focus(&self) -> bool {
+        self.is_visible && !self.is_disabled
     }
 }
 
@@ -0,0 +1,325 @@
+use minifb::{Key, KeyRepeat, MouseButton, MouseMode, Window};
+
+use crate::{
+    clipboard::Clipboard,
+    color::Color,
+    components::TextBox,
+    drawable::{DirtyRegion, Drawable, FrameBuffer, Widget},
+    font::{DEFAULT_SIZE, FONT},
+    layout::{Anchor, Padding, Stack},
+    rect::{Frame, Rect},
+};
+
+const PALETTE_WIDTH: u32 = 500;
+const PALETTE_TOP: u32 = 60;
+const INPUT_HEIGHT: u32 = 30;
+const ROW_HEIGHT: u32 = 28;
+const MAX_ROWS: usize = 8;
+const PADDING: u32 = 10;
+
+#[derive(Debug, Clone)]
+pub struct PaletteEntry<T> {
+    pub title: String,
+    /// Shown on the right of the title, e.g. the command's shortcut
+    pub detail: String,
+    pub value: T,
+}
+
+/// A search box over a list of entries, shown near the top of the window over a dimmed screen. Typing filters the
+/// entries with a fuzzy match, Up and Down move the selection, Enter or a click chooses it and Escape or a click
+/// outside closes the palette.
+#[derive(Debug)]
+pub struct CommandPalette<T> {
+    entries: Vec<PaletteEntry<T>>,
+    /// Indices of the entries matching the query, best match first
+    matches: Vec<usize>,
+    /// Index into `matches`
+    selected: usize,
+    /// Index of the first match shown
+    scroll: usize,
+    query: String,
+    input: TextBox,
+    rect: Rect,
+    /// The row the mouse was pressed on, or None if it was pressed outside the palette. The row is chosen when the
+    /// mouse is released so the click doesn't also reach the screen beneath.
+    pressed: Option<Option<usize>>,
+    chosen: Option<T>,
+    is_closed: bool,
+    is_backdrop_drawn: bool,
+    dirty: DirtyRegion,
+}
+
+impl<T: Clone> CommandPalette<T> {
+    pub fn new(entries: Vec<PaletteEntry<T>>, clipboard: Clipboard) -> Self {
+        let mut input = TextBox::new(Frame::default(), clipboard);
+        input.set_placeholder("Search commands and games").set_active(true);
+        let mut palette = Self {
+            entries,
+            matches: Vec::new(),
+            selected: 0,
+            scroll: 0,
+            query: String::new(),
+            input,
+            rect: {
+                let mut r = Rect::from_frame(Frame::default(), Color::grey(0x30));
+                r.set_border(2, Color::light_grey());
+                r
+            },
+            pressed: None,
+            chosen: None,
+            is_closed: false,
+            is_backdrop_drawn: false,
+            dirty: DirtyRegion::default(),
+        };
+        palette.filter();
+        palette
+    }
+
+    /// True once an entry was chosen or the palette was dismissed
+    pub fn is_closed(&self) -> bool {
+        self.is_closed
+    }
+
+    /// The value of the chosen entry
+    pub fn take_chosen(&mut self) -> Option<T> {
+        self.chosen.take()
+    }
+
+    /// Centres the palette horizontally near the top of the window
+    pub fn set_window_size(&mut self, window_width: u32, window_height: u32) -> &mut Self {
+        let height = PADDING * 3 + INPUT_HEIGHT + ROW_HEIGHT * MAX_ROWS as u32;
+        let outer = Frame::new(0, PALETTE_TOP, window_width, window_height.saturating_sub(PALETTE_TOP));
+        let frame = Anchor::Top.place(outer, PALETTE_WIDTH, height);
+        if frame == self.rect.frame() {
+            return self;
+        }
+        self.rect.set_frame(frame);
+        self.dirty.add(frame);
+        let mut column = Stack::column(Padding::all(PADDING).apply(frame));
+        self.input.set_frame(column.next(INPUT_HEIGHT));
+        // The screen beneath is redrawn in full after a resize
+        self.is_backdrop_drawn = false;
+        self
+    }
+
+    pub fn update(&mut self, window: &Window) {
+        self.input.update(window);
+        if self.input.value() != self.query {
+            self.query = self.input.value().to_string();
+            self.filter();
+        }
+
+        let count = self.matches.len();
+        for key in window.get_keys_pressed(KeyRepeat::Yes).into_iter().flatten() {
+            match key {
+                Key::Up if count > 0 => self.select((self.selected + count - 1) % count),
+                Key::Down if count > 0 => self.select((self.selected + 1) % count),
+                Key::PageUp => self.select(self.selected.saturating_sub(MAX_ROWS)),
+                Key::PageDown if count > 0 => self.select((self.selected + MAX_ROWS).min(count - 1)),
+                _ => {},
+            }
+        }
+        if window.is_key_pressed(Key::Enter, KeyRepeat::No) {
+            self.choose(self.selected);
+        }
+        if window.is_key_pressed(Key::Escape, KeyRepeat::No) {
+            self.is_closed = true;
+        }
+
+        let mouse_pos = window
+            .get_mouse_pos(MouseMode::Discard)
+            .map(|(x, y)| (x.round() as u32, y.round() as u32));
+        if window.get_mouse_down(MouseButton::Left) {
+            if self.pressed.is_none() {
+                self.pressed = mouse_pos.map(|(x, y)| self.row_at(x, y)).or(Some(None));
+                if let Some(Some(row)) = self.pressed {
+                    self.select(row);
+                }
+            }
+        } else if let Some(pressed) = self.pressed.take() {
+            match pressed {
+                Some(row) => self.choose(row),
+                None if !mouse_pos.map(|(x, y)| self.rect.is_in_boundary(x, y)).unwrap_or(false) => {
+                    self.is_closed = true
+                },
+                None => {},
+            }
+        }
+    }
+
+    /// Matches the entries against the query, keeping the entries' order for equal scores
+    fn filter(&mut self) {
+        let mut scored = self
+            .entries
+            .iter()
+            .enumerate()
+            .filter_map(|(i, entry)| fuzzy_score(&self.query, &entry.title).map(|score| (i, score)))
+            .collect::<Vec<_>>();
+        scored.sort_by_key(|(_, score)| -score);
+        self.matches = scored.into_iter().map(|(i, _)| i).collect();
+        self.selected = 0;
+        self.scroll = 0;
+        self.mark_rows_dirty();
+    }
+
+    fn select(&mut self, selected: usize) {
+        if selected == self.selected {
+            return;
+        }
+        self.selected = selected;
+        if self.selected < self.scroll {
+            self.scroll = self.selected;
+        } else if self.selected >= self.scroll + MAX_ROWS {
+            self.scroll = self.selected + 1 - MAX_ROWS;
+        }
+        self.mark_rows_dirty();
+    }
+
+    fn choose(&mut self, selected: usize) {
+        if let Some(entry) = self.matches.get(selected).and_then(|i| self.entries.get(*i)) {
+            self.chosen = Some(entry.value.clone());
+            self.is_closed = true;
+        }
+    }
+
+    /// The index into `matches` of the row at a window position
+    fn row_at(&self, x: u32, y: u32) -> Option<usize> {
+        let rows = self.rows_frame();
+        if !rows.is_in_boundary(x, y) {
+            return None;
+        }
+        let row = self.scroll + ((y - rows.y) / ROW_HEIGHT) as usize;
+        if row < self.matches.len() {
+            Some(row)
+        } else {
+            None
+        }
+    }
+
+    fn rows_frame(&self) -> Frame {
+        let mut column = Stack::column(Padding::all(PADDING).apply(self.rect.frame()));
+        column.skip(INPUT_HEIGHT + PADDING);
+        column.rest()
+    }
+
+    fn mark_rows_dirty(&mut self) {
+        let rows = self.rows_frame();
+        if rows.w > 0 {
+            self.dirty.add(rows);
+        }
+    }
+
+    fn draw_rows(&self, buf: &mut FrameBuffer) {
+        let rows = self.rows_frame();
+        if self.matches.is_empty() {
+            FONT.draw_string(
+                "No matches",
+                rows.x + 8,
+                rows.y + 6,
+                DEFAULT_SIZE,
+                Color::light_grey(),
+                buf,
+            );
+            return;
+        }
+        let visible = self.matches.iter().enumerate().skip(self.scroll).take(MAX_ROWS);
+        for (row, (i, entry_index)) in visible.enumerate() {
+            let entry = &self.entries[*entry_index];
+            let frame = Frame::new(rows.x, rows.y + row as u32 * ROW_HEIGHT, rows.w, ROW_HEIGHT);
+            if i == self.selected {
+                Rect::from_frame(frame, Color::dark_blue()).draw(buf);
+            }
+            let y = frame.y + ROW_HEIGHT.saturating_sub(FONT.line_height(DEFAULT_SIZE)) / 2;
+            FONT.draw_string(&entry.title, frame.x + 8, y, DEFAULT_SIZE, Color::white(), buf);
+            let detail_width = FONT.measure(&entry.detail, DEFAULT_SIZE);
+            let detail_x = (frame.x + frame.w).saturating_sub(detail_width + 8);
+            FONT.draw_string(&entry.detail, detail_x, y, DEFAULT_SIZE, Color::light_grey(), buf);
+        }
+    }
+}
+
+impl<T: Clone> Drawable for CommandPalette<T> {
+    fn draw(&mut self, buf: &mut FrameBuffer) {
+        if !self.is_backdrop_drawn {
+            // Dims whatever was last drawn beneath, once
+            buf.invalidate_all();
+            Frame::new(0, 0, buf.width(), buf.height()).blend(Color::new(0, 0, 0, 0xa0), buf);
+            self.is_backdrop_drawn = true;
+        }
+        if let Some(frame) = self.take_dirty() {
+            buf.invalidate(frame);
+        }
+        if !buf.is_damaged(&self.rect.frame()) {
+            return;
+        }
+        self.rect.draw(buf);
+        self.input.draw(buf);
+        buf.push_clip(self.rows_frame());
+        self.draw_rows(buf);
+        buf.pop_clip();
+    }
+}
+
+impl<T: Clone> Widget for CommandPalette<T> {
+    fn frame(&self) -> Frame {
+        self.rect.frame()
+    }
+
+    fn take_dirty(&mut self) -> Option<Frame> {
+        let mut dirty = self.dirty.take();
+        if let Some(frame) = self.input.take_dirty() {
+            dirty = Some(dirty.map(|d| d.union(&frame)).unwrap_or(frame));
+        }
+        dirty
+    }
+}
+
+/// Scores how well `query` matches `text` as a case-insensitive subsequence, or None if it doesn't match. Matches at
+/// the start of words and runs of consecutive chars score higher, gaps score lower.
+pub fn fuzzy_score(query: &str, text: &str) -> Option<i32> {
+    let text = text.chars().flat_map(char::to_lowercase).collect::<Vec<_>>();
+    let mut score = 0;
+    let mut pos = 0;
+    for ch in query
+        .chars()
+        .flat_map(char::to_lowercase)
+        .filter(|ch| !ch.is_whitespace())
+    {
+        let idx = (pos..text.len()).find(|i| text[*i] == ch)?;
+        score += 1;
+        if pos > 0 && idx == pos {
+            score += 5;
+        }
+        if idx == 0 || !text[idx - 1].is_alphanumeric() {
+            score += 3;
+        }
+        score -= (idx - pos).min(5) as i32;
+        pos = idx + 1;
+    }
+    Some(score)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn fuzzy_matches_subsequences() {
+        assert!(fuzzy_score("rsg", "Resign").is_some());
+        assert!(fuzzy_score("ng", "Resign").is_none());
+        assert_eq!(fuzzy_score("", "Resign"), Some(0));
+
+        let titles = ["Resign", "Next game", "Game 12 vs abc"];
+        let mut matches = titles
+            .iter()
+            .filter_map(|t| fuzzy_score("game", t).map(|score| (*t, score)))
+            .collect::<Vec<_>>();
+        matches.sort_by_key(|(_, score)| -score);
+        assert_eq!(matches.iter().map(|(t, _)| *t).collect::<Vec<_>>(), vec![
+            "Game 12 vs abc",
+            "Next game"
+        ]);
+        // Word starts beat matches inside words
+        assert!(fuzzy_score("cp", "Copy my public key") > fuzzy_score("cp", "Accept draw"));
+    }
+}
@@ -0,0 +1,216 @@
+use minifb::{Key, KeyRepeat, MouseButton, Window};
+
+use crate::{
+    color::Color,
+    components::Button,
+    drawable::{DirtyRegion, Drawable, FrameBuffer, Widget},
+    focus::{FocusManager, Focusable},
+    font::{DEFAULT_SIZE, FONT},
+    layout::{Anchor, Padding, Stack},
+    rect::{Frame, Rect},
+};
+
+const DIALOG_WIDTH: u32 = 420;
+const BUTTON_WIDTH: u32 = 100;
+const BUTTON_HEIGHT: u32 = 30;
+const PADDING: u32 = 16;
+
+/// A message box with a row of buttons, shown in the middle of the window over a dimmed screen. Whoever shows it is
+/// expected to stop passing input to the screen beneath until one of the buttons is chosen.
+#[derive(Debug)]
+pub struct Dialog {
+    title: String,
+    /// The message, wrapped to fit the dialog
+    lines: Vec<String>,
+    buttons: Vec<Button>,
+    rect: Rect,
+    focus: FocusManager,
+    /// The button the mouse was pressed on. It is chosen when the mouse is released so the click doesn't also reach
+    /// the screen beneath. Buttons only see presses, so a press that started before the dialog opened is ignored.
+    pressed: Option<usize>,
+    result: Option<usize>,
+    is_backdrop_drawn: bool,
+    dirty: DirtyRegion,
+}
+
+impl Dialog {
+    /// Enter chooses the focused button, initially the first, and Escape chooses the last
+    pub fn new<T: Into<String>>(title: T, message: &str, buttons: &[&str]) -> Self {
+        let inner_width = DIALOG_WIDTH - PADDING * 2;
+        let buttons = buttons
+            .iter()
+            .map(|text| {
+                let mut button = Button::new(Rect::from_frame(Frame::default(), Color::white()));
+                button.set_text(*text);
+                button
+            })
+            .collect();
+        let mut focus = FocusManager::new();
+        focus.focus(Some(0));
+        Self {
+            title: title.into(),
+            lines: wrap_text(message, inner_width),
+            buttons,
+            rect: {
+                let mut r = Rect::from_frame(Frame::default(), Color::grey(0x30));
+                r.set_border(2, Color::light_grey());
+                r
+            },
+            focus,
+            pressed: None,
+            result: None,
+            is_backdrop_drawn: false,
+            dirty: DirtyRegion::default(),
+        }
+    }
+
+    /// Focuses the button that Enter chooses. Dialogs the opponent opens should focus the safe choice, in case they
+    /// open while the player is typing or clicking.
+    pub fn set_default_button(&mut self, index: usize) -> &mut Self {
+        self.focus.focus(Some(index.min(self.buttons.len().saturating_sub(1))));
+        self
+    }
+
+    /// The index of the chosen button
+    pub fn result(&self) -> Option<usize> {
+        self.result
+    }
+
+    /// Centres the dialog in the window
+    pub fn set_window_size(&mut self, window_width: u32, window_height: u32) -> &mut Self {
+        let line_height = FONT.line_height(DEFAULT_SIZE) + 4;
+        let height = PADDING * 3 + line_height * (self.lines.len() as u32 + 2) + BUTTON_HEIGHT;
+        let frame = Anchor::Centre.place(Frame::new(0, 0, window_width, window_height), DIALOG_WIDTH, height);
+        if frame == self.rect.frame() {
+            return self;
+        }
+        self.rect.set_frame(frame);
+        self.dirty.add(frame);
+        let mut column = Stack::column(Padding::all(PADDING).apply(frame));
+        column.skip(line_height * (self.lines.len() as u32 + 2) + PADDING);
+        let mut row = Stack::row(Anchor::Right.place(
+            column.next(BUTTON_HEIGHT),
+            self.buttons.len() as u32 * (BUTTON_WIDTH + 10),
+            BUTTON_HEIGHT,
+        ));
+        row.set_spacing(10);
+        for button in self.buttons.iter_mut() {
+            button.set_frame(row.next(BUTTON_WIDTH));
+        }
+        // The screen beneath is redrawn in full after a resize
+        self.is_backdrop_drawn = false;
+        self
+    }
+
+    pub fn update(&mut self, window: &Window) {
+        for button in self.buttons.iter_mut() {
+            button.update(window);
+        }
+        let mut buttons = self
+            .buttons
+            .iter_mut()
+            .map(|b| b as &mut dyn Focusable)
+            .collect::<Vec<_>>();
+        self.focus.update(window, &mut buttons);
+
+        for (i, button) in self.buttons.iter_mut().enumerate() {
+            if button.was_clicked() {
+                self.pressed = Some(i);
+            }
+        }
+        if !window.get_mouse_down(MouseButton::Left) {
+            if let Some(pressed) = self.pressed.take() {
+                self.result = Some(pressed);
+            }
+        }
+        if window.is_key_pressed(Key::Escape, KeyRepeat::No) {
+            self.result = self.buttons.len().checked_sub(1);
+        }
+    }
+
+    fn draw_text(&self, buf: &mut FrameBuffer) {
+        let line_height = FONT.line_height(DEFAULT_SIZE) + 4;
+        let frame = Padding::all(PADDING).apply(self.rect.frame());
+        FONT.draw_string(&self.title, frame.x, frame.y, DEFAULT_SIZE, Color::white(), buf);
+        for (i, line) in self.lines.iter().enumerate() {
+            let y = frame.y + line_height * (i as u32 + 2);
+            FONT.draw_string(line, frame.x, y, DEFAULT_SIZE, Color::light_grey(), buf);
+        }
+    }
+}
+
+impl Drawable for Dialog {
+    fn draw(&mut self, buf: &mut FrameBuffer) {
+        if !self.is_backdrop_drawn {
+            // Dims whatever was last drawn beneath, once
+            buf.invalidate_all();
+            Frame::new(0, 0, buf.width(), buf.height()).blend(Color::new(0, 0, 0, 0xa0), buf);
+            self.is_backdrop_drawn = true;
+        }
+        if let Some(frame) = self.take_dirty() {
+            buf.invalidate(frame);
+        }
+        if !buf.is_damaged(&self.rect.frame()) {
+            return;
+        }
+        self.rect.draw(buf);
+        buf.push_clip(self.rect.frame());
+        self.draw_text(buf);
+        buf.pop_clip();
+        for button in self.buttons.iter_mut() {
+            button.draw(buf);
+        }
+    }
+}
+
+impl Widget for Dialog {
+    fn frame(&self) -> Frame {
+        self.rect.frame()
+    }
+
+    fn take_dirty(&mut self) -> Option<Frame> {
+        let mut dirty = self.dirty.take();
+        for button in self.buttons.iter_mut() {
+            if let Some(frame) = button.take_dirty() {
+                dirty = Some(dirty.map(|d| d.union(&frame)).unwrap_or(frame));
+            }
+        }
+        dirty
+    }
+}
+
+/// Splits `text` into lines no wider than `width`, breaking between words where possible
+fn wrap_text(text: &str, width: u32) -> Vec<String> {
+    let mut lines = Vec::new();
+    for paragraph in text.lines() {
+        let mut line = String::new();
+        for word in paragraph.split_whitespace() {
+            let candidate = if line.is_empty() {
+                word.to_string()
+            } else {
+                format!("{} {}", line, word)
+            };
+            if line.is_empty() || FONT.measure(&candidate, DEFAULT_SIZE) <= width {
+                line = candidate;
+            } else {
+                lines.push(std::mem::replace(&mut line, word.to_string()));
+            }
+        }
+        lines.push(line);
+    }
+    lines
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn wraps_between_words() {
+        let width = FONT.measure("aaa bbb", DEFAULT_SIZE);
+        assert_eq!(wrap_text("aaa bbb ccc\nddd", width), vec!["aaa bbb", "ccc", "ddd"]);
+        // Words wider than the line get a line of their own
+        assert_eq!(wrap_text("aaaaaaaaaa bbb", width), vec!["aaaaaaaaaa", "bbb"]);
+        assert!(wrap_text("", width).is_empty());
+    }
+}
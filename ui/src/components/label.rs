@@ -1,7 +1,7 @@
 use crate::{
     color::Color,
-    drawable::{Drawable, FrameBuffer},
-    letters::LETTERS,
+    drawable::{DirtyRegion, Drawable, FrameBuffer, Widget},
+    font::{DEFAULT_SIZE, FONT},
     rect::{Frame, Rect},
 };
 
@@ -11,6 +11,8 @@ pub struct Label {
     text_color: Color,
     bg_color: Option<Color>,
     dims: Frame,
+    is_visible: bool,
+    dirty: DirtyRegion,
 }
 
 impl Label {
@@ -20,35 +22,90 @@ impl Label {
             text_color: Color::white(),
             bg_color: None,
             dims,
+            is_visible: true,
+            dirty: DirtyRegion::new(dims),
         }
     }
 
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
     pub fn set_text<T: Into<String>>(&mut self, text: T) -> &mut Self {
-        self.text = text.into();
+        let text = text.into();
+        if text != self.text {
+            self.text = text;
+            self.dirty.add(self.dims);
+        }
         self
     }
 
     pub fn set_text_color(&mut self, color: Color) -> &mut Self {
-        self.text_color = color;
+        if color != self.text_color {
+            self.text_color = color;
+            self.dirty.add(self.dims);
+        }
         self
     }
 
     pub fn set_bg_color(&mut self, color: Color) -> &mut Self {
-        self.bg_color = Some(color);
+        if Some(color) != self.bg_color {
+            self.bg_color = Some(color);
+            self.dirty.add(self.dims);
+        }
+        self
+    }
+
+    pub fn set_frame(&mut self, dims: Frame) -> &mut Self {
+        if dims != self.dims {
+            self.dirty.add(self.dims);
+            self.dims = dims;
+            self.dirty.add(self.dims);
+        }
+        self
+    }
+
+    pub fn set_visible(&mut self, is_visible: bool) -> &mut Self {
+        if is_visible != self.is_visible {
+            self.is_visible = is_visible;
+            self.dirty.add(self.dims);
+        }
         self
     }
 
     fn draw_text(&self, buf: &mut FrameBuffer) {
-        let mid = (self.dims.h / 2) - 10;
-        LETTERS.draw_string(&self.text, self.dims.x + 2, self.dims.y + mid, self.text_color, buf);
+        let mid = self.dims.h.saturating_sub(FONT.line_height(DEFAULT_SIZE)) / 2;
+        FONT.draw_string(
+            &self.text,
+            self.dims.x + 2,
+            self.dims.y + mid,
+            DEFAULT_SIZE,
+            self.text_color,
+            buf,
+        );
     }
 }
 
 impl Drawable for Label {
     fn draw(&mut self, buf: &mut FrameBuffer) {
+        if !self.is_visible || !buf.is_damaged(&self.dims) {
+            return;
+        }
         if let Some(c) = self.bg_color {
             Rect::from_frame(self.dims, c).draw(buf);
         }
+        buf.push_clip(self.dims);
         self.draw_text(buf);
+        buf.pop_clip();
+    }
+}
+
+impl Widget for Label {
+    fn frame(&self) -> Frame {
+        self.dims
+    }
+
+    fn take_dirty(&mut self) -> Option<Frame> {
+        self.dirty.take()
     }
 }
@@ -1,28 +1,132 @@
-use std::cmp;
+use std::time::{Duration, Instant};
 
 use minifb::{Key, KeyRepeat, MouseButton, MouseMode, Window};
 
 use crate::{
     color::Color,
-    drawable::{Drawable, FrameBuffer},
-    letters::LETTERS,
+    drawable::{DirtyRegion, Drawable, FrameBuffer, Widget},
+    focus::Focusable,
+    font::{DEFAULT_SIZE, FONT},
+    layout::{Padding, Size, Stack},
     rect::{Frame, Rect},
 };
 
+const ROW_HEIGHT: u32 = 25;
+const SCROLLBAR_WIDTH: u32 = 10;
+const MIN_THUMB_HEIGHT: u32 = 20;
+/// Pixels scrolled per step of the mouse wheel
+const WHEEL_STEP: f32 = ROW_HEIGHT as f32;
+/// Two clicks on the same row within this time activate it
+const DOUBLE_CLICK_TIME: Duration = Duration::from_millis(400);
+
+/// How a cell is ordered when its column is sorted
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum SortKey {
+    Text(String),
+    Number(i64),
+}
+
+/// A piece of text drawn in a column of a list row, optionally on a coloured badge
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cell {
+    text: String,
+    color: Color,
+    badge: Option<Color>,
+    sort_key: SortKey,
+}
+
+impl Cell {
+    /// Plain text, sorted alphabetically ignoring case
+    pub fn new<T: Into<String>>(text: T) -> Self {
+        let text = text.into();
+        Self {
+            sort_key: SortKey::Text(text.to_lowercase()),
+            text,
+            color: Color::white(),
+            badge: None,
+        }
+    }
+
+    /// Text drawn on a box filled with `color`
+    pub fn badge<T: Into<String>>(text: T, color: Color) -> Self {
+        Self {
+            badge: Some(color),
+            ..Self::new(text)
+        }
+    }
+
+    pub fn with_color(self, color: Color) -> Self {
+        Self { color, ..self }
+    }
+
+    /// Sorts by `key` instead of the text, e.g. for ages and counts
+    pub fn with_sort_key(self, key: i64) -> Self {
+        Self {
+            sort_key: SortKey::Number(key),
+            ..self
+        }
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    fn draw(&self, frame: Frame, buf: &mut FrameBuffer) {
+        let line_height = FONT.line_height(DEFAULT_SIZE);
+        let y = frame.y + frame.h.saturating_sub(line_height) / 2;
+        if let Some(badge) = self.badge {
+            let width = FONT.measure(&self.text, DEFAULT_SIZE) + 8;
+            Frame::new(frame.x, y, width.min(frame.w), line_height).fill(badge.to_rgba(), buf);
+        }
+        FONT.draw_string(&self.text, frame.x + 4, y, DEFAULT_SIZE, self.color, buf);
+    }
+}
+
+/// A list column with a header that sorts the list when clicked
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Column {
+    pub title: String,
+    pub width: Size,
+}
+
+impl Column {
+    pub fn new<T: Into<String>>(title: T, width: Size) -> Self {
+        Self {
+            title: title.into(),
+            width,
+        }
+    }
+}
+
+/// A scrolling list of rows. Rows are plain strings or cells laid out in columns. Indices always refer to the order
+/// rows were given in, whichever column the list is sorted by.
 #[derive(Debug)]
 pub struct ListBox {
-    values: Vec<String>,
-    text_color: Color,
+    rows: Vec<Vec<Cell>>,
+    columns: Vec<Column>,
+    /// Row indices in display order
+    order: Vec<usize>,
+    /// The sorted column and whether it is descending
+    sort: Option<(usize, bool)>,
     rect: Rect,
     is_active: bool,
     selected: usize,
+    /// Pixels scrolled down from the first row
+    scroll: u32,
+    was_mouse_down: bool,
+    is_dragging_scrollbar: bool,
+    last_click: Option<(Instant, usize)>,
+    is_activated: bool,
+    dirty: DirtyRegion,
 }
 
 impl ListBox {
     pub fn new(dims: Frame) -> Self {
         Self {
-            values: Vec::new(),
-            text_color: Color::white(),
+            rows: Vec::new(),
+            columns: Vec::new(),
+            order: Vec::new(),
+            sort: None,
             rect: {
                 let mut r = Rect::from_frame(dims, Color::dark_grey());
                 r.set_border(2, Color::light_grey());
@@ -30,26 +134,49 @@ impl ListBox {
             },
             is_active: false,
             selected: 0,
+            scroll: 0,
+            was_mouse_down: false,
+            is_dragging_scrollbar: false,
+            last_click: None,
+            is_activated: false,
+            dirty: DirtyRegion::new(dims),
         }
     }
 
     pub fn selected_index(&self) -> Option<usize> {
-        if self.values.is_empty() {
+        if self.rows.is_empty() {
             return None;
         }
         Some(self.selected)
     }
 
+    /// The text of the first cell of the selected row
     pub fn selected(&self) -> Option<&str> {
-        self.values.get(self.selected).map(|s| &**s)
+        self.rows.get(self.selected)?.first().map(Cell::text)
+    }
+
+    /// True for the frame a row was double clicked or Enter was pressed while the list had focus
+    pub fn was_activated(&self) -> bool {
+        self.is_activated
     }
 
     pub fn is_in_boundary(&self, x: u32, y: u32) -> bool {
         self.rect.is_in_boundary(x, y)
     }
 
+    pub fn set_frame(&mut self, dims: Frame) -> &mut Self {
+        if dims != self.rect.frame() {
+            self.dirty.add(self.rect.frame());
+            self.rect.set_frame(dims);
+            self.dirty.add(dims);
+            self.scroll = self.scroll.min(self.max_scroll());
+        }
+        self
+    }
+
     pub fn set_bg_color(&mut self, color: Color) -> &mut Self {
         self.rect.set_bg_colour(color);
+        self.dirty.add(self.rect.frame());
         self
     }
 
@@ -58,77 +185,334 @@ impl ListBox {
         self
     }
 
-    pub fn update(&mut self, window: &Window) {
-        if let Some((x, y)) = window.get_mouse_pos(MouseMode::Discard) {
-            if window.get_mouse_down(MouseButton::Left) {
-                let x = x.round() as u32;
-                let y = y.round() as u32;
-                let active = self.is_in_boundary(x, y);
-                self.set_active(active);
-                if active {
-                    if let Some(rel_y) = y.checked_sub(self.rect.y()) {
-                        if rel_y <= self.rect.h() {
-                            let idx = (rel_y / 25) as usize;
-                            if idx < self.values.len() {
-                                self.selected = idx;
-                            }
-                        }
-                    }
-                }
-            }
+    /// Shows a header with the column titles. Rows are expected to have a cell for each column.
+    pub fn set_columns(&mut self, columns: Vec<Column>) -> &mut Self {
+        if columns != self.columns {
+            self.columns = columns;
+            self.sort = None;
+            self.apply_sort();
+            self.dirty.add(self.rect.frame());
+        }
+        self
+    }
+
+    /// Sorts by `column`, or shows rows in the order they were given if None
+    pub fn set_sort(&mut self, sort: Option<(usize, bool)>) -> &mut Self {
+        if sort != self.sort {
+            self.sort = sort;
+            self.apply_sort();
+            self.dirty.add(self.rect.frame());
+        }
+        self
+    }
+
+    pub fn set_values(&mut self, values: Vec<String>) -> &mut Self {
+        self.set_rows(values.into_iter().map(|value| vec![Cell::new(value)]).collect())
+    }
+
+    pub fn set_rows(&mut self, rows: Vec<Vec<Cell>>) -> &mut Self {
+        if rows != self.rows {
+            self.rows = rows;
+            self.selected = self.selected.min(self.rows.len().saturating_sub(1));
+            self.apply_sort();
+            self.scroll = self.scroll.min(self.max_scroll());
+            self.dirty.add(self.rect.frame());
         }
+        self
+    }
 
+    pub fn update(&mut self, window: &Window) {
+        let before = (self.selected, self.scroll, self.sort);
+        self.is_activated = false;
+        self.handle_mouse(window);
         if self.is_active {
             self.collect_keystrokes(window);
         }
+        if (self.selected, self.scroll, self.sort) != before {
+            self.dirty.add(self.rect.frame());
+        }
     }
 
-    fn collect_keystrokes(&mut self, window: &Window) {
-        if let Some(keys) = window.get_keys_pressed(KeyRepeat::No) {
-            for key in keys {
-                match key {
-                    Key::Up => {
-                        self.selected = self.selected.saturating_sub(1);
-                    },
-                    Key::Down => self.selected = cmp::min(self.selected + 1, self.values.len() - 1),
-                    _ => {},
-                }
+    fn handle_mouse(&mut self, window: &Window) {
+        let is_down = window.get_mouse_down(MouseButton::Left);
+        let is_click = is_down && !self.was_mouse_down;
+        self.was_mouse_down = is_down;
+        if !is_down {
+            self.is_dragging_scrollbar = false;
+        }
+        let (x, y) = match window.get_mouse_pos(MouseMode::Clamp) {
+            Some((x, y)) => (x.round() as u32, y.round() as u32),
+            None => return,
+        };
+        if self.is_in_boundary(x, y) {
+            if let Some((_, dy)) = window.get_scroll_wheel() {
+                self.scroll_by((-dy * WHEEL_STEP) as i64);
             }
         }
+        if self.is_dragging_scrollbar {
+            self.drag_scrollbar(y);
+            return;
+        }
+        if !is_click {
+            return;
+        }
+        let active = self.is_in_boundary(x, y);
+        self.set_active(active);
+        if !active {
+            return;
+        }
+        let (header, body, track) = self.layout();
+        if let Some(track) = track.filter(|track| track.is_in_boundary(x, y)) {
+            self.is_dragging_scrollbar = true;
+            self.drag_scrollbar(y.max(track.y));
+        } else if let Some(header) = header.filter(|header| header.is_in_boundary(x, y)) {
+            let column = self.column_frames(header).iter().position(|f| f.is_in_boundary(x, y));
+            if let Some(column) = column {
+                self.toggle_sort(column);
+            }
+        } else if let Some(pos) = self.row_at(body, y) {
+            let row = self.order[pos];
+            let now = Instant::now();
+            self.is_activated = matches!(
+                self.last_click,
+                Some((time, last_row)) if last_row == row && now - time <= DOUBLE_CLICK_TIME
+            );
+            self.last_click = if self.is_activated { None } else { Some((now, row)) };
+            self.selected = row;
+        }
     }
 
-    pub fn set_values(&mut self, values: Vec<String>) -> &mut Self {
-        self.values = values;
-        self
+    fn collect_keystrokes(&mut self, window: &Window) {
+        if self.rows.is_empty() {
+            return;
+        }
+        let page = (self.layout().1.h / ROW_HEIGHT).max(1) as usize;
+        let last = self.rows.len() - 1;
+        let pos = self.selected_position();
+        for key in window.get_keys_pressed(KeyRepeat::Yes).into_iter().flatten() {
+            let pos = match key {
+                Key::Up => pos.saturating_sub(1),
+                Key::Down => (pos + 1).min(last),
+                Key::PageUp => pos.saturating_sub(page),
+                Key::PageDown => (pos + page).min(last),
+                Key::Home => 0,
+                Key::End => last,
+                _ => continue,
+            };
+            self.selected = self.order[pos];
+            self.scroll_to_selected();
+        }
+    }
+
+    fn toggle_sort(&mut self, column: usize) {
+        let sort = match self.sort {
+            Some((sorted, is_descending)) if sorted == column => Some((column, !is_descending)),
+            _ => Some((column, false)),
+        };
+        self.set_sort(sort);
+    }
+
+    fn apply_sort(&mut self) {
+        self.order = sorted_order(&self.rows, self.sort);
+    }
+
+    fn selected_position(&self) -> usize {
+        self.order.iter().position(|i| *i == self.selected).unwrap_or(0)
+    }
+
+    /// Splits the inside of the border into the header, the rows and the scrollbar track
+    fn layout(&self) -> (Option<Frame>, Frame, Option<Frame>) {
+        let mut column = Stack::column(Padding::all(2).apply(self.rect.frame()));
+        let header = if self.columns.is_empty() {
+            None
+        } else {
+            Some(column.next(ROW_HEIGHT))
+        };
+        let body = column.rest();
+        if self.content_height() <= body.h {
+            return (header, body, None);
+        }
+        let mut row = Stack::row(body);
+        let parts = row.split(&[Size::Fill(1), Size::Fixed(SCROLLBAR_WIDTH)]);
+        (header, parts[0], Some(parts[1]))
+    }
+
+    fn column_frames(&self, row: Frame) -> Vec<Frame> {
+        if self.columns.is_empty() {
+            return vec![row];
+        }
+        let mut stack = Stack::row(row);
+        stack.set_spacing(8);
+        stack.split(&self.columns.iter().map(|c| c.width).collect::<Vec<_>>())
+    }
+
+    /// The display position of the row at window y coordinate `y`
+    fn row_at(&self, body: Frame, y: u32) -> Option<usize> {
+        let offset = y.checked_sub(body.y)?;
+        if offset > body.h {
+            return None;
+        }
+        let pos = ((offset + self.scroll) / ROW_HEIGHT) as usize;
+        if pos < self.rows.len() {
+            Some(pos)
+        } else {
+            None
+        }
+    }
+
+    fn content_height(&self) -> u32 {
+        self.rows.len() as u32 * ROW_HEIGHT
+    }
+
+    fn max_scroll(&self) -> u32 {
+        let body = self.layout().1;
+        self.content_height().saturating_sub(body.h)
+    }
+
+    fn scroll_by(&mut self, delta: i64) {
+        self.scroll = (self.scroll as i64 + delta).clamp(0, self.max_scroll() as i64) as u32;
+    }
+
+    fn scroll_to_selected(&mut self) {
+        let body = self.layout().1;
+        let top = self.selected_position() as u32 * ROW_HEIGHT;
+        if top < self.scroll {
+            self.scroll = top;
+        } else if top + ROW_HEIGHT > self.scroll + body.h {
+            self.scroll = (top + ROW_HEIGHT).saturating_sub(body.h);
+        }
+    }
+
+    /// The scrollbar thumb's offset from the top of the track and its height
+    fn thumb(&self, track: Frame) -> (u32, u32) {
+        let content = self.content_height().max(1);
+        let height = (track.h * track.h / content).clamp(MIN_THUMB_HEIGHT.min(track.h), track.h);
+        let offset = (track.h - height) * self.scroll / self.max_scroll().max(1);
+        (offset, height)
+    }
+
+    /// Scrolls so that the middle of the thumb is at window y coordinate `y`
+    fn drag_scrollbar(&mut self, y: u32) {
+        if let (_, _, Some(track)) = self.layout() {
+            let (_, height) = self.thumb(track);
+            let free = (track.h - height).max(1);
+            let offset = y.saturating_sub(track.y + height / 2).min(free);
+            self.scroll = self.max_scroll() * offset / free;
+        }
+    }
+
+    fn draw_header(&self, header: Frame, buf: &mut FrameBuffer) {
+        header.fill(Color::grey(0x50).to_rgba(), buf);
+        for (i, (column, frame)) in self.columns.iter().zip(self.column_frames(header)).enumerate() {
+            let title = match self.sort {
+                Some((sorted, false)) if sorted == i => format!("{} ▲", column.title),
+                Some((sorted, true)) if sorted == i => format!("{} ▼", column.title),
+                _ => column.title.clone(),
+            };
+            Cell::new(title).draw(frame, buf);
+        }
     }
 
-    fn draw_items(&self, buf: &mut FrameBuffer) {
-        let num_items = self.rect.y() as usize / 40;
-        for (i, s) in self.values.iter().take(num_items).enumerate() {
-            if self.selected == i {
-                Rect::new(
-                    self.rect.x() + 1,
-                    self.rect.y() + (i as u32) * 25,
-                    self.rect.w() - 1,
-                    22,
-                    Color::light_grey(),
-                )
-                .draw(buf);
+    fn draw_items(&self, body: Frame, buf: &mut FrameBuffer) {
+        buf.push_clip(body);
+        let first = (self.scroll / ROW_HEIGHT) as usize;
+        let count = (body.h / ROW_HEIGHT + 2) as usize;
+        for (pos, index) in self.order.iter().enumerate().skip(first).take(count) {
+            let y = (body.y + pos as u32 * ROW_HEIGHT).saturating_sub(self.scroll);
+            let row = Frame::new(body.x, y, body.w, ROW_HEIGHT);
+            if self.selected == *index {
+                Rect::new(row.x, row.y, row.w, ROW_HEIGHT - 3, Color::light_grey()).draw(buf);
+            }
+            for (cell, frame) in self.rows[*index].iter().zip(self.column_frames(row)) {
+                buf.push_clip(frame);
+                cell.draw(frame, buf);
+                buf.pop_clip();
             }
-            LETTERS.draw_string(
-                s,
-                self.rect.x() + 2,
-                self.rect.y() + (i as u32) * 25,
-                self.text_color,
-                buf,
-            );
         }
+        buf.pop_clip();
+    }
+
+    fn draw_scrollbar(&self, track: Frame, buf: &mut FrameBuffer) {
+        track.fill(Color::grey(0x30).to_rgba(), buf);
+        let (offset, height) = self.thumb(track);
+        Frame::new(track.x, track.y + offset, track.w, height).fill(Color::light_grey().to_rgba(), buf);
+    }
+}
+
+/// Row indices ordered by the cells in the sorted column. Equal rows keep their order.
+fn sorted_order(rows: &[Vec<Cell>], sort: Option<(usize, bool)>) -> Vec<usize> {
+    let mut order = (0..rows.len()).collect::<Vec<_>>();
+    if let Some((column, is_descending)) = sort {
+        let key = |i: &usize| rows[*i].get(column).map(|cell| &cell.sort_key);
+        order.sort_by(|a, b| {
+            let ordering = key(a).cmp(&key(b));
+            if is_descending {
+                ordering.reverse()
+            } else {
+                ordering
+            }
+        });
     }
+    order
 }
 
 impl Drawable for ListBox {
     fn draw(&mut self, buf: &mut FrameBuffer) {
+        if !buf.is_damaged(&self.rect.frame()) {
+            return;
+        }
         self.rect.draw(buf);
-        self.draw_items(buf);
+        let (header, body, track) = self.layout();
+        buf.push_clip(self.rect.frame());
+        if let Some(header) = header {
+            self.draw_header(header, buf);
+        }
+        self.draw_items(body, buf);
+        if let Some(track) = track {
+            self.draw_scrollbar(track, buf);
+        }
+        buf.pop_clip();
+    }
+}
+
+impl Widget for ListBox {
+    fn frame(&self) -> Frame {
+        self.rect.frame()
+    }
+
+    fn take_dirty(&mut self) -> Option<Frame> {
+        self.dirty.take()
+    }
+}
+
+impl Focusable for ListBox {
+    fn is_focused(&self) -> bool {
+        self.is_active
+    }
+
+    fn set_focused(&mut self, is_focused: bool) {
+        self.set_active(is_focused);
+    }
+
+    fn activate(&mut self) {
+        self.is_activated = !self.rows.is_empty();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn sorts_by_column() {
+        let rows = vec![
+            vec![Cell::new("bob"), Cell::new("2h").with_sort_key(7200)],
+            vec![Cell::new("Alice"), Cell::new("5m").with_sort_key(300)],
+            vec![Cell::new("carol"), Cell::new("now").with_sort_key(0)],
+        ];
+        assert_eq!(sorted_order(&rows, None), vec![0, 1, 2]);
+        assert_eq!(sorted_order(&rows, Some((0, false))), vec![1, 0, 2]);
+        assert_eq!(sorted_order(&rows, Some((1, false))), vec![2, 1, 0]);
+        assert_eq!(sorted_order(&rows, Some((1, true))), vec![0, 1, 2]);
     }
 }
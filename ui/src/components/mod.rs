@@ -1,12 +1,22 @@
+mod text_edit;
 mod textbox;
 pub use textbox::TextBox;
 
 mod button;
 pub use button::Button;
+mod command_palette;
+pub use command_palette::{CommandPalette, PaletteEntry};
+mod dialog;
+pub use dialog::Dialog;
 mod listbox;
-pub use listbox::ListBox;
+pub use listbox::{Cell, Column, ListBox, SortKey};
+mod move_list;
+pub use move_list::MoveList;
 
 mod handler;
 
 mod label;
 pub use label::Label;
+
+mod toast;
+pub use toast::{ToastKind, Toasts};
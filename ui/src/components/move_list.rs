@@ -0,0 +1,172 @@
+use minifb::{MouseMode, Window};
+
+use crate::{
+    color::Color,
+    drawable::{DirtyRegion, Drawable, FrameBuffer, Widget},
+    font::{DEFAULT_SIZE, FONT},
+    rect::{Frame, Rect},
+};
+
+const ROW_HEIGHT: u32 = 22;
+
+/// Moves in numbered white/black pairs, scrolled with the mouse wheel
+#[derive(Debug)]
+pub struct MoveList {
+    rect: Rect,
+    moves: Vec<String>,
+    first_move_number: u32,
+    is_black_first: bool,
+    current: Option<usize>,
+    scroll: usize,
+    dirty: DirtyRegion,
+}
+
+impl MoveList {
+    pub fn new(dims: Frame) -> Self {
+        Self {
+            rect: {
+                let mut r = Rect::from_frame(dims, Color::dark_grey());
+                r.set_border(2, Color::light_grey());
+                r
+            },
+            moves: Vec::new(),
+            first_move_number: 1,
+            is_black_first: false,
+            current: None,
+            scroll: 0,
+            dirty: DirtyRegion::new(dims),
+        }
+    }
+
+    /// Sets the moves and highlights the last one
+    pub fn set_moves(&mut self, moves: Vec<String>, first_move_number: u32, is_black_first: bool) -> &mut Self {
+        if moves != self.moves || first_move_number != self.first_move_number || is_black_first != self.is_black_first {
+            self.dirty.add(self.rect.frame());
+        }
+        self.moves = moves;
+        self.first_move_number = first_move_number;
+        self.is_black_first = is_black_first;
+        self.set_current(self.moves.len().checked_sub(1))
+    }
+
+    /// Highlights the move at the given index, scrolling it into view
+    pub fn set_current(&mut self, current: Option<usize>) -> &mut Self {
+        let scroll = self.scroll;
+        if current != self.current {
+            self.dirty.add(self.rect.frame());
+        }
+        self.current = current;
+        if let Some(row) = current.map(|i| self.row_of(i)) {
+            let visible_rows = self.visible_rows();
+            if row < self.scroll {
+                self.scroll = row;
+            } else if row >= self.scroll + visible_rows {
+                self.scroll = row + 1 - visible_rows;
+            }
+        }
+        if self.scroll != scroll {
+            self.dirty.add(self.rect.frame());
+        }
+        self
+    }
+
+    /// Resizes the list, keeping the highlighted move in view
+    pub fn set_frame(&mut self, dims: Frame) -> &mut Self {
+        if dims != self.rect.frame() {
+            self.dirty.add(self.rect.frame());
+            self.dirty.add(dims);
+        }
+        self.rect.set_frame(dims);
+        self.scroll = self.scroll.min(self.num_rows().saturating_sub(self.visible_rows()));
+        self.set_current(self.current)
+    }
+
+    pub fn update(&mut self, window: &Window) {
+        let is_hovered = window
+            .get_mouse_pos(MouseMode::Discard)
+            .map(|(x, y)| self.rect.is_in_boundary(x.round() as u32, y.round() as u32))
+            .unwrap_or(false);
+        if !is_hovered {
+            return;
+        }
+        if let Some((_, y)) = window.get_scroll_wheel() {
+            let max_scroll = self.num_rows().saturating_sub(self.visible_rows());
+            let scroll = self.scroll;
+            if y > 0.0 {
+                self.scroll = self.scroll.saturating_sub(1);
+            } else if y < 0.0 {
+                self.scroll = (self.scroll + 1).min(max_scroll);
+            }
+            if self.scroll != scroll {
+                self.dirty.add(self.rect.frame());
+            }
+        }
+    }
+
+    fn row_of(&self, idx: usize) -> usize {
+        (idx + self.is_black_first as usize) / 2
+    }
+
+    fn num_rows(&self) -> usize {
+        if self.moves.is_empty() {
+            return 0;
+        }
+        self.row_of(self.moves.len() - 1) + 1
+    }
+
+    fn visible_rows(&self) -> usize {
+        (self.rect.h() / ROW_HEIGHT).max(1) as usize
+    }
+
+    fn draw_move(&self, idx: usize, x: u32, y: u32, buf: &mut FrameBuffer) {
+        let san = match self.moves.get(idx) {
+            Some(san) => san,
+            None => return,
+        };
+        if self.current == Some(idx) {
+            Rect::new(x - 2, y, 95, ROW_HEIGHT - 2, Color::light_grey()).draw(buf);
+        }
+        FONT.draw_string(san, x, y, DEFAULT_SIZE, Color::white(), buf);
+    }
+}
+
+impl Drawable for MoveList {
+    fn draw(&mut self, buf: &mut FrameBuffer) {
+        if !buf.is_damaged(&self.rect.frame()) {
+            return;
+        }
+        self.rect.draw(buf);
+        buf.push_clip(self.rect.frame());
+        let (x, y) = (self.rect.x() + 4, self.rect.y() + 2);
+        let last_row = self.num_rows().min(self.scroll + self.visible_rows());
+        for (i, row) in (self.scroll..last_row).enumerate() {
+            let row_y = y + i as u32 * ROW_HEIGHT;
+            let number = self.first_move_number as usize + row;
+            FONT.draw_string(
+                &format!("{}.", number),
+                x,
+                row_y,
+                DEFAULT_SIZE,
+                Color::light_grey(),
+                buf,
+            );
+            // The first row has no white move if black moved first
+            let white = (row * 2).checked_sub(self.is_black_first as usize);
+            if let Some(white) = white {
+                self.draw_move(white, x + 55, row_y, buf);
+            }
+            self.draw_move(row * 2 + 1 - self.is_black_first as usize, x + 155, row_y, buf);
+        }
+        buf.pop_clip();
+    }
+}
+
+impl Widget for MoveList {
+    fn frame(&self) -> Frame {
+        self.rect.frame()
+    }
+
+    fn take_dirty(&mut self) -> Option<Frame> {
+        self.dirty.take()
+    }
+}
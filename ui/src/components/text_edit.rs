@@ -0,0 +1,207 @@
+/// The text, caret and selection of a single line editor. Positions are counted in chars.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TextEdit {
+    value: String,
+    caret: usize,
+    /// The other end of the selection from the caret, if anything is selected
+    anchor: Option<usize>,
+    max_length: Option<usize>,
+}
+
+impl TextEdit {
+    pub fn value(&self) -> &str {
+        &self.value
+    }
+
+    pub fn caret(&self) -> usize {
+        self.caret
+    }
+
+    /// Replaces the text, truncated to the max length, and moves the caret to the end
+    pub fn set_value(&mut self, value: &str) {
+        self.value = match self.max_length {
+            Some(max) => value.chars().take(max).collect(),
+            None => value.to_string(),
+        };
+        self.caret = self.len();
+        self.anchor = None;
+    }
+
+    pub fn set_max_length(&mut self, max_length: Option<usize>) {
+        self.max_length = max_length;
+        let value = self.value.clone();
+        self.set_value(&value);
+    }
+
+    /// The start and end of the selection, if it isn't empty
+    pub fn selection(&self) -> Option<(usize, usize)> {
+        let anchor = self.anchor?;
+        match anchor.cmp(&self.caret) {
+            std::cmp::Ordering::Less => Some((anchor, self.caret)),
+            std::cmp::Ordering::Greater => Some((self.caret, anchor)),
+            std::cmp::Ordering::Equal => None,
+        }
+    }
+
+    pub fn selected_text(&self) -> Option<&str> {
+        let (start, end) = self.selection()?;
+        Some(&self.value[self.byte_index(start)..self.byte_index(end)])
+    }
+
+    /// The text before the char at `pos`
+    pub fn prefix(&self, pos: usize) -> &str {
+        &self.value[..self.byte_index(pos)]
+    }
+
+    pub fn select_all(&mut self) {
+        self.anchor = Some(0);
+        self.caret = self.len();
+    }
+
+    /// Moves the caret to `pos`, extending the selection if `is_selecting`
+    pub fn move_to(&mut self, pos: usize, is_selecting: bool) {
+        if is_selecting {
+            self.anchor.get_or_insert(self.caret);
+        } else {
+            self.anchor = None;
+        }
+        self.caret = pos.min(self.len());
+    }
+
+    /// Moves the caret one char left, or to the start of the selection
+    pub fn move_left(&mut self, is_selecting: bool) {
+        match self.selection() {
+            Some((start, _)) if !is_selecting => self.move_to(start, false),
+            _ => self.move_to(self.caret.saturating_sub(1), is_selecting),
+        }
+    }
+
+    /// Moves the caret one char right, or to the end of the selection
+    pub fn move_right(&mut self, is_selecting: bool) {
+        match self.selection() {
+            Some((_, end)) if !is_selecting => self.move_to(end, false),
+            _ => self.move_to(self.caret + 1, is_selecting),
+        }
+    }
+
+    /// Replaces the selection with `text`, dropping whatever doesn't fit in the max length
+    pub fn insert(&mut self, text: &str) {
+        self.delete_selection();
+        let room = self
+            .max_length
+            .map(|max| max.saturating_sub(self.len()))
+            .unwrap_or(usize::MAX);
+        let text = text
+            .chars()
+            .filter(|ch| !ch.is_control())
+            .take(room)
+            .collect::<String>();
+        let at = self.byte_index(self.caret);
+        self.value.insert_str(at, &text);
+        self.caret += text.chars().count();
+    }
+
+    /// Deletes the selection or the char before the caret
+    pub fn backspace(&mut self) {
+        if !self.delete_selection() && self.caret > 0 {
+            self.caret -= 1;
+            self.remove(self.caret, self.caret + 1);
+        }
+    }
+
+    /// Deletes the selection or the char after the caret
+    pub fn delete(&mut self) {
+        if !self.delete_selection() && self.caret < self.len() {
+            self.remove(self.caret, self.caret + 1);
+        }
+    }
+
+    /// Removes and returns the selected text
+    pub fn cut(&mut self) -> Option<String> {
+        let text = self.selected_text()?.to_string();
+        self.delete_selection();
+        Some(text)
+    }
+
+    fn delete_selection(&mut self) -> bool {
+        match self.selection() {
+            Some((start, end)) => {
+                self.remove(start, end);
+                self.caret = start;
+                self.anchor = None;
+                true
+            },
+            None => {
+                self.anchor = None;
+                false
+            },
+        }
+    }
+
+    fn remove(&mut self, start: usize, end: usize) {
+        let range = self.byte_index(start)..self.byte_index(end);
+        self.value.replace_range(range, "");
+    }
+
+    fn len(&self) -> usize {
+        self.value.chars().count()
+    }
+
+    fn byte_index(&self, pos: usize) -> usize {
+        self.value
+            .char_indices()
+            .nth(pos)
+            .map(|(i, _)| i)
+            .unwrap_or(self.value.len())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn edits_at_caret() {
+        let mut edit = TextEdit::default();
+        edit.insert("héllo");
+        edit.move_left(false);
+        edit.move_left(false);
+        edit.insert("!");
+        assert_eq!(edit.value(), "hél!lo");
+        edit.backspace();
+        edit.delete();
+        assert_eq!(edit.value(), "hélo");
+        edit.move_to(0, false);
+        edit.delete();
+        assert_eq!(edit.value(), "élo");
+        assert_eq!(edit.caret(), 0);
+    }
+
+    #[test]
+    fn replaces_and_cuts_selection() {
+        let mut edit = TextEdit::default();
+        edit.set_value("one two");
+        edit.move_left(true);
+        edit.move_left(true);
+        edit.move_left(true);
+        assert_eq!(edit.selected_text(), Some("two"));
+        edit.insert("2");
+        assert_eq!(edit.value(), "one 2");
+
+        edit.select_all();
+        assert_eq!(edit.cut().as_deref(), Some("one 2"));
+        assert_eq!(edit.value(), "");
+        assert_eq!(edit.cut(), None);
+    }
+
+    #[test]
+    fn limits_length() {
+        let mut edit = TextEdit::default();
+        edit.set_max_length(Some(4));
+        edit.insert("abcdef");
+        assert_eq!(edit.value(), "abcd");
+        edit.move_left(true);
+        edit.insert("xyz");
+        assert_eq!(edit.value(), "abcx");
+    }
+}
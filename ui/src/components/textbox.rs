@@ -1,26 +1,41 @@
 use minifb::{Key, KeyRepeat, MouseButton, MouseMode, Window};
 
+use super::text_edit::TextEdit;
 use crate::{
     clipboard::Clipboard,
     color::Color,
-    drawable::{Drawable, FrameBuffer},
-    letters::LETTERS,
+    drawable::{DirtyRegion, Drawable, FrameBuffer, Widget},
+    focus::Focusable,
+    font::{DEFAULT_SIZE, FONT},
+    input,
     rect::{Frame, Rect},
 };
 
+/// Pixels between the border and the text
+const TEXT_MARGIN: u32 = 4;
+
+/// A single line text input. The active text box takes typed characters, moves the caret with the arrow keys,
+/// Home and End, selects with Shift or the mouse and supports Ctrl-A, Ctrl-C, Ctrl-X and Ctrl-V.
 #[derive(Debug)]
 pub struct TextBox {
-    value: String,
+    edit: TextEdit,
+    placeholder: String,
     text_color: Color,
     rect: Rect,
     is_active: bool,
+    is_visible: bool,
+    /// How far the text is scrolled left to keep the caret in view
+    scroll: u32,
+    was_mouse_down: bool,
+    dirty: DirtyRegion,
     clipboard: Clipboard,
 }
 
 impl TextBox {
     pub fn new(dims: Frame, clipboard: Clipboard) -> Self {
         Self {
-            value: String::new(),
+            edit: TextEdit::default(),
+            placeholder: String::new(),
             text_color: Color::white(),
             rect: {
                 let mut r = Rect::from_frame(dims, Color::dark_grey());
@@ -28,122 +43,288 @@ impl TextBox {
                 r
             },
             is_active: false,
+            is_visible: true,
+            scroll: 0,
+            was_mouse_down: false,
+            dirty: DirtyRegion::new(dims),
             clipboard,
         }
     }
 
     pub fn value(&self) -> &str {
-        &self.value
+        self.edit.value()
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.is_active
     }
 
     pub fn is_in_boundary(&self, x: u32, y: u32) -> bool {
         self.rect.is_in_boundary(x, y)
     }
 
+    pub fn set_position(&mut self, x: u32, y: u32) -> &mut Self {
+        let frame = self.rect.frame();
+        self.set_frame(Frame { x, y, ..frame })
+    }
+
+    pub fn set_frame(&mut self, dims: Frame) -> &mut Self {
+        if dims != self.rect.frame() {
+            self.dirty.add(self.rect.frame());
+            self.rect.set_frame(dims);
+            self.dirty.add(dims);
+            self.scroll_to_caret();
+        }
+        self
+    }
+
     pub fn set_bg_color(&mut self, color: Color) -> &mut Self {
         self.rect.set_bg_colour(color);
+        self.dirty.add(self.rect.frame());
         self
     }
 
     pub fn set_active(&mut self, active: bool) -> &mut Self {
+        if active == self.is_active {
+            return self;
+        }
         self.is_active = active;
         if active {
             self.rect.set_bg_colour(Color::grey(0x60));
         } else {
             self.rect.set_bg_colour(Color::dark_grey());
         }
+        self.dirty.add(self.rect.frame());
         self
     }
 
-    pub fn update(&mut self, window: &Window) {
-        if let Some((x, y)) = window.get_mouse_pos(MouseMode::Discard) {
-            if window.get_mouse_down(MouseButton::Left) {
-                let active = self.is_in_boundary(x.round() as u32, y.round() as u32);
-                self.set_active(active);
-            }
+    /// Hidden text boxes are not drawn and ignore input
+    pub fn set_visible(&mut self, is_visible: bool) -> &mut Self {
+        if is_visible != self.is_visible {
+            self.is_visible = is_visible;
+            self.dirty.add(self.rect.frame());
         }
+        self
+    }
 
+    /// Shown in grey while the text box is empty
+    pub fn set_placeholder<T: Into<String>>(&mut self, placeholder: T) -> &mut Self {
+        let placeholder = placeholder.into();
+        if placeholder != self.placeholder {
+            self.placeholder = placeholder;
+            self.dirty.add(self.rect.frame());
+        }
+        self
+    }
+
+    /// Limits the number of characters that can be entered. Longer values are truncated.
+    pub fn set_max_length(&mut self, max_length: Option<usize>) -> &mut Self {
+        self.edit.set_max_length(max_length);
+        self.scroll_to_caret();
+        self.dirty.add(self.rect.frame());
+        self
+    }
+
+    pub fn update(&mut self, window: &Window) {
+        if !self.is_visible {
+            return;
+        }
+        let before = self.edit.clone();
+        self.handle_mouse(window);
         if self.is_active {
             self.collect_keystrokes(window);
         }
+        if self.edit != before {
+            self.scroll_to_caret();
+            self.dirty.add(self.rect.frame());
+        }
     }
 
-    fn collect_keystrokes(&mut self, window: &Window) {
-        if let Some(keys) = window.get_keys_pressed(KeyRepeat::No) {
-            let modifiers = window.get_keys();
-            let mut is_ctrl = false;
-            let mut is_shift = false;
-            let mut char = None;
-            for key in modifiers.into_iter().flatten() {
-                match key {
-                    #[cfg(any(target_os = "windows", target_os = "linux"))]
-                    Key::LeftCtrl | Key::RightCtrl => is_ctrl = true,
-                    #[cfg(target_os = "macos")]
-                    Key::LeftSuper | Key::RightSuper => is_ctrl = true,
-                    Key::LeftShift | Key::RightShift => is_shift = true,
-                    Key::Backspace => {
-                        self.value.pop();
-                        continue;
-                    },
-                    _ => {},
+    /// Clicking focuses the text box and places the caret, dragging selects
+    fn handle_mouse(&mut self, window: &Window) {
+        let is_down = window.get_mouse_down(MouseButton::Left);
+        let is_click = is_down && !self.was_mouse_down;
+        self.was_mouse_down = is_down;
+        if !is_down {
+            return;
+        }
+        if let Some((x, y)) = window.get_mouse_pos(MouseMode::Clamp) {
+            let (x, y) = (x.round() as u32, y.round() as u32);
+            if is_click {
+                let is_inside = self.is_in_boundary(x, y);
+                self.set_active(is_inside);
+                if is_inside {
+                    let is_shift = window.is_key_down(Key::LeftShift) || window.is_key_down(Key::RightShift);
+                    self.edit.move_to(self.char_at(x), is_shift);
                 }
+            } else if self.is_active {
+                self.edit.move_to(self.char_at(x), true);
             }
-            for key in keys {
-                char = key_to_char(key);
-                if char.is_some() {
-                    break;
-                }
+        }
+    }
+
+    fn collect_keystrokes(&mut self, window: &Window) {
+        let is_shift = window.is_key_down(Key::LeftShift) || window.is_key_down(Key::RightShift);
+        #[cfg(not(target_os = "macos"))]
+        let is_ctrl = window.is_key_down(Key::LeftCtrl) || window.is_key_down(Key::RightCtrl);
+        #[cfg(target_os = "macos")]
+        let is_ctrl = window.is_key_down(Key::LeftSuper) || window.is_key_down(Key::RightSuper);
+
+        for key in window.get_keys_pressed(KeyRepeat::Yes).into_iter().flatten() {
+            match key {
+                Key::Left => self.edit.move_left(is_shift),
+                Key::Right => self.edit.move_right(is_shift),
+                Key::Home => self.edit.move_to(0, is_shift),
+                Key::End => self.edit.move_to(usize::MAX, is_shift),
+                Key::Backspace => self.edit.backspace(),
+                Key::Delete => self.edit.delete(),
+                Key::A if is_ctrl => self.edit.select_all(),
+                Key::C if is_ctrl => {
+                    if let Some(text) = self.edit.selected_text() {
+                        self.copy_to_clipboard(text.to_string());
+                    }
+                },
+                Key::X if is_ctrl => {
+                    if let Some(text) = self.edit.cut() {
+                        self.copy_to_clipboard(text);
+                    }
+                },
+                Key::V if is_ctrl => {
+                    if let Some(text) = self.paste_clipboard() {
+                        self.edit.insert(&text);
+                    }
+                },
+                _ => {},
             }
-            if is_ctrl && char.map(|c| c == 'v').unwrap_or(false) {
-                if let Some(s) = self.paste_clipboard() {
-                    self.value += &s;
-                }
-            } else if let Some(mut ch) = char {
-                if is_shift {
-                    ch = ch.to_ascii_uppercase();
-                }
-                self.value.push(ch);
+        }
+        // Shortcuts also come through as control characters, which are ignored
+        if !is_ctrl {
+            let typed = input::typed_chars()
+                .into_iter()
+                .filter(|ch| !ch.is_control())
+                .collect::<String>();
+            if !typed.is_empty() {
+                self.edit.insert(&typed);
             }
         }
     }
 
+    fn copy_to_clipboard(&self, text: String) {
+        if let Err(err) = self.clipboard.set_contents(text) {
+            log::warn!("Failed to copy to the clipboard: {}", err);
+        }
+    }
+
     fn paste_clipboard(&self) -> Option<String> {
         self.clipboard.get_contents().ok()
     }
 
     pub fn set_value(&mut self, value: String) -> &mut Self {
-        self.value = value;
+        if value != self.edit.value() {
+            self.edit.set_value(&value);
+            self.scroll_to_caret();
+            self.dirty.add(self.rect.frame());
+        }
         self
     }
 
+    /// The position of the char boundary nearest to window x coordinate `x`
+    fn char_at(&self, x: u32) -> usize {
+        let target = (x + self.scroll).saturating_sub(self.rect.x() + TEXT_MARGIN);
+        let value = self.edit.value();
+        let mut prev_width = 0;
+        for (pos, (i, ch)) in value.char_indices().enumerate() {
+            let width = FONT.measure(&value[..i + ch.len_utf8()], DEFAULT_SIZE);
+            if target < (prev_width + width) / 2 {
+                return pos;
+            }
+            prev_width = width;
+        }
+        value.chars().count()
+    }
+
+    fn text_x(&self, pos: usize) -> u32 {
+        FONT.measure(self.edit.prefix(pos), DEFAULT_SIZE)
+    }
+
+    fn scroll_to_caret(&mut self) {
+        let visible = self.rect.w().saturating_sub(TEXT_MARGIN * 2);
+        let caret = self.text_x(self.edit.caret());
+        let text = self.text_x(usize::MAX);
+        if caret < self.scroll {
+            self.scroll = caret;
+        } else if caret > self.scroll + visible {
+            self.scroll = caret - visible;
+        }
+        // Don't leave empty space on the right when text is deleted
+        self.scroll = self.scroll.min(text.saturating_sub(visible));
+    }
+
     fn draw_text(&self, buf: &mut FrameBuffer) {
-        let mid = (self.rect.h() / 2) - 10;
-        LETTERS.draw_string(
-            &self.value,
-            self.rect.x() + 2,
-            self.rect.y() + mid,
+        let line_height = FONT.line_height(DEFAULT_SIZE);
+        let y = self.rect.y() + self.rect.h().saturating_sub(line_height) / 2;
+        let left = self.rect.x() + TEXT_MARGIN;
+        let to_screen = |offset: u32| left + offset.saturating_sub(self.scroll);
+
+        if self.edit.value().is_empty() {
+            FONT.draw_string(&self.placeholder, left, y, DEFAULT_SIZE, Color::light_grey(), buf);
+        } else if let Some((start, end)) = self.edit.selection().filter(|_| self.is_active) {
+            let (start, end) = (to_screen(self.text_x(start)), to_screen(self.text_x(end)));
+            Frame::new(start, y, end - start, line_height).fill(Color::dark_blue().to_rgba(), buf);
+        }
+        // Text scrolled out of view is skipped rather than drawn left of the text box
+        let first = (0..=self.edit.caret())
+            .find(|pos| self.text_x(*pos) >= self.scroll)
+            .unwrap_or(0);
+        let visible = &self.edit.value()[self.edit.prefix(first).len()..];
+        FONT.draw_string(
+            visible,
+            to_screen(self.text_x(first)),
+            y,
+            DEFAULT_SIZE,
             self.text_color,
             buf,
         );
+
+        if self.is_active {
+            let caret = to_screen(self.text_x(self.edit.caret()));
+            Frame::new(caret, y, 1, line_height).fill(self.text_color.to_rgba(), buf);
+        }
     }
 }
 
 impl Drawable for TextBox {
     fn draw(&mut self, buf: &mut FrameBuffer) {
+        if !self.is_visible || !buf.is_damaged(&self.rect.frame()) {
+            return;
+        }
         self.rect.draw(buf);
+        buf.push_clip(self.rect.frame());
         self.draw_text(buf);
+        buf.pop_clip();
     }
 }
 
-fn key_to_char(key: Key) -> Option<char> {
-    if (Key::A as u32..=Key::Z as u32).contains(&(key as u32)) {
-        return Some(char::from_u32('a' as u32 - Key::A as u32 + key as u32).unwrap());
+impl Widget for TextBox {
+    fn frame(&self) -> Frame {
+        self.rect.frame()
     }
-    if (Key::Key0 as u32..=Key::Key9 as u32).contains(&(key as u32)) {
-        return Some(char::from_u32('0' as u32 - Key::Key0 as u32 + key as u32).unwrap());
+
+    fn take_dirty(&mut self) -> Option<Frame> {
+        self.dirty.take()
+    }
+}
+
+impl Focusable for TextBox {
+    fn is_focused(&self) -> bool {
+        self.is_active
     }
-    if key == Key::Space {
-        return Some(' ');
+
+    fn set_focused(&mut self, is_focused: bool) {
+        self.set_active(is_focused);
+    }
+
+    fn can_focus(&self) -> bool {
+        self.is_visible
     }
-    None
 }
@@ -0,0 +1,178 @@
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    color::Color,
+    drawable::{DirtyRegion, Drawable, FrameBuffer, Widget},
+    font::{DEFAULT_SIZE, FONT},
+    rect::{Frame, Rect},
+};
+
+/// How long each toast stays on screen
+const TOAST_TIME: Duration = Duration::from_secs(4);
+/// Further toasts wait until one of the shown toasts expires
+const MAX_VISIBLE: usize = 3;
+const TOAST_HEIGHT: u32 = 36;
+const MARGIN: u32 = 10;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ToastKind {
+    Info,
+    Error,
+}
+
+#[derive(Debug, Clone)]
+struct Toast {
+    message: String,
+    kind: ToastKind,
+}
+
+/// Short messages shown in the bottom right corner of the window on top of the screen. Each toast is shown for a few
+/// seconds, a few at a time, in the order they were pushed.
+#[derive(Debug)]
+pub struct Toasts {
+    pending: VecDeque<Toast>,
+    /// Shown toasts, newest last, with the time each was shown
+    visible: Vec<(Toast, Instant)>,
+    window_width: u32,
+    window_height: u32,
+    dirty: DirtyRegion,
+}
+
+impl Toasts {
+    pub fn new(window_width: u32, window_height: u32) -> Self {
+        Self {
+            pending: VecDeque::new(),
+            visible: Vec::new(),
+            window_width,
+            window_height,
+            dirty: DirtyRegion::default(),
+        }
+    }
+
+    pub fn push<T: Into<String>>(&mut self, message: T, kind: ToastKind) -> &mut Self {
+        self.pending.push_back(Toast {
+            message: message.into(),
+            kind,
+        });
+        self
+    }
+
+    pub fn set_window_size(&mut self, window_width: u32, window_height: u32) -> &mut Self {
+        if (window_width, window_height) != (self.window_width, self.window_height) {
+            self.window_width = window_width;
+            self.window_height = window_height;
+            self.mark_dirty();
+        }
+        self
+    }
+
+    /// Removes expired toasts and shows waiting ones
+    pub fn update(&mut self) {
+        self.advance(Instant::now());
+    }
+
+    fn advance(&mut self, now: Instant) {
+        let before = self.toast_frames();
+        let count = self.visible.len();
+        self.visible
+            .retain(|(_, shown_at)| now.duration_since(*shown_at) < TOAST_TIME);
+        let mut is_changed = self.visible.len() != count;
+        while self.visible.len() < MAX_VISIBLE {
+            match self.pending.pop_front() {
+                Some(toast) => self.visible.push((toast, now)),
+                None => break,
+            }
+            is_changed = true;
+        }
+        if is_changed {
+            for frame in before {
+                self.dirty.add(frame);
+            }
+            self.mark_dirty();
+        }
+    }
+
+    fn mark_dirty(&mut self) {
+        for frame in self.toast_frames() {
+            self.dirty.add(frame);
+        }
+    }
+
+    /// The frame of each shown toast, stacked upwards from the bottom right with the newest at the bottom
+    fn toast_frames(&self) -> Vec<Frame> {
+        let max_width = self.window_width.saturating_sub(MARGIN * 2);
+        let mut bottom = self.window_height.saturating_sub(MARGIN);
+        self.visible
+            .iter()
+            .rev()
+            .map(|(toast, _)| {
+                let width = (FONT.measure(&toast.message, DEFAULT_SIZE) + 24).min(max_width);
+                let y = bottom.saturating_sub(TOAST_HEIGHT);
+                bottom = y.saturating_sub(MARGIN);
+                Frame::new(self.window_width.saturating_sub(MARGIN + width), y, width, TOAST_HEIGHT)
+            })
+            .collect()
+    }
+}
+
+impl Drawable for Toasts {
+    fn draw(&mut self, buf: &mut FrameBuffer) {
+        for ((toast, _), frame) in self.visible.iter().rev().zip(self.toast_frames()) {
+            if !buf.is_damaged(&frame) {
+                continue;
+            }
+            let border = match toast.kind {
+                ToastKind::Info => Color::light_grey(),
+                ToastKind::Error => Color::red(),
+            };
+            let mut rect = Rect::from_frame(frame, Color::grey(0x30));
+            rect.set_border(2, border);
+            rect.draw(buf);
+            buf.push_clip(frame);
+            let y = frame.y + frame.h.saturating_sub(FONT.line_height(DEFAULT_SIZE)) / 2;
+            FONT.draw_string(&toast.message, frame.x + 12, y, DEFAULT_SIZE, Color::white(), buf);
+            buf.pop_clip();
+        }
+    }
+}
+
+impl Widget for Toasts {
+    fn frame(&self) -> Frame {
+        self.toast_frames()
+            .into_iter()
+            .reduce(|a, b| a.union(&b))
+            .unwrap_or_default()
+    }
+
+    fn take_dirty(&mut self) -> Option<Frame> {
+        self.dirty.take()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn shows_toasts_a_few_at_a_time() {
+        let mut toasts = Toasts::new(800, 600);
+        for i in 0..4 {
+            toasts.push(format!("Toast {}", i), ToastKind::Info);
+        }
+        let start = Instant::now();
+        toasts.advance(start);
+        assert_eq!(toasts.visible.len(), MAX_VISIBLE);
+        assert!(toasts.take_dirty().is_some());
+
+        toasts.advance(start + Duration::from_secs(1));
+        assert!(toasts.take_dirty().is_none());
+
+        toasts.advance(start + TOAST_TIME);
+        assert_eq!(toasts.visible.len(), 1);
+        assert_eq!(toasts.visible[0].0.message, "Toast 3");
+        assert!(toasts.take_dirty().is_some());
+    }
+}
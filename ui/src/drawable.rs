@@ -1,14 +1,50 @@
-use std::{cmp, collections::Bound, ops::RangeBounds};
+use std::ops::Range;
 
-use crate::color::Color;
+use crate::{color::Color, rect::Frame};
 
 pub trait Drawable {
     fn draw(&mut self, buf: &mut FrameBuffer);
 }
 
+/// A retained widget, which only needs drawing again once it changes
+pub trait Widget: Drawable {
+    fn frame(&self) -> Frame;
+
+    /// Returns the area that changed since the last call, covering both where the widget was and where it is now
+    fn take_dirty(&mut self) -> Option<Frame>;
+}
+
+/// Accumulates the area a widget needs redrawn
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DirtyRegion(Option<Frame>);
+
+impl DirtyRegion {
+    pub fn new(frame: Frame) -> Self {
+        Self(Some(frame))
+    }
+
+    pub fn add(&mut self, frame: Frame) {
+        self.0 = Some(self.0.map(|f| f.union(&frame)).unwrap_or(frame));
+    }
+
+    pub fn take(&mut self) -> Option<Frame> {
+        self.0.take()
+    }
+}
+
+/// Damaged areas are merged once they overlap. Past this many, all of them are merged into one.
+const MAX_DAMAGE_FRAMES: usize = 8;
+
 pub struct FrameBuffer {
     buf: Vec<u32>,
     width: u32,
+    height: u32,
+    /// Areas drawing is limited to as left, top, right and bottom, where right and bottom are exclusive. Only the
+    /// last one applies.
+    clip: Vec<(u32, u32, u32, u32)>,
+    /// The areas to redraw this frame, which never overlap. Drawing outside of them is clipped, and widgets outside
+    /// of them skip drawing.
+    damage: Vec<Frame>,
 }
 
 impl FrameBuffer {
@@ -16,45 +52,271 @@ impl FrameBuffer {
         Self {
             buf: vec![background_color.to_rgba(); width as usize * height as usize],
             width,
+            height,
+            clip: Vec::new(),
+            damage: vec![Frame::new(0, 0, width, height)],
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Resizes the buffer to follow the window, clearing it to the background colour
+    pub fn resize(&mut self, width: u32, height: u32, background_color: Color) -> &mut Self {
+        self.buf = vec![background_color.to_rgba(); width as usize * height as usize];
+        self.width = width;
+        self.height = height;
+        self.clip.clear();
+        self.invalidate_all()
+    }
+
+    /// Marks an area to be redrawn this frame. Invalidate before drawing anything, as drawing is clipped to the
+    /// damaged area.
+    pub fn invalidate(&mut self, frame: Frame) -> &mut Self {
+        let mut frame = frame;
+        // A merged frame can overlap frames the original didn't
+        while let Some(i) = self.damage.iter().position(|d| d.intersects(&frame)) {
+            frame = frame.union(&self.damage.swap_remove(i));
+        }
+        self.damage.push(frame);
+        if self.damage.len() > MAX_DAMAGE_FRAMES {
+            let all = self.damage.drain(..).reduce(|a, b| a.union(&b));
+            self.damage.extend(all);
+        }
+        self
+    }
+
+    pub fn invalidate_all(&mut self) -> &mut Self {
+        self.invalidate(Frame::new(0, 0, self.width, self.height))
+    }
+
+    /// Invalidates the area of each widget that changed since it was last drawn
+    pub fn invalidate_widgets(&mut self, widgets: &mut [&mut dyn Widget]) -> &mut Self {
+        for widget in widgets.iter_mut() {
+            if let Some(frame) = widget.take_dirty() {
+                self.invalidate(frame);
+            }
         }
+        self
+    }
+
+    /// Returns true if `frame` overlaps any of the areas being redrawn
+    pub fn is_damaged(&self, frame: &Frame) -> bool {
+        self.damage.iter().any(|d| d.intersects(frame))
+    }
+
+    /// Returns the areas redrawn since the last call, which is empty if the frame is unchanged
+    pub fn take_damage(&mut self) -> Vec<Frame> {
+        std::mem::take(&mut self.damage)
+    }
+
+    /// Limits drawing to `frame` (within any clip already pushed) until the matching `pop_clip`
+    pub fn push_clip(&mut self, frame: Frame) -> &mut Self {
+        let (left, top, right, bottom) = self.clip_bounds();
+        // Frames include their right and bottom edges
+        self.clip.push((
+            frame.x.max(left),
+            frame.y.max(top),
+            frame.x.saturating_add(frame.w).saturating_add(1).min(right),
+            frame.y.saturating_add(frame.h).saturating_add(1).min(bottom),
+        ));
+        self
+    }
+
+    pub fn pop_clip(&mut self) -> &mut Self {
+        self.clip.pop();
+        self
     }
 
+    /// Pixels outside of the buffer or the clip area are not drawn
     pub fn put_pixel(&mut self, x: u32, y: u32, val: u32) -> &mut Self {
-        let pos = (x + y * self.width) as usize;
-        if pos < self.buf.len() {
-            self.buf[pos] = val;
+        if self.is_visible(x, y) {
+            self.buf[(x + y * self.width) as usize] = val;
+        }
+        self
+    }
+
+    /// Composites `color` over the pixel at (x, y), with its alpha scaled by `coverage`, e.g. from antialiasing
+    pub fn blend_pixel(&mut self, x: u32, y: u32, color: Color, coverage: u8) -> &mut Self {
+        let alpha = (u32::from(color.a) * u32::from(coverage) / 255) as u8;
+        if alpha == 0 || !self.is_visible(x, y) {
+            return self;
         }
+        let pos = (x + y * self.width) as usize;
+        self.buf[pos] = color.with_alpha(alpha).over(Color::from_rgba(self.buf[pos])).to_rgba();
         self
     }
 
     pub fn put_line(&mut self, x: u32, y: u32, width: u32, val: u32) -> &mut Self {
-        let offset_y = self.width * y;
-        let start = (x + offset_y) as usize;
-        let end = (x + offset_y + width) as usize;
-        self.as_slice_mut(start..=end).fill(val);
+        for i in 0..self.damage.len() {
+            if let Some(range) = self.line_range(i, x, y, width) {
+                self.buf[range].fill(val);
+            }
+        }
         self
     }
 
-    pub fn clear(&mut self, colour: Color) -> &mut Self {
-        self.buf[..].fill(colour.to_rgba());
+    /// Composites `color` over a line of pixels, the same ones `put_line` would draw
+    pub fn blend_line(&mut self, x: u32, y: u32, width: u32, color: Color) -> &mut Self {
+        for i in 0..self.damage.len() {
+            if let Some(range) = self.line_range(i, x, y, width) {
+                for px in self.buf[range].iter_mut() {
+                    *px = color.over(Color::from_rgba(*px)).to_rgba();
+                }
+            }
+        }
         self
     }
 
-    fn as_slice_mut<R: RangeBounds<usize>>(&mut self, range: R) -> &mut [u32] {
-        let start = match range.start_bound() {
-            Bound::Included(x) => cmp::min(*x + 1, self.buf.len() - 1),
-            Bound::Excluded(x) => cmp::min(*x, self.buf.len() - 1),
-            Bound::Unbounded => 0,
-        };
-        let end = match range.end_bound() {
-            Bound::Included(x) => cmp::min(*x + 1, self.buf.len() - 1),
-            Bound::Excluded(x) => cmp::min(*x, self.buf.len() - 1),
-            Bound::Unbounded => self.buf.len() - 1,
-        };
-        &mut self.buf[start..end]
+    /// Clears the areas being redrawn
+    pub fn clear(&mut self, colour: Color) -> &mut Self {
+        let val = colour.to_rgba();
+        for i in 0..self.damage.len() {
+            if let Some((left, top, right, bottom)) = self.visible_area(i) {
+                for y in top..bottom {
+                    let offset_y = (self.width * y) as usize;
+                    self.buf[offset_y + left as usize..offset_y + right as usize].fill(val);
+                }
+            }
+        }
+        self
     }
 
     pub fn as_slice(&self) -> &[u32] {
         &self.buf
     }
+
+    fn clip_bounds(&self) -> (u32, u32, u32, u32) {
+        self.clip.last().copied().unwrap_or((0, 0, self.width, self.height))
+    }
+
+    /// The part of damaged area `i` inside the clip area and the buffer, or None if nothing of it is visible
+    fn visible_area(&self, i: usize) -> Option<(u32, u32, u32, u32)> {
+        let (left, top, right, bottom) = self.clip_bounds();
+        let d = self.damage[i];
+        // Frames include their right and bottom edges
+        let area = (
+            d.x.max(left),
+            d.y.max(top),
+            d.x.saturating_add(d.w).saturating_add(1).min(right).min(self.width),
+            d.y.saturating_add(d.h).saturating_add(1).min(bottom).min(self.height),
+        );
+        (area.0 < area.2 && area.1 < area.3).then_some(area)
+    }
+
+    fn is_visible(&self, x: u32, y: u32) -> bool {
+        (0..self.damage.len())
+            .filter_map(|i| self.visible_area(i))
+            .any(|(left, top, right, bottom)| x >= left && x < right && y >= top && y < bottom)
+    }
+
+    /// The part of a line inside damaged area `i` as a range of the buffer
+    fn line_range(&self, i: usize, x: u32, y: u32, width: u32) -> Option<Range<usize>> {
+        let (left, top, right, bottom) = self.visible_area(i)?;
+        if y < top || y >= bottom {
+            return None;
+        }
+        // Lines start one pixel to the right of x
+        let start = x.saturating_add(1).max(left);
+        let end = x.saturating_add(width).saturating_add(1).min(right);
+        if start >= end {
+            return None;
+        }
+        let offset_y = (self.width * y) as usize;
+        Some(offset_y + start as usize..offset_y + end as usize)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn clips_to_the_innermost_frame() {
+        let (black, white) = (Color::black().to_rgba(), Color::white().to_rgba());
+        let mut buf = FrameBuffer::new(10, 10, Color::black());
+        buf.push_clip(Frame::new(2, 2, 5, 5)).push_clip(Frame::new(4, 0, 9, 3));
+        for y in 0..10 {
+            buf.put_line(0, y, 10, white);
+        }
+        buf.pop_clip().put_pixel(2, 7, white).pop_clip().put_pixel(0, 0, white);
+        buf.take_damage();
+        buf.put_pixel(9, 9, white);
+
+        let px = |x: usize, y: usize| buf.as_slice()[x + y * 10];
+        assert_eq!(px(4, 2), white);
+        assert_eq!(px(7, 3), white);
+        assert_eq!(px(3, 2), black);
+        assert_eq!(px(4, 4), black);
+        assert_eq!(px(2, 7), white);
+        assert_eq!(px(0, 0), white);
+        // Nothing is drawn outside of the damaged area
+        assert_eq!(px(9, 9), black);
+    }
+
+    #[test]
+    fn damage_covers_invalidated_frames() {
+        let mut buf = FrameBuffer::new(100, 100, Color::black());
+        buf.take_damage();
+        assert!(!buf.is_damaged(&Frame::new(0, 0, 100, 100)));
+        buf.invalidate(Frame::new(10, 10, 5, 5))
+            .invalidate(Frame::new(50, 20, 10, 10));
+        assert!(buf.is_damaged(&Frame::new(12, 12, 1, 1)));
+        assert!(buf.is_damaged(&Frame::new(55, 25, 1, 1)));
+        // Frames far apart are kept apart
+        assert!(!buf.is_damaged(&Frame::new(30, 15, 1, 1)));
+        assert!(!buf.is_damaged(&Frame::new(70, 70, 10, 10)));
+        assert_eq!(buf.take_damage(), vec![
+            Frame::new(10, 10, 5, 5),
+            Frame::new(50, 20, 10, 10)
+        ]);
+        assert!(buf.take_damage().is_empty());
+    }
+
+    #[test]
+    fn merges_only_overlapping_damage() {
+        let mut buf = FrameBuffer::new(100, 100, Color::black());
+        buf.take_damage();
+        buf.invalidate(Frame::new(0, 0, 10, 10))
+            .invalidate(Frame::new(30, 0, 10, 10))
+            .invalidate(Frame::new(80, 80, 5, 5))
+            // Overlaps the first two, and the union of those overlaps nothing else
+            .invalidate(Frame::new(5, 5, 30, 2));
+        assert_eq!(buf.take_damage(), vec![
+            Frame::new(80, 80, 5, 5),
+            Frame::new(0, 0, 40, 10)
+        ]);
+
+        for i in 0..=MAX_DAMAGE_FRAMES as u32 {
+            buf.invalidate(Frame::new(i * 10, 0, 5, 5));
+        }
+        assert_eq!(buf.take_damage(), vec![Frame::new(
+            0,
+            0,
+            MAX_DAMAGE_FRAMES as u32 * 10 + 5,
+            5
+        )]);
+    }
+
+    #[test]
+    fn draws_only_inside_damaged_frames() {
+        let (black, white) = (Color::black().to_rgba(), Color::white().to_rgba());
+        let mut buf = FrameBuffer::new(10, 10, Color::black());
+        buf.take_damage();
+        buf.invalidate(Frame::new(0, 0, 1, 1))
+            .invalidate(Frame::new(6, 6, 1, 1));
+        buf.clear(Color::white());
+        buf.put_line(0, 3, 9, white);
+
+        let px = |x: usize, y: usize| buf.as_slice()[x + y * 10];
+        assert_eq!(px(0, 0), white);
+        assert_eq!(px(7, 7), white);
+        assert_eq!(px(3, 3), black);
+        assert_eq!(px(4, 4), black);
+    }
 }
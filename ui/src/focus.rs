@@ -0,0 +1,173 @@
+use minifb::{Key, KeyRepeat, Window};
+
+/// A widget that can take keyboard focus
+pub trait Focusable {
+    fn is_focused(&self) -> bool;
+
+    fn set_focused(&mut self, is_focused: bool);
+
+    /// Called when Enter is pressed while the widget has focus
+    fn activate(&mut self) {}
+
+    /// Hidden and disabled widgets are skipped when moving focus
+    fn can_focus(&self) -> bool {
+        true
+    }
+}
+
+/// Keeps track of which of a screen's widgets has keyboard focus. Tab and Shift-Tab move focus through the widgets in
+/// the order they are passed to `update` and Enter activates the focused widget. Widgets focused with the mouse take
+/// focus from the others.
+#[derive(Debug, Default)]
+pub struct FocusManager {
+    focused: Option<usize>,
+    /// False until a focus set with `focus` has been passed on to the widget
+    is_applied: bool,
+}
+
+impl FocusManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Gives focus to the widget at `index` on the next update
+    pub fn focus(&mut self, index: Option<usize>) -> &mut Self {
+        self.focused = index;
+        self.is_applied = false;
+        self
+    }
+
+    pub fn focused(&self) -> Option<usize> {
+        self.focused
+    }
+
+    /// Call after the widgets have been updated with the window, so that focus changes from the mouse are seen
+    pub fn update(&mut self, window: &Window, widgets: &mut [&mut dyn Focusable]) {
+        let is_shift = window.is_key_down(Key::LeftShift) || window.is_key_down(Key::RightShift);
+        let is_tab = window.is_key_pressed(Key::Tab, KeyRepeat::Yes);
+        let is_enter = window.is_key_pressed(Key::Enter, KeyRepeat::No);
+        self.handle_input(widgets, is_tab, is_shift, is_enter);
+    }
+
+    fn handle_input(&mut self, widgets: &mut [&mut dyn Focusable], is_tab: bool, is_shift: bool, is_enter: bool) {
+        self.sync_with_mouse(widgets);
+        if is_tab {
+            self.focused = self.step(widgets, !is_shift);
+        }
+        for (i, widget) in widgets.iter_mut().enumerate() {
+            widget.set_focused(Some(i) == self.focused);
+        }
+        self.is_applied = true;
+        if is_enter && !is_tab {
+            if let Some(widget) = self.focused.and_then(|i| widgets.get_mut(i)) {
+                widget.activate();
+            }
+        }
+    }
+
+    /// Adopts a widget that was clicked on, or drops focus if the focused widget was clicked away from
+    fn sync_with_mouse(&mut self, widgets: &[&mut dyn Focusable]) {
+        let clicked = (0..widgets.len()).find(|i| widgets[*i].is_focused() && Some(*i) != self.focused);
+        if clicked.is_some() {
+            self.focused = clicked;
+        } else if self.is_applied {
+            let has_focus = self
+                .focused
+                .and_then(|i| widgets.get(i))
+                .map(|w| w.is_focused() && w.can_focus())
+                .unwrap_or(false);
+            if !has_focus {
+                self.focused = None;
+            }
+        }
+    }
+
+    /// The next widget that can take focus after the focused one, wrapping around
+    fn step(&self, widgets: &[&mut dyn Focusable], forward: bool) -> Option<usize> {
+        let len = widgets.len();
+        let start = match (self.focused, forward) {
+            (Some(i), true) => i + 1,
+            (Some(i), false) => i + len - 1,
+            (None, true) => 0,
+            (None, false) => len.saturating_sub(1),
+        };
+        (0..len)
+            .map(|n| {
+                if forward {
+                    (start + n) % len
+                } else {
+                    (start + len - n) % len
+                }
+            })
+            .find(|i| widgets[*i].can_focus())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[derive(Default)]
+    struct Widget {
+        is_focused: bool,
+        is_hidden: bool,
+        activations: u32,
+    }
+
+    impl Focusable for Widget {
+        fn is_focused(&self) -> bool {
+            self.is_focused
+        }
+
+        fn set_focused(&mut self, is_focused: bool) {
+            self.is_focused = is_focused;
+        }
+
+        fn activate(&mut self) {
+            self.activations += 1;
+        }
+
+        fn can_focus(&self) -> bool {
+            !self.is_hidden
+        }
+    }
+
+    #[test]
+    fn tabs_through_focusable_widgets() {
+        let (mut a, mut b, mut c) = (Widget::default(), Widget::default(), Widget::default());
+        b.is_hidden = true;
+        let mut focus = FocusManager::new();
+        focus.handle_input(&mut [&mut a, &mut b, &mut c], true, false, false);
+        assert_eq!(focus.focused(), Some(0));
+        focus.handle_input(&mut [&mut a, &mut b, &mut c], true, false, false);
+        assert_eq!(focus.focused(), Some(2));
+        focus.handle_input(&mut [&mut a, &mut b, &mut c], true, false, false);
+        assert_eq!(focus.focused(), Some(0));
+        focus.handle_input(&mut [&mut a, &mut b, &mut c], true, true, false);
+        assert_eq!(focus.focused(), Some(2));
+        assert!(c.is_focused && !a.is_focused);
+
+        focus.handle_input(&mut [&mut a, &mut b, &mut c], false, false, true);
+        assert_eq!(c.activations, 1);
+    }
+
+    #[test]
+    fn follows_mouse_focus() {
+        let (mut a, mut b) = (Widget::default(), Widget::default());
+        let mut focus = FocusManager::new();
+        focus.focus(Some(0));
+        focus.handle_input(&mut [&mut a, &mut b], false, false, false);
+        assert!(a.is_focused);
+
+        // b was clicked on
+        b.is_focused = true;
+        focus.handle_input(&mut [&mut a, &mut b], false, false, false);
+        assert_eq!(focus.focused(), Some(1));
+        assert!(!a.is_focused);
+
+        // Clicked away from b
+        b.is_focused = false;
+        focus.handle_input(&mut [&mut a, &mut b], false, false, false);
+        assert_eq!(focus.focused(), None);
+    }
+}
@@ -0,0 +1,173 @@
+use std::{collections::HashMap, fs, sync::Mutex};
+
+use fontdue::{FontSettings, Metrics};
+use once_cell::sync::{Lazy, OnceCell};
+
+use crate::{color::Color, drawable::FrameBuffer};
+
+/// The text size used by components, in pixels per em
+pub const DEFAULT_SIZE: f32 = 16.0;
+
+pub static FONT: Lazy<Font> = Lazy::new(Font::initialize);
+
+/// System fonts used for characters the bundled font doesn't cover, e.g. CJK names. Each is loaded the first time
+/// such a character is drawn, since some are large. Missing files are skipped.
+const FALLBACK_PATHS: [&str; 6] = [
+    "/usr/share/fonts/opentype/noto/NotoSansCJK-Regular.ttc",
+    "/usr/share/fonts/truetype/droid/DroidSansFallbackFull.ttf",
+    "/usr/share/fonts/truetype/noto/NotoSansSymbols2-Regular.ttf",
+    "/System/Library/Fonts/Supplemental/Arial Unicode.ttf",
+    "C:\\Windows\\Fonts\\msyh.ttc",
+    "C:\\Windows\\Fonts\\seguisym.ttf",
+];
+
+/// Rasterises text from the bundled DejaVu Sans, falling back to system fonts for characters it doesn't have.
+/// Glyphs are cached per character and size.
+pub struct Font {
+    bundled: fontdue::Font,
+    /// One per fallback path, None if the file is missing or invalid
+    fallbacks: [OnceCell<Option<fontdue::Font>>; FALLBACK_PATHS.len()],
+    glyphs: Mutex<HashMap<(char, u32), Glyph>>,
+}
+
+struct Glyph {
+    metrics: Metrics,
+    coverage: Vec<u8>,
+}
+
+impl Font {
+    pub fn initialize() -> Self {
+        Self {
+            bundled: load_face(include_bytes!("../assets/fonts/DejaVuSans.ttf").as_slice())
+                .expect("bundled font is valid"),
+            fallbacks: Default::default(),
+            glyphs: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// The height of a line of text, from the top of the tallest glyph to the bottom of the lowest
+    pub fn line_height(&self, size: f32) -> u32 {
+        let (ascent, descent) = self.ascent_descent(size);
+        (ascent - descent).ceil() as u32
+    }
+
+    /// The width of `text` when drawn at `size`
+    pub fn measure(&self, text: &str, size: f32) -> u32 {
+        let mut width = 0.0;
+        let mut prev = None;
+        for ch in text.chars().filter(|ch| !ch.is_control()) {
+            let face = self.face_for(ch);
+            width += self.kerning(face, prev, ch, size) + face.metrics(ch, size).advance_width;
+            prev = Some(ch);
+        }
+        width.ceil() as u32
+    }
+
+    /// Draws a single line of text with its top left corner at (x, y)
+    pub fn draw_string(&self, text: &str, x: u32, y: u32, size: f32, color: Color, buf: &mut FrameBuffer) {
+        let (ascent, _) = self.ascent_descent(size);
+        let baseline = y as i32 + ascent.round() as i32;
+        let mut glyphs = self.glyphs.lock().unwrap();
+        let mut pen = x as f32;
+        let mut prev = None;
+        for ch in text.chars().filter(|ch| !ch.is_control()) {
+            let face = self.face_for(ch);
+            pen += self.kerning(face, prev, ch, size);
+            prev = Some(ch);
+
+            let glyph = glyphs.entry((ch, size.to_bits())).or_insert_with(|| {
+                let (metrics, coverage) = face.rasterize(ch, size);
+                Glyph { metrics, coverage }
+            });
+            let metrics = glyph.metrics;
+            let left = pen.round() as i32 + metrics.xmin;
+            let top = baseline - metrics.ymin - metrics.height as i32;
+            for (i, alpha) in glyph.coverage.iter().enumerate() {
+                let px = left + (i % metrics.width) as i32;
+                let py = top + (i / metrics.width) as i32;
+                if px >= 0 && py >= 0 {
+                    buf.blend_pixel(px as u32, py as u32, color, *alpha);
+                }
+            }
+            pen += metrics.advance_width;
+        }
+    }
+
+    fn face_for(&self, ch: char) -> &fontdue::Font {
+        if self.bundled.lookup_glyph_index(ch) != 0 {
+            return &self.bundled;
+        }
+        FALLBACK_PATHS
+            .iter()
+            .zip(&self.fallbacks)
+            .filter_map(|(path, face)| face.get_or_init(|| load_fallback(path)).as_ref())
+            .find(|face| face.lookup_glyph_index(ch) != 0)
+            // The bundled font draws a missing glyph box
+            .unwrap_or(&self.bundled)
+    }
+
+    fn kerning(&self, face: &fontdue::Font, prev: Option<char>, ch: char, size: f32) -> f32 {
+        prev.and_then(|prev| face.horizontal_kern(prev, ch, size))
+            .unwrap_or(0.0)
+    }
+
+    fn ascent_descent(&self, size: f32) -> (f32, f32) {
+        self.bundled
+            .horizontal_line_metrics(size)
+            .map(|m| (m.ascent, m.descent))
+            .unwrap_or((size, 0.0))
+    }
+}
+
+impl std::fmt::Debug for Font {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let loaded = self.fallbacks.iter().filter(|face| face.get().is_some()).count();
+        f.debug_struct("Font").field("loaded_fallbacks", &loaded).finish()
+    }
+}
+
+fn load_fallback(path: &str) -> Option<fontdue::Font> {
+    let data = fs::read(path).ok()?;
+    match load_face(&data) {
+        Ok(face) => Some(face),
+        Err(err) => {
+            log::warn!("Failed to load fallback font {}: {}", path, err);
+            None
+        },
+    }
+}
+
+fn load_face(data: &[u8]) -> anyhow::Result<fontdue::Font> {
+    fontdue::Font::from_bytes(data, FontSettings::default()).map_err(|err| anyhow::anyhow!(err))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn measures_text() {
+        let font = Font::initialize();
+        assert_eq!(font.measure("", DEFAULT_SIZE), 0);
+        let narrow = font.measure("iiii", DEFAULT_SIZE);
+        let wide = font.measure("WWWW", DEFAULT_SIZE);
+        assert!(narrow > 0 && narrow < wide);
+        assert!(font.measure("Hello", DEFAULT_SIZE * 2.0) > font.measure("Hello", DEFAULT_SIZE));
+        assert!(font.line_height(DEFAULT_SIZE) >= DEFAULT_SIZE as u32);
+    }
+
+    #[test]
+    fn loads_fallbacks_only_for_missing_glyphs() {
+        let font = Font::initialize();
+        font.measure("Hello, wörld", DEFAULT_SIZE);
+        assert!(font.fallbacks.iter().all(|face| face.get().is_none()));
+    }
+
+    #[test]
+    fn draws_punctuation_and_accents() {
+        let font = Font::initialize();
+        let mut buf = FrameBuffer::new(100, 30, Color::black());
+        font.draw_string("(é/ü)", 0, 0, DEFAULT_SIZE, Color::white(), &mut buf);
+        assert!(buf.as_slice().iter().any(|px| *px != Color::black().to_rgba()));
+    }
+}
@@ -1,8 +1,14 @@
-use std::ops::Index;
+use std::{
+    ops::Index,
+    time::{SystemTime, UNIX_EPOCH},
+};
 
 use serde::{de::Error, Deserialize, Deserializer, Serialize, Serializer};
-use tanton::Player;
+use tanton::{BitMove, Player};
 use tari_comms::types::CommsPublicKey;
+use tari_crypto::tari_utilities::encoding::Base58;
+
+use crate::board;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Game {
@@ -14,6 +20,72 @@ pub struct Game {
     pub player: Player,
     pub result: GameResult,
     pub last_activity: u64,
+    /// Spectators approved by both players. Moves are sent to each of them.
+    #[serde(default)]
+    pub spectators: Vec<CommsPublicKey>,
+    /// Spectators we invited that are waiting for their own and the opponent's approval
+    #[serde(default)]
+    pub pending_spectators: Vec<CommsPublicKey>,
+    /// Spectators the opponent invited that are waiting for our approval
+    #[serde(default)]
+    pub spectator_requests: Vec<CommsPublicKey>,
+    /// Set if we are spectating this game rather than playing it
+    #[serde(default)]
+    pub watching: Option<Watching>,
+    /// Set if the game was paired by a tournament organiser
+    #[serde(default)]
+    pub tournament_id: Option<u32>,
+    /// Replies to send automatically when the opponent plays a matching move
+    #[serde(default)]
+    pub conditional_moves: Vec<ConditionalMove>,
+    /// The position `moves` are played from
+    #[serde(default = "initial_board")]
+    pub start_fen: String,
+    #[serde(default)]
+    pub moves: Vec<u16>,
+    /// A takeback the opponent asked for, waiting for our decision
+    #[serde(default)]
+    pub takeback_request: Option<Takeback>,
+    /// A takeback we asked for, waiting for the opponent's decision
+    #[serde(default)]
+    pub sent_takeback: Option<Takeback>,
+    /// The `seq` the opponent offered a draw at
+    #[serde(default)]
+    pub draw_offer: Option<u32>,
+    /// The `seq` we offered a draw at
+    #[serde(default)]
+    pub sent_draw_offer: Option<u32>,
+    /// Our move shown on the board waiting to be confirmed, kept here so it isn't lost when leaving the game
+    #[serde(default)]
+    pub pending_move: Option<u16>,
+}
+
+/// A request to undo the last `plies` moves, made when the game was at `seq`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Takeback {
+    pub seq: u32,
+    pub plies: u32,
+}
+
+/// If the opponent plays `trigger`, reply with `reply` and continue with `next` for their following move
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConditionalMove {
+    pub trigger: u16,
+    pub reply: u16,
+    #[serde(default)]
+    pub next: Vec<ConditionalMove>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Watching {
+    pub white: CommsPublicKey,
+    pub black: CommsPublicKey,
+}
+
+impl Watching {
+    pub fn is_player(&self, public_key: &CommsPublicKey) -> bool {
+        self.white == *public_key || self.black == *public_key
+    }
 }
 
 #[derive(Debug, Clone, Copy, Serialize, Deserialize)]
@@ -26,9 +98,152 @@ pub enum GameResult {
 }
 
 impl Game {
+    pub fn new(id: u32, opponent: CommsPublicKey, player: Player, board_fen: String) -> Self {
+        Self {
+            id,
+            opponent,
+            start_fen: board_fen.clone(),
+            board_fen,
+            seq: 0,
+            player,
+            result: GameResult::None,
+            last_activity: current_timestamp(),
+            spectators: Vec::new(),
+            pending_spectators: Vec::new(),
+            spectator_requests: Vec::new(),
+            watching: None,
+            tournament_id: None,
+            conditional_moves: Vec::new(),
+            moves: Vec::new(),
+            takeback_request: None,
+            sent_takeback: None,
+            draw_offer: None,
+            sent_draw_offer: None,
+            pending_move: None,
+        }
+    }
+
+    /// Records a move played from the current position. Must be called before `board_fen` is updated.
+    pub fn record_move(&mut self, mv: u16) {
+        if board::replay(&self.start_fen, &self.moves).as_deref() != Some(self.board_fen.as_str()) {
+            // Our history is missing moves, e.g. the board was updated by a sync. Start again from here.
+            self.start_fen = self.board_fen.clone();
+            self.moves.clear();
+        }
+        self.moves.push(mv);
+        // Takebacks and draws are only agreed for the position they were asked in
+        self.takeback_request = None;
+        self.sent_takeback = None;
+        self.draw_offer = None;
+        self.sent_draw_offer = None;
+    }
+
+    /// Returns the takeback that makes it our move again: our last move, and the opponent's reply if they have made one
+    pub fn takeback(&self) -> Takeback {
+        let (_, turn) = board::move_number(&self.board_fen);
+        Takeback {
+            seq: self.seq,
+            plies: if turn == self.player { 2 } else { 1 },
+        }
+    }
+
+    /// Undoes the last `plies` moves. Returns false if they are not in our move history.
+    pub fn take_back(&mut self, plies: u32) -> bool {
+        let plies = plies as usize;
+        if plies == 0 || plies > self.moves.len() || plies > self.seq as usize {
+            return false;
+        }
+        if board::replay(&self.start_fen, &self.moves).as_deref() != Some(self.board_fen.as_str()) {
+            return false;
+        }
+        let remaining = self.moves.len() - plies;
+        let board_fen = match board::replay(&self.start_fen, &self.moves[..remaining]) {
+            Some(fen) => fen,
+            None => return false,
+        };
+        self.moves.truncate(remaining);
+        self.board_fen = board_fen;
+        self.seq -= plies as u32;
+        self.last_activity = current_timestamp();
+        // Prepared replies were for a position that no longer exists
+        self.conditional_moves.clear();
+        self.takeback_request = None;
+        self.sent_takeback = None;
+        self.draw_offer = None;
+        self.sent_draw_offer = None;
+        true
+    }
+
     pub fn has_completed(&self) -> bool {
         !matches!(self.result, GameResult::None)
     }
+
+    /// Updates the result from the current board position. Returns true if the game has just completed.
+    pub fn update_result(&mut self) -> bool {
+        if self.has_completed() {
+            return false;
+        }
+        self.result = board::game_result(&self.board_fen);
+        self.has_completed()
+    }
+
+    /// Adds a line of alternating opponent moves and our replies, starting with an opponent move. A reply already
+    /// queued for the same opponent move is replaced.
+    pub fn add_conditional_line(&mut self, line: &[u16]) {
+        let mut branches = &mut self.conditional_moves;
+        for pair in line.chunks_exact(2) {
+            let (trigger, reply) = (pair[0], pair[1]);
+            let idx = match branches.iter().position(|c| board::is_same_move(c.trigger, trigger)) {
+                Some(idx) => {
+                    if !board::is_same_move(branches[idx].reply, reply) {
+                        branches[idx].reply = reply;
+                        branches[idx].next.clear();
+                    }
+                    idx
+                },
+                None => {
+                    branches.push(ConditionalMove {
+                        trigger,
+                        reply,
+                        next: Vec::new(),
+                    });
+                    branches.len() - 1
+                },
+            };
+            branches = &mut branches[idx].next;
+        }
+    }
+
+    /// Returns the queued reply to the opponent's move and advances to the following replies. The remaining replies
+    /// are discarded if the opponent played something we did not prepare for.
+    pub fn take_conditional_reply(&mut self, opponent_move: u16) -> Option<BitMove> {
+        let branches = std::mem::take(&mut self.conditional_moves);
+        let branch = branches
+            .into_iter()
+            .find(|c| board::is_same_move(c.trigger, opponent_move))?;
+        self.conditional_moves = branch.next;
+        Some(BitMove::new(branch.reply))
+    }
+
+    /// Returns the number of distinct lines that have been prepared
+    pub fn conditional_lines(&self) -> usize {
+        fn count(branches: &[ConditionalMove]) -> usize {
+            branches.iter().map(|c| count(&c.next).max(1)).sum()
+        }
+        count(&self.conditional_moves)
+    }
+
+    pub fn is_spectating(&self) -> bool {
+        self.watching.is_some()
+    }
+
+    /// Returns true if `public_key` is allowed to send moves for this game
+    pub fn is_move_sender(&self, public_key: &CommsPublicKey) -> bool {
+        match self.watching {
+            Some(ref watching) => watching.is_player(public_key),
+            None => self.opponent == *public_key,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
@@ -37,6 +252,10 @@ pub struct GameCollection {
 }
 
 impl GameCollection {
+    pub fn get(&self, game_id: u32) -> Option<&Game> {
+        self.games.iter().find(|g| g.id == game_id)
+    }
+
     pub fn get_mut(&mut self, game_id: u32) -> Option<&mut Game> {
         self.games.iter_mut().find(|g| g.id == game_id)
     }
@@ -83,6 +302,45 @@ impl Index<usize> for GameCollection {
     }
 }
 
+/// Every completed game we played. Unlike `GameCollection`, games are never cleaned up so ratings can be recomputed
+/// from the full history.
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct GameArchive {
+    games: Vec<Game>,
+}
+
+impl GameArchive {
+    /// Archives a completed game. Returns false if the game is still in progress, spectated or already archived.
+    pub fn add(&mut self, game: &Game) -> bool {
+        if !game.has_completed() || game.is_spectating() || self.contains(game.id) {
+            return false;
+        }
+        self.games.push(game.clone());
+        true
+    }
+
+    pub fn contains(&self, game_id: u32) -> bool {
+        self.games.iter().any(|g| g.id == game_id)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Game> {
+        self.games.iter()
+    }
+}
+
+fn initial_board() -> String {
+    board::INITIAL_BOARD.to_string()
+}
+
+pub fn current_timestamp() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs()
+}
+
+/// The first `len` characters of the base58 public key, enough to tell players apart where the full key doesn't fit
+pub fn short_key(public_key: &CommsPublicKey, len: usize) -> String {
+    public_key.to_base58().chars().take(len).collect()
+}
+
 fn serialize_player<S: Serializer>(player: &Player, ser: S) -> Result<S::Ok, S::Error> {
     ser.serialize_u8(*player as u8)
 }
@@ -3,24 +3,36 @@ use std::{
     path::PathBuf,
 };
 
-use minifb::{MouseButton, MouseMode, Window};
+use minifb::{Key, KeyRepeat, MouseButton, MouseMode, Window};
 use tanton::{
     core::piece_move::{MoveFlag, PreMoveInfo},
-    BitMove, PieceType, Player,
+    BitMove,
+    PieceType,
+    Player,
+    SQ,
 };
 use tari_comms::types::CommsPublicKey;
 
 use crate::{
-    bitmap::Bitmap,
-    board::ChessBoard,
+    board::{self, ChessBoard},
+    clipboard::Clipboard,
     color::Color,
-    components::{Button, Label},
-    drawable::{Drawable, FrameBuffer},
+    components::{Button, Label, MoveList, TextBox},
+    drawable::{Drawable, FrameBuffer, Widget},
+    font::{DEFAULT_SIZE, FONT},
+    game::short_key,
+    layout::{Anchor, Padding, Stack},
     rect::{Frame, Rect},
-    sprite::SpriteSheet,
-    start_screen::Drawables,
+    theme::Theme,
 };
 
+/// Width of the side panel to the right of the board
+const PANEL_WIDTH: u32 = 304;
+const MIN_SQUARE_SIZE: u32 = 20;
+const MIN_MOVE_LIST_HEIGHT: u32 = 66;
+/// Alpha of the shade over the board once the game is over
+const GAME_OVER_SHADE: u8 = 0xa0;
+
 #[derive(Debug)]
 pub struct GameScreen {
     config: GameConfig,
@@ -29,9 +41,63 @@ pub struct GameScreen {
     state: State,
     board: ChessBoard,
     floating_piece: Option<(u32, u32)>,
+    /// Where the dragged piece was last drawn, redrawn once it moves
+    floating_frame: Option<Frame>,
+    is_game_over_drawn: bool,
     opponent: CommsPublicKey,
     last_move_played: Option<BitMove>,
     back_button: Button,
+    flip_button: Button,
+    is_spectating: bool,
+    spectator_request: Option<CommsPublicKey>,
+    spectator_decision: Option<(CommsPublicKey, bool)>,
+    takeback_button: Button,
+    accept_takeback_button: Button,
+    decline_takeback_button: Button,
+    is_takeback_requested: bool,
+    is_takeback_sent: bool,
+    /// The number of moves the opponent asked to take back, waiting for our decision
+    takeback_request: Option<u32>,
+    takeback_decision: Option<bool>,
+    allow_spectator_button: Button,
+    deny_spectator_button: Button,
+    premove: Option<(SQ, SQ)>,
+    /// Require moves to be confirmed before they are sent
+    confirm_moves: bool,
+    /// A move shown on the board awaiting confirmation, and the position to restore if it is undone
+    pending_move: Option<(BitMove, String, Option<BitMove>)>,
+    confirm_move_button: Button,
+    undo_move_button: Button,
+    /// Moves played while preparing a conditional line, alternating between the opponent and us
+    planned_line: Option<Vec<BitMove>>,
+    /// The live position to return to once we are done preparing a conditional line or viewing history
+    live_position: Option<(String, Option<BitMove>)>,
+    /// The number of moves played in the historic position being viewed, if any
+    viewed_ply: Option<usize>,
+    history_back_button: Button,
+    history_forward_button: Button,
+    history_live_button: Button,
+    saved_line: Option<Vec<u16>>,
+    clear_conditional_requested: bool,
+    conditional_lines: usize,
+    plan_button: Button,
+    save_line_button: Button,
+    cancel_line_button: Button,
+    clear_conditional_button: Button,
+    start_fen: String,
+    moves: Vec<u16>,
+    move_list: MoveList,
+    move_input: TextBox,
+    play_typed_button: Button,
+    id_label: Label,
+    move_label: Label,
+    turn_label: Label,
+    status_label: Label,
+    conditional_label: Label,
+    banner_label: Label,
+    request_label: Label,
+    request_detail_label: Label,
+    takeback_sent_label: Label,
 }
 
 impl GameScreen {
@@ -39,37 +105,378 @@ impl GameScreen {
         id: u32,
         seq: u32,
         config: GameConfig,
+        clipboard: Clipboard,
         player: Player,
         opponent: CommsPublicKey,
         board_fen: &str,
     ) -> Self {
         let mut board = ChessBoard::new(
-            Frame {
-                x: 0,
-                y: 0,
-                w: config.window_height,
-                h: config.window_height,
-            },
-            config.light_color,
-            config.dark_color,
-            init_pieces_sprite(),
+            Frame::new(0, 0, 0, 0),
+            config.theme.colours,
+            config.theme.piece_sprites(),
             player,
         );
         board.set_board_state(board_fen);
-        let mut back_button = Button::new(Rect::new(config.window_height + 10, 50, 100, 20, Color::white()));
+        let mut back_button = Button::new(Rect::new(0, 0, 100, 20, Color::white()));
         back_button.set_text("Back");
+        let mut flip_button = Button::new(Rect::new(0, 0, 64, 20, Color::white()));
+        flip_button.set_text("Flip");
+        let mut allow_spectator_button = Button::new(Rect::new(0, 0, 100, 20, Color::white()));
+        allow_spectator_button.set_text("Allow");
+        let mut deny_spectator_button = Button::new(Rect::new(0, 0, 100, 20, Color::white()));
+        deny_spectator_button.set_text("Deny");
+        let mut takeback_button = Button::new(Rect::new(0, 0, 140, 20, Color::white()));
+        takeback_button.set_text("Takeback");
+        let mut accept_takeback_button = Button::new(Rect::new(0, 0, 100, 20, Color::white()));
+        accept_takeback_button.set_text("Accept");
+        let mut decline_takeback_button = Button::new(Rect::new(0, 0, 100, 20, Color::white()));
+        decline_takeback_button.set_text("Decline");
+        let mut plan_button = Button::new(Rect::new(0, 0, 200, 20, Color::white()));
+        plan_button.set_text("Prepare reply");
+        let mut save_line_button = Button::new(Rect::new(0, 0, 100, 20, Color::white()));
+        save_line_button.set_text("Save");
+        let mut cancel_line_button = Button::new(Rect::new(0, 0, 100, 20, Color::white()));
+        cancel_line_button.set_text("Cancel");
+        let mut confirm_move_button = Button::new(Rect::new(0, 0, 100, 20, Color::white()));
+        confirm_move_button.set_text("Confirm");
+        let mut undo_move_button = Button::new(Rect::new(0, 0, 100, 20, Color::white()));
+        undo_move_button.set_text("Undo");
+        let mut clear_conditional_button = Button::new(Rect::new(0, 0, 200, 20, Color::white()));
+        clear_conditional_button.set_text("Clear replies");
+        let mut history_back_button = Button::new(Rect::new(0, 0, 80, 20, Color::white()));
+        history_back_button.set_text("Prev");
+        let mut history_forward_button = Button::new(Rect::new(0, 0, 80, 20, Color::white()));
+        history_forward_button.set_text("Next");
+        let mut history_live_button = Button::new(Rect::new(0, 0, 80, 20, Color::white()));
+        history_live_button.set_text("Live");
+        let mut move_input = TextBox::new(Frame::new(0, 0, 180, 28), clipboard);
+        move_input.set_placeholder("e.g. e4 or g1f3").set_max_length(Some(10));
+        let mut play_typed_button = Button::new(Rect::new(0, 0, 74, 20, Color::white()));
+        play_typed_button.set_text("Play");
+        let move_list = MoveList::new(Frame::new(0, 0, 274, 0));
+        let panel_label = |color| {
+            let mut label = Label::new(Frame::new(0, 0, 274, 20));
+            label.set_text_color(color).set_bg_color(Color::black());
+            label
+        };
+        let mut banner_label = Label::new(Frame::new(0, 0, 200, 30));
+        banner_label
+            .set_text_color(Color::black())
+            .set_bg_color(Color::yellow());
+        let mut takeback_sent_label = panel_label(Color::light_grey());
+        takeback_sent_label.set_text("Takeback sent");
 
-        Self {
+        let mut screen = Self {
             config,
             id,
             seq,
             state: State::default(),
             board,
             floating_piece: None,
+            floating_frame: None,
+            is_game_over_drawn: false,
             opponent,
             last_move_played: None,
             back_button,
+            flip_button,
+            is_spectating: false,
+            spectator_request: None,
+            spectator_decision: None,
+            takeback_button,
+            accept_takeback_button,
+            decline_takeback_button,
+            is_takeback_requested: false,
+            is_takeback_sent: false,
+            takeback_request: None,
+            takeback_decision: None,
+            allow_spectator_button,
+            deny_spectator_button,
+            premove: None,
+            confirm_moves: false,
+            pending_move: None,
+            confirm_move_button,
+            undo_move_button,
+            planned_line: None,
+            live_position: None,
+            viewed_ply: None,
+            history_back_button,
+            history_forward_button,
+            history_live_button,
+            saved_line: None,
+            clear_conditional_requested: false,
+            conditional_lines: 0,
+            plan_button,
+            save_line_button,
+            cancel_line_button,
+            clear_conditional_button,
+            start_fen: String::new(),
+            moves: Vec::new(),
+            move_list,
+            move_input,
+            play_typed_button,
+            id_label: panel_label(Color::white()),
+            move_label: panel_label(Color::white()),
+            turn_label: panel_label(Color::white()),
+            status_label: panel_label(Color::white()),
+            conditional_label: panel_label(Color::white()),
+            banner_label,
+            request_label: panel_label(Color::white()),
+            request_detail_label: panel_label(Color::light_grey()),
+            takeback_sent_label,
+        };
+        screen.layout();
+        screen
+    }
+
+    /// Fits the screen to a new window size
+    pub fn resize(&mut self, window_width: u32, window_height: u32) -> &mut Self {
+        self.config.window_width = window_width;
+        self.config.window_height = window_height;
+        self.layout();
+        self
+    }
+
+    /// Sizes the board to fit the window next to the side panel, and stacks the panel's rows to the right of it
+    fn layout(&mut self) {
+        let board_size = board_size(self.config.window_width, self.config.window_height);
+        self.board.set_frame(Frame::new(0, 0, board_size, board_size));
+
+        let panel = Frame::new(board_size, 0, PANEL_WIDTH, self.config.window_height);
+        let mut column = Stack::column(
+            Padding {
+                right: 20,
+                ..Padding::all(10)
+            }
+            .apply(panel),
+        );
+        column.set_spacing(10);
+
+        let mut row = button_row(column.next(30));
+        self.banner_label.set_frame(row.next(200));
+        self.flip_button.set_frame(Anchor::Left.place(row.next(64), 64, 20));
+        let mut row = button_row(column.next(20));
+        self.back_button.set_frame(row.next(100));
+        // Only one of these is shown at a time
+        let takeback = row.next(140);
+        self.takeback_button.set_frame(takeback);
+        self.takeback_sent_label.set_frame(takeback);
+
+        for label in [
+            &mut self.id_label,
+            &mut self.move_label,
+            &mut self.turn_label,
+            &mut self.status_label,
+        ] {
+            label.set_frame(column.next(20));
+        }
+        let mut row = button_row(column.next(20));
+        self.history_back_button.set_frame(row.next(80));
+        self.history_forward_button.set_frame(row.next(80));
+        self.history_live_button.set_frame(row.next(80));
+
+        self.request_label.set_frame(column.next(20));
+        self.request_detail_label.set_frame(column.next(20));
+        // A spectator request and a takeback request share the row, one is shown at a time
+        let mut row = button_row(column.next(20));
+        let (accept, decline) = (row.next(100), row.next(100));
+        self.allow_spectator_button.set_frame(accept);
+        self.accept_takeback_button.set_frame(accept);
+        self.deny_spectator_button.set_frame(decline);
+        self.decline_takeback_button.set_frame(decline);
+
+        column.skip(10);
+        self.conditional_label.set_frame(column.next(20));
+        // Preparing replies and confirming a move use the same row
+        let mut row = button_row(column.next(20));
+        let first = row.next(100);
+        self.plan_button.set_frame(Frame { w: 200, ..first });
+        self.confirm_move_button.set_frame(first);
+        self.undo_move_button.set_frame(row.next(100));
+        let mut row = button_row(column.next(20));
+        self.save_line_button.set_frame(row.next(100));
+        self.cancel_line_button.set_frame(row.next(100));
+        self.clear_conditional_button
+            .set_frame(Anchor::Left.place(column.next(20), 200, 20));
+
+        let mut row = button_row(column.next(28));
+        self.move_input.set_frame(row.next(180));
+        self.play_typed_button
+            .set_frame(Anchor::Left.place(row.next(74), 74, 20));
+        // The move list takes the rest of the panel's height
+        let move_list = column.rest();
+        self.move_list.set_frame(Frame {
+            h: move_list.h.max(MIN_MOVE_LIST_HEIGHT),
+            ..move_list
+        });
+    }
+
+    /// Brings the side panel's text and which of its buttons are shown up to date with the game
+    fn refresh_panel(&mut self) {
+        self.id_label.set_text(format!("ID: {}", self.id));
+        self.move_label.set_text(format!("MOVE: {}", self.seq));
+        self.turn_label.set_text(format!("Turn: {}", self.board.turn()));
+        self.status_label
+            .set_text(format!("Status: {}", self.state.game_status()));
+        self.conditional_label.set_text(match self.planned_line {
+            Some(ref line) if line.len() % 2 == 0 => "Play their move".to_string(),
+            Some(_) => "Play your reply".to_string(),
+            None if self.pending_move.is_some() => "Confirm your move".to_string(),
+            None => format!("Prepared replies: {}", self.conditional_lines),
+        });
+
+        match self.viewed_ply {
+            Some(ply) => {
+                self.banner_label
+                    .set_text(format!("HISTORY {} OF {}", ply, self.moves.len()))
+                    .set_visible(true);
+            },
+            None => {
+                self.banner_label.set_text("SPECTATING").set_visible(self.is_spectating);
+            },
+        }
+
+        let request = match (self.takeback_request, &self.spectator_request) {
+            (Some(1), _) => Some(("Takeback request", "Undo 1 move".to_string())),
+            (Some(plies), _) => Some(("Takeback request", format!("Undo {} moves", plies))),
+            (None, Some(spectator)) => Some(("Spectator request", short_key(spectator, 24))),
+            (None, None) => None,
+        };
+        self.request_label.set_visible(request.is_some());
+        self.request_detail_label.set_visible(request.is_some());
+        if let Some((title, detail)) = request {
+            self.request_label.set_text(title);
+            self.request_detail_label.set_text(detail);
+        }
+
+        let can_request_takeback = !self.is_spectating && self.takeback_request.is_none();
+        self.takeback_sent_label
+            .set_visible(can_request_takeback && self.is_takeback_sent);
+        self.takeback_button
+            .set_visible(can_request_takeback && !self.is_takeback_sent && !self.moves.is_empty());
+        let is_takeback_requested = !self.is_spectating && self.takeback_request.is_some();
+        self.accept_takeback_button.set_visible(is_takeback_requested);
+        self.decline_takeback_button.set_visible(is_takeback_requested);
+        // The spectator buttons share a row with the takeback buttons, so one request is shown at a time
+        let is_spectator_requested = self.shown_spectator_request().is_some();
+        self.allow_spectator_button.set_visible(is_spectator_requested);
+        self.deny_spectator_button.set_visible(is_spectator_requested);
+        for button in [
+            &mut self.history_back_button,
+            &mut self.history_forward_button,
+            &mut self.history_live_button,
+        ] {
+            button.set_visible(self.planned_line.is_none());
+        }
+
+        // Historic positions and finished games are read-only
+        let is_playable = !self.is_spectating && self.viewed_ply.is_none() && !self.is_game_over_shown();
+        let is_pending = self.pending_move.is_some();
+        self.confirm_move_button.set_visible(is_playable && is_pending);
+        self.undo_move_button.set_visible(is_playable && is_pending);
+        let is_playing = is_playable && !is_pending;
+        self.move_input.set_visible(is_playing);
+        self.play_typed_button.set_visible(is_playing);
+        let is_planning = self.planned_line.is_some();
+        self.save_line_button.set_visible(is_playing && is_planning);
+        self.cancel_line_button.set_visible(is_playing && is_planning);
+        self.plan_button
+            .set_visible(is_playing && !is_planning && !self.board.is_our_turn());
+        self.clear_conditional_button.set_visible(is_playing && !is_planning);
+    }
+
+    /// Pieces can be moved on the board, i.e. the move input is shown
+    fn is_board_playable(&self) -> bool {
+        !self.is_spectating && self.viewed_ply.is_none() && !self.is_game_over_shown() && self.pending_move.is_none()
+    }
+
+    /// Where the piece being dragged is drawn, following the mouse
+    fn floating_piece_frame(&self) -> Option<Frame> {
+        if !self.state.is_left_mouse_down || !self.is_board_playable() {
+            return None;
         }
+        let (offset_x, offset_y) = self.floating_piece?;
+        let (mouse_x, mouse_y) = self.state.mouse_pos?;
+        let square_size = self.board.square_size();
+        Some(Frame::new(
+            mouse_x.saturating_sub(offset_x),
+            mouse_y.saturating_sub(offset_y),
+            square_size,
+            square_size,
+        ))
+    }
+
+    /// A spectating game screen is read-only and moves can not be made
+    pub fn set_spectating(&mut self, is_spectating: bool) -> &mut Self {
+        self.is_spectating = is_spectating;
+        self
+    }
+
+    pub fn is_spectating(&self) -> bool {
+        self.is_spectating
+    }
+
+    /// Moves are shown on the board with Confirm and Undo buttons and only sent once confirmed
+    pub fn set_confirm_moves(&mut self, confirm_moves: bool) -> &mut Self {
+        self.confirm_moves = confirm_moves;
+        self
+    }
+
+    pub fn set_move_hints(&mut self, show_move_hints: bool) -> &mut Self {
+        self.board.set_show_move_hints(show_move_hints);
+        self
+    }
+
+    pub fn set_theme(&mut self, theme: &Theme) -> &mut Self {
+        self.board.set_theme(theme.colours, theme.piece_sprites());
+        self.config.theme = theme.clone();
+        self
+    }
+
+    /// Sets the spectator the opponent wants to invite, to be approved or denied by us. It is shown once there is no
+    /// takeback request.
+    pub fn set_spectator_request(&mut self, spectator: Option<CommsPublicKey>) -> &mut Self {
+        self.spectator_request = spectator;
+        self
+    }
+
+    fn shown_spectator_request(&self) -> Option<&CommsPublicKey> {
+        self.spectator_request
+            .as_ref()
+            .filter(|_| self.takeback_request.is_none())
+    }
+
+    pub fn take_spectator_decision(&mut self) -> Option<(CommsPublicKey, bool)> {
+        self.spectator_decision.take()
+    }
+
+    /// Sets the number of moves the opponent wants to take back, to be accepted or declined by us
+    pub fn set_takeback_request(&mut self, plies: Option<u32>) -> &mut Self {
+        self.takeback_request = plies;
+        self
+    }
+
+    pub fn take_takeback_decision(&mut self) -> Option<bool> {
+        self.takeback_decision.take()
+    }
+
+    /// Hides the takeback button while our request is waiting for the opponent
+    pub fn set_takeback_sent(&mut self, is_takeback_sent: bool) -> &mut Self {
+        self.is_takeback_sent = is_takeback_sent;
+        self
+    }
+
+    pub fn take_takeback_requested(&mut self) -> bool {
+        std::mem::take(&mut self.is_takeback_requested)
+    }
+
+    /// Rolls the board back to `fen` once a takeback was agreed
+    pub fn take_back(&mut self, fen: &str, last_move: Option<BitMove>, seq: u32) -> &mut Self {
+        self.stop_preparing_line();
+        self.clear_premove();
+        self.cancel_pick_up();
+        self.set_board_state(fen, None).set_seq(seq);
+        self.board.set_last_move(last_move);
+        self
     }
 
     pub fn game_id(&self) -> u32 {
@@ -87,6 +494,22 @@ impl GameScreen {
     }
 
     pub fn set_board_state(&mut self, fen: &str, mv: Option<BitMove>) -> &mut Self {
+        // The position changed under an unconfirmed move, e.g. after a sync
+        self.pending_move = None;
+        if self.viewed_ply.is_some() {
+            // Snap back to the live position
+            self.viewed_ply = None;
+            self.live_position = None;
+            self.move_list.set_current(self.moves.len().checked_sub(1));
+        }
+        if let Some((ref mut live_fen, ref mut last_move)) = self.live_position {
+            // Keep the line being prepared on the board, the live position is restored afterwards
+            *live_fen = fen.to_string();
+            if mv.is_some() {
+                *last_move = mv;
+            }
+            return self;
+        }
         self.board.set_board_state(fen);
         if let Some(mv) = mv {
             self.board.set_last_move(mv);
@@ -94,9 +517,158 @@ impl GameScreen {
         self
     }
 
+    /// Sets the moves played from `start_fen`, shown in the move list
+    pub fn set_move_history(&mut self, start_fen: &str, moves: &[u16]) -> &mut Self {
+        if self.start_fen == start_fen && self.moves == moves {
+            return self;
+        }
+        self.start_fen = start_fen.to_string();
+        self.moves = moves.to_vec();
+        let (number, player) = board::move_number(start_fen);
+        self.move_list
+            .set_moves(board::san_moves(start_fen, moves), number, player == Player::Black);
+        self
+    }
+
+    pub fn clear_premove(&mut self) -> &mut Self {
+        self.premove = None;
+        self.board.set_premove(None);
+        self
+    }
+
+    pub fn set_conditional_lines(&mut self, lines: usize) -> &mut Self {
+        self.conditional_lines = lines;
+        self
+    }
+
+    /// Returns a prepared line of alternating opponent moves and replies
+    pub fn take_saved_line(&mut self) -> Option<Vec<u16>> {
+        self.saved_line.take()
+    }
+
+    pub fn take_clear_conditional_requested(&mut self) -> bool {
+        std::mem::take(&mut self.clear_conditional_requested)
+    }
+
+    pub fn is_preparing_line(&self) -> bool {
+        self.planned_line.is_some()
+    }
+
+    pub fn is_viewing_history(&self) -> bool {
+        self.viewed_ply.is_some()
+    }
+
+    fn update_history(&mut self, window: &Window) {
+        if self.planned_line.is_some() {
+            return;
+        }
+        self.history_back_button.update(window);
+        self.history_forward_button.update(window);
+        self.history_live_button.update(window);
+        if self.history_back_button.was_clicked() {
+            self.step_history(false);
+        }
+        if self.history_forward_button.was_clicked() {
+            self.step_history(true);
+        }
+        if self.history_live_button.was_clicked() {
+            self.view_ply(None);
+        }
+
+        // The arrow keys move the caret while typing a move
+        if self.move_input.is_active() {
+            return;
+        }
+        if let Some(keys) = window.get_keys_pressed(KeyRepeat::Yes) {
+            for key in keys {
+                match key {
+                    Key::Left => self.step_history(false),
+                    Key::Right => self.step_history(true),
+                    Key::Home => self.view_ply(Some(0)),
+                    Key::End => self.view_ply(None),
+                    _ => {},
+                }
+            }
+        }
+    }
+
+    fn step_history(&mut self, forward: bool) {
+        let num_moves = self.moves.len();
+        let ply = match (self.viewed_ply, forward) {
+            (None, true) => return,
+            (None, false) => num_moves.checked_sub(1),
+            (Some(ply), false) => Some(ply.saturating_sub(1)),
+            (Some(ply), true) if ply + 1 < num_moves => Some(ply + 1),
+            (Some(_), true) => None,
+        };
+        self.view_ply(ply);
+    }
+
+    /// Shows the position after `ply` moves, or the live position if None
+    fn view_ply(&mut self, ply: Option<usize>) {
+        let ply = match ply {
+            Some(ply) => ply,
+            None => {
+                if self.viewed_ply.take().is_some() {
+                    if let Some((fen, last_move)) = self.live_position.take() {
+                        self.board.set_board_state(&fen).set_last_move(last_move);
+                    }
+                    self.move_list.set_current(self.moves.len().checked_sub(1));
+                }
+                return;
+            },
+        };
+        let fen = match board::replay(&self.start_fen, &self.moves[..ply.min(self.moves.len())]) {
+            Some(fen) => fen,
+            None => return,
+        };
+        if self.live_position.is_none() {
+            self.live_position = Some((self.board.to_fen(), self.board.last_move()));
+        }
+        self.cancel_pick_up();
+        self.board
+            .set_board_state(&fen)
+            .set_last_move(ply.checked_sub(1).map(|i| BitMove::new(self.moves[i])));
+        self.viewed_ply = Some(ply);
+        self.move_list.set_current(ply.checked_sub(1));
+    }
+
     pub fn update(&mut self, window: &Window) {
         self.state.update(window);
         self.back_button.update(window);
+        self.flip_button.update(window);
+        self.move_list.update(window);
+        if self.flip_button.was_clicked() {
+            self.flip_board();
+        }
+
+        if let Some(spectator) = self.shown_spectator_request().cloned() {
+            self.allow_spectator_button.update(window);
+            self.deny_spectator_button.update(window);
+            if self.allow_spectator_button.was_clicked() {
+                self.spectator_decision = Some((spectator, true));
+            } else if self.deny_spectator_button.was_clicked() {
+                self.spectator_decision = Some((spectator, false));
+            }
+        }
+
+        self.update_history(window);
+
+        if !self.is_spectating {
+            self.update_takeback(window);
+        }
+
+        if !self.is_spectating && self.viewed_ply.is_none() && self.pending_move.is_some() {
+            self.update_pending_move(window);
+        } else if !self.is_spectating && self.viewed_ply.is_none() {
+            self.update_conditional(window);
+            if self.state.is_right_mouse_down {
+                self.cancel_pick_up();
+                self.clear_premove();
+            }
+            self.update_move_input(window);
+            self.try_premove();
+        }
 
         if self.board.is_stalemate() {
             self.state.set_game_status(GameStatus::StaleMate);
@@ -112,6 +684,193 @@ impl GameScreen {
         }
     }
 
+    fn update_conditional(&mut self, window: &Window) {
+        if self.planned_line.is_some() {
+            self.save_line_button.update(window);
+            self.cancel_line_button.update(window);
+            if self.save_line_button.was_clicked() {
+                let line = self.planned_line.as_ref().map(|l| l.len() / 2 * 2).unwrap_or(0);
+                if line >= 2 {
+                    self.saved_line = self
+                        .planned_line
+                        .as_ref()
+                        .map(|l| l[..line].iter().map(|mv| mv.get_raw()).collect());
+                    self.stop_preparing_line();
+                }
+            } else if self.cancel_line_button.was_clicked() {
+                self.stop_preparing_line();
+            }
+            return;
+        }
+
+        // Replies are prepared while waiting for the opponent to move
+        if !self.board.is_our_turn() {
+            self.plan_button.update(window);
+            if self.plan_button.was_clicked() {
+                self.live_position = Some((self.board.to_fen(), self.board.last_move()));
+                self.planned_line = Some(Vec::new());
+                self.board.set_any_side(true);
+                self.clear_premove();
+            }
+        }
+        self.clear_conditional_button.update(window);
+        if self.clear_conditional_button.was_clicked() {
+            self.clear_conditional_requested = true;
+        }
+    }
+
+    fn stop_preparing_line(&mut self) {
+        self.planned_line = None;
+        self.board.set_any_side(false);
+        if let Some((fen, last_move)) = self.live_position.take() {
+            self.set_board_state(&fen, last_move);
+        }
+    }
+
+    fn update_takeback(&mut self, window: &Window) {
+        if self.takeback_request.is_some() {
+            self.accept_takeback_button.update(window);
+            self.decline_takeback_button.update(window);
+            if self.accept_takeback_button.was_clicked() {
+                self.takeback_decision = Some(true);
+            } else if self.decline_takeback_button.was_clicked() {
+                self.takeback_decision = Some(false);
+            }
+        } else if !self.is_takeback_sent && !self.moves.is_empty() {
+            self.takeback_button.update(window);
+            if self.takeback_button.was_clicked() {
+                self.is_takeback_requested = true;
+            }
+        }
+    }
+
+    fn update_pending_move(&mut self, window: &Window) {
+        self.confirm_move_button.update(window);
+        self.undo_move_button.update(window);
+        if self.confirm_move_button.was_clicked() {
+            self.confirm_pending_move();
+        } else if self.undo_move_button.was_clicked() {
+            if let Some((_, fen, last_move)) = self.pending_move.take() {
+                self.board.set_board_state(&fen).set_last_move(last_move);
+            }
+        }
+    }
+
+    /// Our move waiting for confirmation
+    pub fn pending_move(&self) -> Option<BitMove> {
+        self.pending_move.as_ref().map(|(mv, _, _)| *mv)
+    }
+
+    /// Shows a move that was waiting for confirmation when the game was last on screen, if it is still legal
+    pub fn set_pending_move(&mut self, mv: BitMove) -> &mut Self {
+        if self.is_spectating || self.pending_move.is_some() || !self.board.is_our_turn() {
+            return self;
+        }
+        let fen = self.board.to_fen();
+        if let Some((mv, _)) = board::play_move(&fen, mv) {
+            let previous = (fen, self.board.last_move());
+            self.board.make_legal_move(mv);
+            self.board.set_last_move(mv);
+            self.pending_move = Some((mv, previous.0, previous.1));
+        }
+        self
+    }
+
+    fn confirm_pending_move(&mut self) {
+        if let Some((mv, _, _)) = self.pending_move.take() {
+            self.last_move_played = Some(mv);
+        }
+    }
+
+    /// Plays the premove as soon as it is our turn, if it is legal
+    fn try_premove(&mut self) {
+        if self.planned_line.is_some() || !self.board.is_our_turn() {
+            return;
+        }
+        if let Some((src, dest)) = self.premove {
+            self.clear_premove();
+            if let Some(mv) = self.board.legal_move(src, dest) {
+                self.play_move(mv, PieceType::Q);
+                // The premove was already chosen ahead of time, send it straight away
+                self.confirm_pending_move();
+            }
+        }
+    }
+
+    /// Plays the move from `src` to `dest` if it is legal, promoting to `promotion` or a queen if none is given. While
+    /// the opponent is to move the move is queued as a premove instead.
+    fn move_to(&mut self, src: SQ, dest: SQ, promotion: Option<PieceType>) -> bool {
+        if !self.board.is_movable_piece(src) {
+            return false;
+        }
+        if let Some(mv) = self.board.legal_move(src, dest) {
+            // A promotion piece typed after a move that isn't a promotion, e.g. "e2e4q"
+            if promotion.is_some() && !mv.is_promo() {
+                return false;
+            }
+            self.play_move(mv, promotion.unwrap_or(PieceType::Q));
+            return true;
+        }
+        // Premoves always promote to a queen
+        let is_queen = matches!(promotion, None | Some(PieceType::Q));
+        if self.planned_line.is_none() && !self.board.is_our_turn() && src != dest && is_queen {
+            self.premove = Some((src, dest));
+            self.board.set_premove(self.premove);
+            return true;
+        }
+        false
+    }
+
+    /// Cancels a piece that is picked up or selected
+    fn cancel_pick_up(&mut self) {
+        self.floating_piece = None;
+        self.board.return_taken_piece();
+        self.board.select(None);
+    }
+
+    fn update_move_input(&mut self, window: &Window) {
+        self.move_input.update(window);
+        self.play_typed_button.update(window);
+        let is_submitted = (self.move_input.is_active() && window.is_key_pressed(Key::Enter, KeyRepeat::No)) ||
+            self.play_typed_button.was_clicked();
+        if !is_submitted || self.move_input.value().trim().is_empty() {
+            return;
+        }
+        let is_played = match self.board.parse_move(self.move_input.value()) {
+            Some((src, dest, promotion)) => self.move_to(src, dest, promotion),
+            None => false,
+        };
+        if is_played {
+            self.cancel_pick_up();
+            let is_active = self.move_input.is_active();
+            self.move_input.set_value(String::new()).set_active(is_active);
+        } else {
+            self.move_input.set_bg_color(Color::red());
+        }
+    }
+
+    /// Plays a legal move, promoting to `promotion` if it is a promotion
+    fn play_move(&mut self, mut mv: BitMove, promotion: PieceType) {
+        if mv.is_promo() {
+            mv = BitMove::init(PreMoveInfo {
+                src: mv.get_src(),
+                dst: mv.get_dest(),
+                flags: MoveFlag::Promotion {
+                    capture: mv.is_capture(),
+                    prom: promotion,
+                },
+            });
+        }
+        let previous = (self.board.to_fen(), self.board.last_move());
+        self.board.make_legal_move(mv);
+        self.board.set_last_move(mv);
+        match self.planned_line {
+            Some(ref mut line) => line.push(mv),
+            None if self.confirm_moves => self.pending_move = Some((mv, previous.0, previous.1)),
+            None => self.last_move_played = Some(mv),
+        }
+    }
+
     pub fn state(&self) -> &State {
         &self.state
     }
@@ -141,111 +900,207 @@ impl GameScreen {
         self.back_button.was_clicked()
     }
 
-    fn labels(&self) -> Drawables<Label> {
-        let mut label1 = Label::new(Frame::new(self.board.height() + 10, 80, 100, 20));
-        label1.set_text(format!("ID: {}", self.id)).set_bg_color(Color::black());
+    /// Shows the board from the other side, whichever colour we are playing
+    pub fn flip_board(&mut self) -> &mut Self {
+        self.board.flip();
+        self
+    }
 
-        let mut label2 = Label::new(Frame::new(self.board.height() + 10, 110, 100, 20));
-        label2
-            .set_text(format!("MOVE: {}", self.seq))
-            .set_bg_color(Color::black());
+    /// Shows a result that isn't on the board, a resignation or an agreed draw
+    pub fn set_game_status(&mut self, status: GameStatus) -> &mut Self {
+        self.state.set_game_status(status);
+        self
+    }
 
-        let mut label3 = Label::new(Frame::new(self.board.height() + 10, 140, 100, 20));
-        label3
-            .set_text(format!("Turn: {}", self.board.turn()))
-            .set_bg_color(Color::black());
+    /// The result is shown over the live position, but not while viewing history
+    fn is_game_over_shown(&self) -> bool {
+        !matches!(self.state.game_status(), GameStatus::InProgress) && self.viewed_ply.is_none()
+    }
 
-        let mut label4 = Label::new(Frame::new(self.board.height() + 10, 170, 100, 20));
-        label4
-            .set_text(format!("Status: {}", self.state().game_status()))
-            .set_bg_color(Color::black());
+    /// Shades the board and shows the result in a box in the middle of it
+    fn draw_game_over(&self, buf: &mut FrameBuffer) {
+        let board_size = self.board.width();
+        Rect::new(0, 0, board_size, board_size, Color::black().with_alpha(GAME_OVER_SHADE)).draw(buf);
 
-        Drawables {
-            items: vec![label1, label2, label3, label4],
-        }
+        let text = self.state.game_status().to_string();
+        let text_size = DEFAULT_SIZE * 1.5;
+        let w = (FONT.measure(&text, text_size) + 40).min(board_size);
+        let h = FONT.line_height(text_size) + 30;
+        let mut dialog = Rect::new(
+            (board_size - w) / 2,
+            board_size.saturating_sub(h) / 2,
+            w,
+            h,
+            Color::white(),
+        );
+        dialog.set_border(2, Color::dark_blue()).draw(buf);
+        buf.push_clip(dialog.frame());
+        FONT.draw_string(&text, dialog.x() + 20, dialog.y() + 15, text_size, Color::black(), buf);
+        buf.pop_clip();
     }
 }
 
 impl Drawable for GameScreen {
     fn draw(&mut self, buf: &mut FrameBuffer) {
-        self.board.draw(buf);
+        self.refresh_panel();
+        let is_game_over_shown = self.is_game_over_shown();
+        if is_game_over_shown != self.is_game_over_drawn {
+            self.is_game_over_drawn = is_game_over_shown;
+            buf.invalidate(self.board.frame());
+        }
+        let floating_frame = self.floating_piece_frame();
+        if floating_frame != self.floating_frame {
+            for frame in [self.floating_frame, floating_frame].into_iter().flatten() {
+                buf.invalidate(frame);
+            }
+            self.floating_frame = floating_frame;
+        }
+        buf.invalidate_widgets(&mut [
+            &mut self.board,
+            &mut self.back_button,
+            &mut self.flip_button,
+            &mut self.move_list,
+            &mut self.history_back_button,
+            &mut self.history_forward_button,
+            &mut self.history_live_button,
+            &mut self.allow_spectator_button,
+            &mut self.deny_spectator_button,
+            &mut self.takeback_button,
+            &mut self.accept_takeback_button,
+            &mut self.decline_takeback_button,
+            &mut self.confirm_move_button,
+            &mut self.undo_move_button,
+            &mut self.move_input,
+            &mut self.play_typed_button,
+            &mut self.plan_button,
+            &mut self.save_line_button,
+            &mut self.cancel_line_button,
+            &mut self.clear_conditional_button,
+            &mut self.id_label,
+            &mut self.move_label,
+            &mut self.turn_label,
+            &mut self.status_label,
+            &mut self.conditional_label,
+            &mut self.banner_label,
+            &mut self.request_label,
+            &mut self.request_detail_label,
+            &mut self.takeback_sent_label,
+        ]);
 
-        // Clear score board
-        Rect::new(
-            self.board.width(),
-            0,
-            self.config.window_width - self.board.width(),
-            self.config.window_height,
-            Color::black(),
-        )
-        .draw(buf);
+        // Clear score board and any space left around the board
+        buf.clear(Color::black());
+        let is_board_damaged = buf.is_damaged(&self.board.frame());
+        self.board.draw(buf);
+        if is_game_over_shown && is_board_damaged {
+            self.draw_game_over(buf);
+        }
 
         self.back_button.draw(buf);
-        self.labels().draw(buf);
+        self.flip_button.draw(buf);
+        self.id_label.draw(buf);
+        self.move_label.draw(buf);
+        self.turn_label.draw(buf);
+        self.status_label.draw(buf);
+        self.conditional_label.draw(buf);
+        self.banner_label.draw(buf);
+        self.request_label.draw(buf);
+        self.request_detail_label.draw(buf);
+        self.takeback_sent_label.draw(buf);
+        self.move_list.draw(buf);
+        self.history_back_button.draw(buf);
+        self.history_forward_button.draw(buf);
+        self.history_live_button.draw(buf);
+        self.allow_spectator_button.draw(buf);
+        self.deny_spectator_button.draw(buf);
+        self.accept_takeback_button.draw(buf);
+        self.decline_takeback_button.draw(buf);
+        self.takeback_button.draw(buf);
+        self.confirm_move_button.draw(buf);
+        self.undo_move_button.draw(buf);
+        self.move_input.draw(buf);
+        self.play_typed_button.draw(buf);
+        self.save_line_button.draw(buf);
+        self.cancel_line_button.draw(buf);
+        self.plan_button.draw(buf);
+        self.clear_conditional_button.draw(buf);
+
+        if let Some(frame) = floating_frame {
+            self.board.draw_taken_piece(frame.x, frame.y, buf);
+        }
+
+        if !self.is_board_playable() {
+            return;
+        }
 
-        if self.state.is_left_mouse_down {
+        if self.state.is_left_click() && self.floating_piece.is_none() {
             if let Some((mouse_x, mouse_y)) = self.state.mouse_pos {
-                match self.floating_piece {
-                    Some((offset_x, offset_y)) => {
-                        self.board.draw_taken_piece(
-                            mouse_x.saturating_sub(offset_x),
-                            mouse_y.saturating_sub(offset_y),
-                            buf,
-                        );
+                let sq = self.board.get_square(mouse_x, mouse_y);
+                // A piece selected by clicking is moved by clicking its destination
+                let selected = self.board.selected().filter(|src| Some(*src) != sq);
+                self.board.select(None);
+                match (selected, sq) {
+                    (Some(src), Some(dest)) if !self.board.is_movable_piece(dest) => {
+                        self.move_to(src, dest, None);
                     },
-                    None => {
+                    _ => {
                         if self.board.take_piece_at(mouse_x, mouse_y).is_some() {
-                            self.floating_piece = Some((mouse_x % 90, mouse_y % 90));
+                            let square_size = self.board.square_size();
+                            self.floating_piece = Some((mouse_x % square_size, mouse_y % square_size));
                         }
                     },
                 }
             }
-        } else {
-            if self.floating_piece.is_some() {
-                match self.state.mouse_pos.and_then(|(x, y)| self.board.get_square(x, y)) {
-                    Some(sq) => match self.board.get_move_to(sq) {
-                        Some(mut legal_move) => {
-                            if legal_move.is_promo() {
-                                legal_move = BitMove::init(PreMoveInfo {
-                                    src: legal_move.get_src(),
-                                    dst: legal_move.get_dest(),
-                                    flags: MoveFlag::Promotion {
-                                        capture: legal_move.is_capture(),
-                                        prom: PieceType::Q,
-                                    },
-                                });
-                            }
-                            self.board.make_legal_move(legal_move);
-                            self.last_move_played = Some(legal_move);
-                            self.board.set_last_move(legal_move);
-                        },
-                        None => {
-                            self.board.return_taken_piece();
-                        },
-                    },
-                    None => {
-                        self.board.return_taken_piece();
-                    },
-                }
-                self.floating_piece = None;
+        } else if !self.state.is_left_mouse_down && self.floating_piece.is_some() {
+            self.floating_piece = None;
+            let src = self.board.taken_square();
+            self.board.return_taken_piece();
+            let dest = self.state.mouse_pos.and_then(|(x, y)| self.board.get_square(x, y));
+            match (src, dest) {
+                // Dropped where it was picked up, keep it selected for click-to-move
+                (Some(src), Some(dest)) if src == dest => {
+                    self.board.select(Some(src));
+                },
+                (Some(src), Some(dest)) => {
+                    self.move_to(src, dest, None);
+                },
+                _ => {},
             }
         }
     }
 }
 
+/// A row of the side panel with the usual gap between buttons
+fn button_row(frame: Frame) -> Stack {
+    let mut row = Stack::row(frame);
+    row.set_spacing(10);
+    row
+}
+
+/// The largest board, in whole squares, that fits in the window next to the side panel
+fn board_size(window_width: u32, window_height: u32) -> u32 {
+    let size = window_height.min(window_width.saturating_sub(PANEL_WIDTH));
+    (size / 8).max(MIN_SQUARE_SIZE) * 8
+}
+
 #[derive(Debug, Clone)]
 pub struct GameConfig {
     pub window_width: u32,
     pub window_height: u32,
-    pub light_color: Color,
-    pub dark_color: Color,
+    pub theme: Theme,
     pub save_path: PathBuf,
+    pub tournaments_path: PathBuf,
+    pub archive_path: PathBuf,
+    pub settings_path: PathBuf,
+    /// Directory of theme files, shared by all profiles
+    pub themes_path: PathBuf,
 }
 
 #[derive(Debug)]
 pub struct State {
     mouse_pos: Option<(u32, u32)>,
     is_left_mouse_down: bool,
+    was_left_mouse_down: bool,
+    is_right_mouse_down: bool,
     game_status: GameStatus,
 }
 
@@ -254,6 +1109,8 @@ impl State {
         Self {
             mouse_pos: None,
             is_left_mouse_down: false,
+            was_left_mouse_down: false,
+            is_right_mouse_down: false,
             game_status: Default::default(),
         }
     }
@@ -262,7 +1119,14 @@ impl State {
         self.mouse_pos = window
             .get_mouse_pos(MouseMode::Discard)
             .map(|(x, y)| (x.round() as u32, y.round() as u32));
+        self.was_left_mouse_down = self.is_left_mouse_down;
         self.is_left_mouse_down = window.get_mouse_down(MouseButton::Left);
+        self.is_right_mouse_down = window.get_mouse_down(MouseButton::Right);
+    }
+
+    /// Returns true if the left mouse button was pressed this frame
+    pub fn is_left_click(&self) -> bool {
+        self.is_left_mouse_down && !self.was_left_mouse_down
     }
 
     pub(crate) fn set_game_status(&mut self, status: GameStatus) -> &mut Self {
@@ -281,38 +1145,13 @@ impl Default for State {
     }
 }
 
-fn init_pieces_sprite() -> SpriteSheet<&'static str, Bitmap> {
-    let image = Bitmap::from_reader(&mut include_bytes!("../assets/pieces.bmp").as_slice()).unwrap();
-    let mut sprite_sheet = SpriteSheet::new(image);
-    let pieces = Frame {
-        x: 0,
-        y: 0,
-        w: 90,
-        h: 90,
-    };
-    sprite_sheet
-        .ignore_color(Color::green())
-        .add_area("king-white", pieces)
-        .add_area("queen-white", pieces.offset_xy(90, 0))
-        .add_area("rook-white", pieces.offset_xy(180, 0))
-        .add_area("bishop-white", pieces.offset_xy(270, 0))
-        .add_area("knight-white", pieces.offset_xy(360, 0))
-        .add_area("pawn-white", pieces.offset_xy(0, 90))
-        .add_area("king-black", pieces.offset_xy(90, 90))
-        .add_area("queen-black", pieces.offset_xy(180, 90))
-        .add_area("rook-black", pieces.offset_xy(270, 90))
-        .add_area("bishop-black", pieces.offset_xy(360, 90))
-        .add_area("knight-black", pieces.offset_xy(0, 180))
-        .add_area("pawn-black", pieces.offset_xy(90, 180));
-    sprite_sheet
-}
-
 #[derive(Debug, Clone, Copy)]
 pub enum GameStatus {
     InProgress,
     StaleMate,
     CheckMate(Player),
     Resign(Player),
+    DrawAgreed,
 }
 
 impl Default for GameStatus {
@@ -328,6 +1167,7 @@ impl Display for GameStatus {
             GameStatus::StaleMate => write!(f, "Stale mate"),
             GameStatus::CheckMate(player) => write!(f, "Checkmate! {} won", player),
             GameStatus::Resign(player) => write!(f, "{} resigned", player),
+            GameStatus::DrawAgreed => write!(f, "Draw agreed"),
         }
     }
 }
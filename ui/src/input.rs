@@ -0,0 +1,33 @@
+use std::cell::RefCell;
+
+use minifb::{InputCallback, Window};
+
+thread_local! {
+    static TYPED_CHARS: RefCell<Vec<char>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Collects the characters typed into a window, after the keyboard layout and modifiers have been applied
+struct TypedChars;
+
+impl InputCallback for TypedChars {
+    fn add_char(&mut self, uni_char: u32) {
+        if let Some(ch) = char::from_u32(uni_char) {
+            TYPED_CHARS.with(|chars| chars.borrow_mut().push(ch));
+        }
+    }
+}
+
+/// Starts collecting typed characters from `window`
+pub fn capture_typed_chars(window: &mut Window) {
+    window.set_input_callback(Box::new(TypedChars));
+}
+
+/// The characters typed since the last window update
+pub fn typed_chars() -> Vec<char> {
+    TYPED_CHARS.with(|chars| chars.borrow().clone())
+}
+
+/// Call before each window update so that characters are only seen for one frame
+pub fn clear_typed_chars() {
+    TYPED_CHARS.with(|chars| chars.borrow_mut().clear());
+}
@@ -0,0 +1,318 @@
+use std::{
+    collections::BTreeMap,
+    fmt::{self, Display, Formatter},
+    str::FromStr,
+};
+
+use anyhow::anyhow;
+use minifb::{Key, KeyRepeat, Window};
+
+/// An action that can be bound to a key and run from the command palette
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Command {
+    CommandPalette,
+    FlipBoard,
+    Resign,
+    OfferDraw,
+    NextGame,
+    PreviousGame,
+    CopyPublicKey,
+    Quit,
+}
+
+impl Command {
+    pub const ALL: [Command; 8] = [
+        Command::CommandPalette,
+        Command::FlipBoard,
+        Command::Resign,
+        Command::OfferDraw,
+        Command::NextGame,
+        Command::PreviousGame,
+        Command::CopyPublicKey,
+        Command::Quit,
+    ];
+
+    /// Shown in the command palette
+    pub fn title(self) -> &'static str {
+        match self {
+            Command::CommandPalette => "Command palette",
+            Command::FlipBoard => "Flip board",
+            Command::Resign => "Resign",
+            Command::OfferDraw => "Offer or accept a draw",
+            Command::NextGame => "Next game",
+            Command::PreviousGame => "Previous game",
+            Command::CopyPublicKey => "Copy my public key",
+            Command::Quit => "Quit",
+        }
+    }
+
+    /// Names the command in the settings file
+    pub fn id(self) -> &'static str {
+        match self {
+            Command::CommandPalette => "command-palette",
+            Command::FlipBoard => "flip-board",
+            Command::Resign => "resign",
+            Command::OfferDraw => "offer-draw",
+            Command::NextGame => "next-game",
+            Command::PreviousGame => "previous-game",
+            Command::CopyPublicKey => "copy-public-key",
+            Command::Quit => "quit",
+        }
+    }
+
+    fn default_binding(self) -> &'static str {
+        match self {
+            Command::CommandPalette => "Ctrl+P",
+            Command::FlipBoard => "Ctrl+F",
+            Command::Resign => "Ctrl+R",
+            Command::OfferDraw => "Ctrl+D",
+            Command::NextGame => "Ctrl+PageDown",
+            Command::PreviousGame => "Ctrl+PageUp",
+            Command::CopyPublicKey => "Ctrl+Shift+C",
+            Command::Quit => "Ctrl+Q",
+        }
+    }
+}
+
+/// A key and the modifiers that must be held with it, written like "Ctrl+Shift+C". Ctrl is Cmd on macOS.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyBinding {
+    pub key: Key,
+    pub ctrl: bool,
+    pub shift: bool,
+    pub alt: bool,
+}
+
+impl KeyBinding {
+    /// True on the frame the key is pressed with exactly the binding's modifiers
+    pub fn is_pressed(&self, window: &Window) -> bool {
+        window.is_key_pressed(self.key, KeyRepeat::No) && modifiers(window) == (self.ctrl, self.shift, self.alt)
+    }
+}
+
+impl FromStr for KeyBinding {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut binding = KeyBinding {
+            key: Key::Unknown,
+            ctrl: false,
+            shift: false,
+            alt: false,
+        };
+        let mut parts = s.split('+').map(str::trim).peekable();
+        while let Some(part) = parts.next() {
+            if parts.peek().is_none() {
+                binding.key = KEY_NAMES
+                    .iter()
+                    .find(|(name, _)| name.eq_ignore_ascii_case(part))
+                    .map(|(_, key)| *key)
+                    .ok_or_else(|| anyhow!("Unknown key '{}'", part))?;
+            } else if part.eq_ignore_ascii_case("ctrl") || part.eq_ignore_ascii_case("cmd") {
+                binding.ctrl = true;
+            } else if part.eq_ignore_ascii_case("shift") {
+                binding.shift = true;
+            } else if part.eq_ignore_ascii_case("alt") {
+                binding.alt = true;
+            } else {
+                return Err(anyhow!("Unknown modifier '{}'", part));
+            }
+        }
+        Ok(binding)
+    }
+}
+
+impl Display for KeyBinding {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let ctrl = if cfg!(target_os = "macos") { "Cmd+" } else { "Ctrl+" };
+        for (is_held, name) in [(self.ctrl, ctrl), (self.shift, "Shift+"), (self.alt, "Alt+")] {
+            if is_held {
+                write!(f, "{}", name)?;
+            }
+        }
+        let name = KEY_NAMES
+            .iter()
+            .find(|(_, key)| *key == self.key)
+            .map(|(name, _)| *name)
+            .unwrap_or("?");
+        write!(f, "{}", name)
+    }
+}
+
+/// The key bound to each command: the defaults, replaced by any bindings from the settings file
+#[derive(Debug, Clone)]
+pub struct KeyBindings {
+    bindings: Vec<(Command, Option<KeyBinding>)>,
+}
+
+impl KeyBindings {
+    /// `overrides` maps command ids to key bindings, or to an empty string to unbind a command. Invalid entries are
+    /// logged and the default is kept.
+    pub fn new(overrides: &BTreeMap<String, String>) -> Self {
+        for id in overrides.keys() {
+            if !Command::ALL.iter().any(|c| c.id() == id) {
+                log::warn!("Ignoring key binding for unknown command '{}'", id);
+            }
+        }
+        let bindings = Command::ALL
+            .iter()
+            .map(|command| {
+                let binding = match overrides.get(command.id()) {
+                    Some(s) if s.trim().is_empty() => None,
+                    Some(s) => match s.parse() {
+                        Ok(binding) => Some(binding),
+                        Err(err) => {
+                            log::warn!("Invalid key binding for '{}': {}", command.id(), err);
+                            command.default_binding().parse().ok()
+                        },
+                    },
+                    None => command.default_binding().parse().ok(),
+                };
+                (*command, binding)
+            })
+            .collect();
+        Self { bindings }
+    }
+
+    pub fn binding(&self, command: Command) -> Option<KeyBinding> {
+        self.bindings
+            .iter()
+            .find(|(c, _)| *c == command)
+            .and_then(|(_, binding)| *binding)
+    }
+
+    /// The command whose keys were pressed this frame
+    pub fn pressed(&self, window: &Window) -> Option<Command> {
+        self.bindings
+            .iter()
+            .find(|(_, binding)| binding.map(|b| b.is_pressed(window)).unwrap_or(false))
+            .map(|(command, _)| *command)
+    }
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        Self::new(&BTreeMap::new())
+    }
+}
+
+/// Whether ctrl, shift and alt are held
+fn modifiers(window: &Window) -> (bool, bool, bool) {
+    #[cfg(not(target_os = "macos"))]
+    let is_ctrl = window.is_key_down(Key::LeftCtrl) || window.is_key_down(Key::RightCtrl);
+    #[cfg(target_os = "macos")]
+    let is_ctrl = window.is_key_down(Key::LeftSuper) || window.is_key_down(Key::RightSuper);
+    let is_shift = window.is_key_down(Key::LeftShift) || window.is_key_down(Key::RightShift);
+    let is_alt = window.is_key_down(Key::LeftAlt) || window.is_key_down(Key::RightAlt);
+    (is_ctrl, is_shift, is_alt)
+}
+
+const KEY_NAMES: &[(&str, Key)] = &[
+    ("A", Key::A),
+    ("B", Key::B),
+    ("C", Key::C),
+    ("D", Key::D),
+    ("E", Key::E),
+    ("F", Key::F),
+    ("G", Key::G),
+    ("H", Key::H),
+    ("I", Key::I),
+    ("J", Key::J),
+    ("K", Key::K),
+    ("L", Key::L),
+    ("M", Key::M),
+    ("N", Key::N),
+    ("O", Key::O),
+    ("P", Key::P),
+    ("Q", Key::Q),
+    ("R", Key::R),
+    ("S", Key::S),
+    ("T", Key::T),
+    ("U", Key::U),
+    ("V", Key::V),
+    ("W", Key::W),
+    ("X", Key::X),
+    ("Y", Key::Y),
+    ("Z", Key::Z),
+    ("0", Key::Key0),
+    ("1", Key::Key1),
+    ("2", Key::Key2),
+    ("3", Key::Key3),
+    ("4", Key::Key4),
+    ("5", Key::Key5),
+    ("6", Key::Key6),
+    ("7", Key::Key7),
+    ("8", Key::Key8),
+    ("9", Key::Key9),
+    ("F1", Key::F1),
+    ("F2", Key::F2),
+    ("F3", Key::F3),
+    ("F4", Key::F4),
+    ("F5", Key::F5),
+    ("F6", Key::F6),
+    ("F7", Key::F7),
+    ("F8", Key::F8),
+    ("F9", Key::F9),
+    ("F10", Key::F10),
+    ("F11", Key::F11),
+    ("F12", Key::F12),
+    ("Up", Key::Up),
+    ("Down", Key::Down),
+    ("Left", Key::Left),
+    ("Right", Key::Right),
+    ("Home", Key::Home),
+    ("End", Key::End),
+    ("PageUp", Key::PageUp),
+    ("PageDown", Key::PageDown),
+    ("Insert", Key::Insert),
+    ("Delete", Key::Delete),
+    ("Backspace", Key::Backspace),
+    ("Enter", Key::Enter),
+    ("Escape", Key::Escape),
+    ("Space", Key::Space),
+    ("Tab", Key::Tab),
+    (",", Key::Comma),
+    (".", Key::Period),
+    ("/", Key::Slash),
+    (";", Key::Semicolon),
+    ("'", Key::Apostrophe),
+    ("[", Key::LeftBracket),
+    ("]", Key::RightBracket),
+    ("-", Key::Minus),
+    ("=", Key::Equal),
+    ("`", Key::Backquote),
+    ("\\", Key::Backslash),
+];
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parses_bindings() {
+        let binding = "ctrl + shift+c".parse::<KeyBinding>().unwrap();
+        assert_eq!(binding, KeyBinding {
+            key: Key::C,
+            ctrl: true,
+            shift: true,
+            alt: false,
+        });
+        assert_eq!("PageDown".parse::<KeyBinding>().unwrap().key, Key::PageDown);
+        assert!("Ctrl+Nope".parse::<KeyBinding>().is_err());
+        assert!("Hyper+A".parse::<KeyBinding>().is_err());
+        assert!("".parse::<KeyBinding>().is_err());
+    }
+
+    #[test]
+    fn overrides_replace_defaults() {
+        let overrides = [("resign", ""), ("offer-draw", "Alt+D"), ("quit", "Ctrl+Nope")]
+            .into_iter()
+            .map(|(id, binding)| (id.to_string(), binding.to_string()))
+            .collect();
+        let bindings = KeyBindings::new(&overrides);
+        assert_eq!(bindings.binding(Command::Resign), None);
+        assert_eq!(bindings.binding(Command::OfferDraw), "Alt+D".parse().ok());
+        assert_eq!(bindings.binding(Command::Quit), "Ctrl+Q".parse().ok());
+        assert_eq!(bindings.binding(Command::NextGame), "Ctrl+PageDown".parse().ok());
+    }
+}
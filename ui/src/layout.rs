@@ -0,0 +1,218 @@
+use crate::rect::Frame;
+
+/// Space left around the inside of a frame
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Padding {
+    pub top: u32,
+    pub right: u32,
+    pub bottom: u32,
+    pub left: u32,
+}
+
+impl Padding {
+    pub fn all(size: u32) -> Self {
+        Self::symmetric(size, size)
+    }
+
+    pub fn symmetric(vertical: u32, horizontal: u32) -> Self {
+        Self {
+            top: vertical,
+            right: horizontal,
+            bottom: vertical,
+            left: horizontal,
+        }
+    }
+
+    /// Shrinks `frame` by the padding, down to nothing if it doesn't fit
+    pub fn apply(&self, frame: Frame) -> Frame {
+        Frame::new(
+            frame.x + self.left.min(frame.w),
+            frame.y + self.top.min(frame.h),
+            frame.w.saturating_sub(self.left + self.right),
+            frame.h.saturating_sub(self.top + self.bottom),
+        )
+    }
+}
+
+/// Where a widget is placed within the space it is given
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Anchor {
+    TopLeft,
+    Top,
+    TopRight,
+    Left,
+    Centre,
+    Right,
+    BottomLeft,
+    Bottom,
+    BottomRight,
+}
+
+impl Anchor {
+    /// Places a `w` by `h` frame inside `outer`, clamped to its size
+    pub fn place(self, outer: Frame, w: u32, h: u32) -> Frame {
+        let (w, h) = (w.min(outer.w), h.min(outer.h));
+        let (free_x, free_y) = (outer.w - w, outer.h - h);
+        let x = match self {
+            Anchor::TopLeft | Anchor::Left | Anchor::BottomLeft => 0,
+            Anchor::Top | Anchor::Centre | Anchor::Bottom => free_x / 2,
+            Anchor::TopRight | Anchor::Right | Anchor::BottomRight => free_x,
+        };
+        let y = match self {
+            Anchor::TopLeft | Anchor::Top | Anchor::TopRight => 0,
+            Anchor::Left | Anchor::Centre | Anchor::Right => free_y / 2,
+            Anchor::BottomLeft | Anchor::Bottom | Anchor::BottomRight => free_y,
+        };
+        Frame::new(outer.x + x, outer.y + y, w, h)
+    }
+}
+
+/// The size of a slot along a row or column
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Size {
+    Fixed(u32),
+    /// Shares the space left by fixed slots with other `Fill` slots in proportion to the weight
+    Fill(u32),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Direction {
+    Row,
+    Column,
+}
+
+/// Lays out widgets one after another, left to right in a row or top to bottom in a column. Each slot takes the full
+/// height of a row or the full width of a column.
+#[derive(Debug, Clone)]
+pub struct Stack {
+    frame: Frame,
+    direction: Direction,
+    spacing: u32,
+    /// How far along the main axis the next slot starts
+    offset: u32,
+}
+
+impl Stack {
+    pub fn row(frame: Frame) -> Self {
+        Self::new(frame, Direction::Row)
+    }
+
+    pub fn column(frame: Frame) -> Self {
+        Self::new(frame, Direction::Column)
+    }
+
+    fn new(frame: Frame, direction: Direction) -> Self {
+        Self {
+            frame,
+            direction,
+            spacing: 0,
+            offset: 0,
+        }
+    }
+
+    /// Sets the gap left between slots
+    pub fn set_spacing(&mut self, spacing: u32) -> &mut Self {
+        self.spacing = spacing;
+        self
+    }
+
+    /// Takes the next slot, `size` pixels along the stack. Slots past the end are empty.
+    pub fn next(&mut self, size: u32) -> Frame {
+        let size = size.min(self.len().saturating_sub(self.offset));
+        let slot = self.slot(self.offset, size);
+        self.offset = (self.offset + size + self.spacing).min(self.len());
+        slot
+    }
+
+    /// Skips `size` pixels, e.g. to separate groups of widgets
+    pub fn skip(&mut self, size: u32) -> &mut Self {
+        self.offset = (self.offset + size).min(self.len());
+        self
+    }
+
+    /// Takes all the space that is left
+    pub fn rest(&mut self) -> Frame {
+        let size = self.len() - self.offset;
+        self.next(size)
+    }
+
+    /// Divides the remaining space between `sizes` and takes it all
+    pub fn split(&mut self, sizes: &[Size]) -> Vec<Frame> {
+        let gaps = self.spacing * sizes.len().saturating_sub(1) as u32;
+        let fixed = sizes
+            .iter()
+            .map(|size| match size {
+                Size::Fixed(n) => *n,
+                Size::Fill(_) => 0,
+            })
+            .sum::<u32>();
+        let free = self.len().saturating_sub(self.offset + gaps + fixed);
+        let weights = sizes
+            .iter()
+            .map(|size| match size {
+                Size::Fill(weight) => *weight,
+                Size::Fixed(_) => 0,
+            })
+            .sum::<u32>();
+        let mut filled = 0;
+        let mut weight_so_far = 0;
+        sizes
+            .iter()
+            .map(|size| match size {
+                Size::Fixed(n) => self.next(*n),
+                Size::Fill(weight) => {
+                    // Rounding is spread so the fills add up to exactly the free space
+                    weight_so_far += weight;
+                    let end = (u64::from(free) * u64::from(weight_so_far) / u64::from(weights.max(1))) as u32;
+                    let slot = self.next(end - filled);
+                    filled = end;
+                    slot
+                },
+            })
+            .collect()
+    }
+
+    fn len(&self) -> u32 {
+        match self.direction {
+            Direction::Row => self.frame.w,
+            Direction::Column => self.frame.h,
+        }
+    }
+
+    fn slot(&self, offset: u32, size: u32) -> Frame {
+        match self.direction {
+            Direction::Row => Frame::new(self.frame.x + offset, self.frame.y, size, self.frame.h),
+            Direction::Column => Frame::new(self.frame.x, self.frame.y + offset, self.frame.w, size),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn stacks_slots_with_spacing() {
+        let mut column = Stack::column(Padding::all(10).apply(Frame::new(0, 0, 200, 100)));
+        column.set_spacing(5);
+        assert_eq!(column.next(20), Frame::new(10, 10, 180, 20));
+        assert_eq!(column.next(20), Frame::new(10, 35, 180, 20));
+        assert_eq!(column.rest(), Frame::new(10, 60, 180, 30));
+        assert_eq!(column.next(20).h, 0);
+
+        let mut row = Stack::row(Frame::new(0, 0, 100, 30));
+        row.set_spacing(10);
+        let slots = row.split(&[Size::Fixed(20), Size::Fill(1), Size::Fill(2)]);
+        assert_eq!(slots[0], Frame::new(0, 0, 20, 30));
+        assert_eq!(slots[1], Frame::new(30, 0, 20, 30));
+        assert_eq!(slots[2], Frame::new(60, 0, 40, 30));
+    }
+
+    #[test]
+    fn anchors_within_frame() {
+        let outer = Frame::new(10, 10, 100, 50);
+        assert_eq!(Anchor::Centre.place(outer, 20, 10), Frame::new(50, 30, 20, 10));
+        assert_eq!(Anchor::BottomRight.place(outer, 20, 10), Frame::new(90, 50, 20, 10));
+        assert_eq!(Anchor::Top.place(outer, 200, 10), Frame::new(10, 10, 100, 10));
+    }
+}
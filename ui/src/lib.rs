@@ -4,16 +4,30 @@ pub mod clipboard;
 pub mod color;
 pub mod components;
 pub mod drawable;
+pub mod focus;
+pub mod font;
 pub mod game;
 pub mod game_screen;
-pub mod letters;
+pub mod input;
+pub mod keybindings;
+pub mod layout;
+pub mod notifier;
 pub mod palette;
+pub mod profile_screen;
+pub mod rating;
 pub mod rect;
 pub mod screen_manager;
+pub mod settings;
+pub mod settings_screen;
+pub mod sound;
 pub mod sprite;
 pub mod start_screen;
+pub mod stats_screen;
+pub mod theme;
+pub mod tournament;
+pub mod tournament_screen;
 mod ui;
 // Reexports
 pub use minifb::{Key, ScaleMode, Window, WindowOptions};
 pub use tanton::BitMove;
-pub use ui::ChessUi;
+pub use ui::{ChessUi, ProfilePicker};
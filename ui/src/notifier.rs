@@ -0,0 +1,164 @@
+use std::fmt::Debug;
+
+/// Tells the user about game activity while the game is not on screen. Called from the UI thread, so implementations
+/// must not block.
+pub trait Notifier: Debug {
+    fn notify(&self, summary: &str, body: &str) -> anyhow::Result<()>;
+}
+
+/// Writes notifications to the log, used when desktop notifications are unavailable
+#[derive(Debug, Default)]
+pub struct LogNotifier;
+
+impl Notifier for LogNotifier {
+    fn notify(&self, summary: &str, body: &str) -> anyhow::Result<()> {
+        log::info!("{}: {}", summary, body);
+        Ok(())
+    }
+}
+
+/// Shows desktop notifications, using D-Bus on Linux. Showing a notification can block until the notification server
+/// replies, so they are shown one after another from a worker thread.
+#[cfg(feature = "notifications")]
+#[derive(Debug)]
+pub struct DesktopNotifier {
+    sender: std::sync::mpsc::Sender<notify_rust::Notification>,
+}
+
+#[cfg(feature = "notifications")]
+impl DesktopNotifier {
+    /// Starts the worker thread, which stops once the notifier is dropped
+    pub fn new() -> anyhow::Result<Self> {
+        let (sender, receiver) = std::sync::mpsc::channel::<notify_rust::Notification>();
+        std::thread::Builder::new()
+            .name("notifications".to_string())
+            .spawn(move || {
+                for notification in receiver {
+                    if let Err(err) = notification.show() {
+                        log::error!("Failed to show notification: {}", err);
+                    }
+                }
+            })?;
+        Ok(Self { sender })
+    }
+}
+
+#[cfg(feature = "notifications")]
+impl Notifier for DesktopNotifier {
+    fn notify(&self, summary: &str, body: &str) -> anyhow::Result<()> {
+        let mut notification = notify_rust::Notification::new();
+        notification.appname("P2P Chess").summary(summary).body(body);
+        self.sender
+            .send(notification)
+            .map_err(|_| anyhow::anyhow!("The notification thread has stopped"))
+    }
+}
+
+/// Tells the player about an event in a game that isn't on screen. While the window is in the background nothing is
+/// on screen, so `notifier` is used if notifications are turned on. Returns true if the event should be shown in the
+/// window instead, because it has focus but shows another game.
+pub fn notify_unless_on_screen(
+    notifier: &dyn Notifier,
+    is_window_active: bool,
+    show_notifications: bool,
+    is_game_open: bool,
+    summary: &str,
+    body: &str,
+) -> bool {
+    if is_window_active {
+        return !is_game_open;
+    }
+    if show_notifications {
+        if let Err(err) = notifier.notify(summary, body) {
+            log::error!("Failed to show notification: {}", err);
+        }
+    }
+    false
+}
+
+/// Returns the desktop notifier, or the log notifier if the `notifications` feature is disabled or the worker thread
+/// couldn't be started
+#[cfg(feature = "notifications")]
+pub fn default_notifier() -> Box<dyn Notifier> {
+    match DesktopNotifier::new() {
+        Ok(notifier) => Box::new(notifier),
+        Err(err) => {
+            log::warn!("Desktop notifications are unavailable: {}", err);
+            Box::new(LogNotifier)
+        },
+    }
+}
+
+#[cfg(not(feature = "notifications"))]
+pub fn default_notifier() -> Box<dyn Notifier> {
+    Box::new(LogNotifier)
+}
+
+#[cfg(test)]
+mod test {
+    use std::cell::RefCell;
+
+    use super::*;
+
+    #[derive(Debug, Default)]
+    struct RecordingNotifier {
+        notifications: RefCell<Vec<(String, String)>>,
+    }
+
+    impl Notifier for RecordingNotifier {
+        fn notify(&self, summary: &str, body: &str) -> anyhow::Result<()> {
+            self.notifications
+                .borrow_mut()
+                .push((summary.to_string(), body.to_string()));
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn log_notifier_never_fails() {
+        assert!(LogNotifier.notify("Your move", "Game 1").is_ok());
+    }
+
+    #[test]
+    fn notifies_only_for_games_not_on_screen() {
+        let notifier = RecordingNotifier::default();
+        // The open game in the focused window needs no notice, another game gets a toast
+        assert!(!notify_unless_on_screen(
+            &notifier,
+            true,
+            true,
+            true,
+            "Your move",
+            "Game 1"
+        ));
+        assert!(notify_unless_on_screen(
+            &notifier,
+            true,
+            true,
+            false,
+            "Your move",
+            "Game 2"
+        ));
+        assert!(notifier.notifications.borrow().is_empty());
+
+        // In the background no game is on screen
+        assert!(!notify_unless_on_screen(
+            &notifier,
+            false,
+            true,
+            true,
+            "Your move",
+            "Game 1"
+        ));
+        assert!(!notify_unless_on_screen(
+            &notifier, false, true, false, "New game", "Game 3"
+        ));
+        assert!(!notify_unless_on_screen(
+            &notifier, false, false, false, "Resigned", "Game 4"
+        ));
+        assert_eq!(*notifier.notifications.borrow(), vec![
+            ("Your move".to_string(), "Game 1".to_string()),
+            ("New game".to_string(), "Game 3".to_string()),
+        ]);
+    }
+}
@@ -0,0 +1,123 @@
+use minifb::Window;
+
+use crate::{
+    clipboard::Clipboard,
+    color::Color,
+    components::{Button, Label, ListBox, TextBox},
+    drawable::{Drawable, FrameBuffer},
+    focus::FocusManager,
+    rect::{Frame, Rect},
+    start_screen::Drawables,
+};
+
+#[derive(Debug)]
+pub struct ProfileScreen {
+    profiles: Vec<String>,
+    profiles_list: ListBox,
+    open_button: Button,
+    name_input: TextBox,
+    create_button: Button,
+    labels: Drawables<Label>,
+    error_label: Label,
+    chosen_profile: Option<String>,
+    focus: FocusManager,
+}
+
+impl ProfileScreen {
+    pub fn new(clipboard: Clipboard, profiles: Vec<String>) -> Self {
+        let mut title_label = Label::new(Frame::new(400, 10, 500, 40));
+        title_label.set_text("Privacy Chess").set_text_color(Color::dark_blue());
+
+        let mut choose_label = Label::new(Frame::new(10, 50, 500, 40));
+        choose_label.set_text("Choose a profile");
+
+        let mut create_label = Label::new(Frame::new(10, 400, 500, 40));
+        create_label.set_text("Or create a new profile");
+
+        let mut error_label = Label::new(Frame::new(10, 530, 500, 40));
+        error_label.set_text("").set_text_color(Color::red());
+
+        let mut profiles_list = ListBox::new(Frame::new(10, 100, 500, 200));
+        profiles_list.set_values(profiles.clone());
+        let mut focus = FocusManager::new();
+        focus.focus(Some(0));
+
+        let mut open_button = Button::new(Rect::new(10, 320, 100, 30, Color::white()));
+        open_button.set_text("Open");
+
+        let mut name_input = TextBox::new(Frame::new(10, 440, 500, 40), clipboard);
+        name_input.set_placeholder("New profile name").set_max_length(Some(32));
+        let mut create_button = Button::new(Rect::new(10, 500, 100, 30, Color::white()));
+        create_button.set_text("Create");
+
+        Self {
+            profiles,
+            profiles_list,
+            open_button,
+            name_input,
+            create_button,
+            labels: Drawables::new(vec![title_label, choose_label, create_label]),
+            error_label,
+            chosen_profile: None,
+            focus,
+        }
+    }
+
+    pub fn update(&mut self, window: &Window) {
+        self.profiles_list.update(window);
+        self.open_button.update(window);
+        self.name_input.update(window);
+        self.create_button.update(window);
+        self.focus.update(window, &mut [
+            &mut self.profiles_list,
+            &mut self.open_button,
+            &mut self.name_input,
+            &mut self.create_button,
+        ]);
+
+        if self.open_button.was_clicked() || self.profiles_list.was_activated() {
+            self.chosen_profile = self.profiles_list.selected().map(ToString::to_string);
+        }
+
+        if self.create_button.was_clicked() {
+            let name = self.name_input.value().trim();
+            if !is_valid_name(name) {
+                self.error_label
+                    .set_text("Profile names may only contain letters, numbers, '-' and '_'");
+            } else if self.profiles.iter().any(|p| p == name) {
+                self.error_label.set_text("Profile already exists");
+            } else {
+                self.chosen_profile = Some(name.to_string());
+            }
+        }
+    }
+
+    pub fn take_chosen_profile(&mut self) -> Option<String> {
+        self.chosen_profile.take()
+    }
+}
+
+impl Drawable for ProfileScreen {
+    fn draw(&mut self, buf: &mut FrameBuffer) {
+        buf.invalidate_widgets(&mut [
+            &mut self.labels,
+            &mut self.profiles_list,
+            &mut self.open_button,
+            &mut self.name_input,
+            &mut self.create_button,
+            &mut self.error_label,
+        ]);
+        buf.clear(Color::black());
+        self.labels.draw(buf);
+        self.profiles_list.draw(buf);
+        self.open_button.draw(buf);
+        self.name_input.draw(buf);
+        self.create_button.draw(buf);
+        self.error_label.draw(buf);
+    }
+}
+
+/// Profile names are used as directory names, so only letters, numbers, '-' and '_' are allowed
+pub fn is_valid_name(name: &str) -> bool {
+    !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
@@ -0,0 +1,186 @@
+use std::f64::consts::{LN_10, PI};
+
+use tanton::Player;
+use tari_comms::types::CommsPublicKey;
+
+use crate::{game::Game, tournament::Outcome};
+
+pub const INITIAL_RATING: f64 = 1500.0;
+pub const INITIAL_DEVIATION: f64 = 350.0;
+/// Keeps ratings responsive after many games
+const MIN_DEVIATION: f64 = 30.0;
+const Q: f64 = LN_10 / 400.0;
+
+/// A Glicko rating
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Rating {
+    pub rating: f64,
+    pub deviation: f64,
+}
+
+impl Default for Rating {
+    fn default() -> Self {
+        Self {
+            rating: INITIAL_RATING,
+            deviation: INITIAL_DEVIATION,
+        }
+    }
+}
+
+impl Rating {
+    /// Returns the rating after a single game against `opponent`, where `score` is 1 for a win, 0.5 for a draw and 0
+    /// for a loss.
+    pub fn update(&self, opponent: &Rating, score: f64) -> Rating {
+        let g = g(opponent.deviation);
+        let expected = 1.0 / (1.0 + 10f64.powf(-g * (self.rating - opponent.rating) / 400.0));
+        let d_squared = 1.0 / (Q * Q * g * g * expected * (1.0 - expected));
+        let denom = 1.0 / (self.deviation * self.deviation) + 1.0 / d_squared;
+        Rating {
+            rating: self.rating + Q / denom * g * (score - expected),
+            deviation: (1.0 / denom).sqrt().max(MIN_DEVIATION),
+        }
+    }
+}
+
+fn g(deviation: f64) -> f64 {
+    1.0 / (1.0 + 3.0 * Q * Q * deviation * deviation / (PI * PI)).sqrt()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Score {
+    Win,
+    Draw,
+    Loss,
+}
+
+impl Score {
+    /// Our score in a completed game
+    pub fn from_game(game: &Game) -> Option<Self> {
+        let score = match Outcome::from_game(game)? {
+            Outcome::Draw => Score::Draw,
+            Outcome::WhiteWins if game.player == Player::White => Score::Win,
+            Outcome::BlackWins if game.player == Player::Black => Score::Win,
+            _ => Score::Loss,
+        };
+        Some(score)
+    }
+
+    pub fn value(&self) -> f64 {
+        match self {
+            Score::Win => 1.0,
+            Score::Draw => 0.5,
+            Score::Loss => 0.0,
+        }
+    }
+}
+
+/// Our wins, draws and losses against a single opponent
+#[derive(Debug, Clone, Copy, Default)]
+pub struct HeadToHead {
+    pub wins: u32,
+    pub draws: u32,
+    pub losses: u32,
+}
+
+impl HeadToHead {
+    pub fn played(&self) -> u32 {
+        self.wins + self.draws + self.losses
+    }
+
+    fn record(&mut self, score: Score) {
+        match score {
+            Score::Win => self.wins += 1,
+            Score::Draw => self.draws += 1,
+            Score::Loss => self.losses += 1,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct OpponentStats {
+    pub public_key: CommsPublicKey,
+    pub rating: Rating,
+    pub record: HeadToHead,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Ratings {
+    pub ours: Rating,
+    pub record: HeadToHead,
+    /// Sorted by rating, highest first
+    pub opponents: Vec<OpponentStats>,
+}
+
+impl Ratings {
+    /// Replays completed games in the order they finished. The same games always produce the same ratings, regardless
+    /// of the order they are given in.
+    pub fn compute<'a, I: IntoIterator<Item = &'a Game>>(games: I) -> Self {
+        let mut games = games
+            .into_iter()
+            .filter(|g| !g.is_spectating())
+            .filter_map(|g| Score::from_game(g).map(|s| (g, s)))
+            .collect::<Vec<_>>();
+        games.sort_by_key(|(g, _)| (g.last_activity, g.id));
+
+        let mut ratings = Ratings::default();
+        for (game, score) in games {
+            let idx = match ratings.opponents.iter().position(|o| o.public_key == game.opponent) {
+                Some(idx) => idx,
+                None => {
+                    ratings.opponents.push(OpponentStats {
+                        public_key: game.opponent.clone(),
+                        rating: Rating::default(),
+                        record: HeadToHead::default(),
+                    });
+                    ratings.opponents.len() - 1
+                },
+            };
+            let opponent = &mut ratings.opponents[idx];
+            let ours = ratings.ours.update(&opponent.rating, score.value());
+            opponent.rating = opponent.rating.update(&ratings.ours, 1.0 - score.value());
+            opponent.record.record(score);
+            ratings.ours = ours;
+            ratings.record.record(score);
+        }
+
+        // Stable sort keeps the order deterministic for equal ratings
+        ratings
+            .opponents
+            .sort_by(|a, b| b.rating.rating.total_cmp(&a.rating.rating));
+        ratings
+    }
+
+    pub fn opponent(&self, public_key: &CommsPublicKey) -> Option<&OpponentStats> {
+        self.opponents.iter().find(|o| o.public_key == *public_key)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn update_matches_glicko_example() {
+        // Example from Glickman's paper, applied one game at a time
+        let player = Rating {
+            rating: 1500.0,
+            deviation: 200.0,
+        };
+        let rating = player.update(
+            &Rating {
+                rating: 1400.0,
+                deviation: 30.0,
+            },
+            1.0,
+        );
+        assert!((rating.rating - 1563.4).abs() < 0.1, "{:?}", rating);
+        assert!((rating.deviation - 175.2).abs() < 0.1, "{:?}", rating);
+    }
+
+    #[test]
+    fn draw_between_equals_keeps_rating() {
+        let rating = Rating::default().update(&Rating::default(), 0.5);
+        assert!((rating.rating - INITIAL_RATING).abs() < f64::EPSILON);
+        assert!(rating.deviation < INITIAL_DEVIATION);
+    }
+}
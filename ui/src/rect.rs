@@ -1,3 +1,5 @@
+use serde::{Deserialize, Serialize};
+
 use crate::{
     color::Color,
     drawable::{Drawable, FrameBuffer},
@@ -41,10 +43,25 @@ impl Rect {
         self
     }
 
+    pub fn set_frame(&mut self, frame: Frame) -> &mut Self {
+        self.frame = frame;
+        self
+    }
+
+    pub fn set_position(&mut self, x: u32, y: u32) -> &mut Self {
+        self.frame.x = x;
+        self.frame.y = y;
+        self
+    }
+
     pub fn is_in_boundary(&self, x: u32, y: u32) -> bool {
         self.frame.is_in_boundary(x, y)
     }
 
+    pub fn frame(&self) -> Frame {
+        self.frame
+    }
+
     pub fn x(&self) -> u32 {
         self.frame.x
     }
@@ -64,14 +81,19 @@ impl Rect {
 
 impl Drawable for Rect {
     fn draw(&mut self, buf: &mut FrameBuffer) {
-        self.frame.fill(self.bg_color.to_rgba(), buf);
+        if self.bg_color.a == 0xff {
+            self.frame.fill(self.bg_color.to_rgba(), buf);
+        } else {
+            // Semi-transparent rects are overlays, blended over what is already drawn
+            self.frame.blend(self.bg_color, buf);
+        }
         if self.border_size > 0 {
             self.frame.draw_border(self.border_size, self.border_color, buf);
         }
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Frame {
     pub x: u32,
     pub y: u32,
@@ -87,6 +109,22 @@ impl Frame {
     pub fn is_in_boundary(&self, x: u32, y: u32) -> bool {
         x >= self.x && x <= self.x + self.w && y >= self.y && y <= self.y + self.h
     }
+
+    /// Returns true if any pixel is in both frames
+    pub fn intersects(&self, other: &Frame) -> bool {
+        self.x <= other.x + other.w &&
+            other.x <= self.x + self.w &&
+            self.y <= other.y + other.h &&
+            other.y <= self.y + self.h
+    }
+
+    /// The smallest frame containing both frames
+    pub fn union(&self, other: &Frame) -> Frame {
+        let (x, y) = (self.x.min(other.x), self.y.min(other.y));
+        let right = (self.x + self.w).max(other.x + other.w);
+        let bottom = (self.y + self.h).max(other.y + other.h);
+        Frame::new(x, y, right - x, bottom - y)
+    }
 }
 
 impl Frame {
@@ -107,6 +145,12 @@ impl Frame {
         }
     }
 
+    pub fn blend(&self, color: Color, buf: &mut FrameBuffer) {
+        for i in 0..=self.h {
+            buf.blend_line(self.x, self.y + i, self.w, color);
+        }
+    }
+
     pub fn draw_border(&self, size: u32, color: Color, buf: &mut FrameBuffer) {
         let bottom = self.h.saturating_sub(size);
         let color = color.to_rgba();
@@ -1,13 +1,10 @@
-use std::{
-    fs,
-    path::Path,
-    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
-};
+use std::{collections::VecDeque, fs, path::Path, time::Instant};
 
 use anyhow::anyhow;
 use minifb::Window;
-use p2p_chess_channel::{ChessOperation, MessageChannel, OperationType, TryRecvError, TrySendError};
+use p2p_chess_channel::{ChessOperation, MessageChannel, OperationType, TournamentPairing, TryRecvError, TrySendError};
 use rand::{rngs::OsRng, RngCore};
+use serde::de::DeserializeOwned;
 use tanton::{BitMove, Player};
 use tari_comms::types::CommsPublicKey;
 use tari_crypto::tari_utilities::encoding::Base58;
@@ -15,13 +12,26 @@ use tari_crypto::tari_utilities::encoding::Base58;
 use crate::{
     board,
     clipboard::Clipboard,
-    color::Color,
-    drawable::{Drawable, FrameBuffer},
-    game::{Game, GameCollection, GameResult},
-    game_screen::{GameConfig, GameScreen},
+    components::{CommandPalette, Dialog, PaletteEntry, ToastKind, Toasts},
+    drawable::{Drawable, FrameBuffer, Widget},
+    game::{current_timestamp, short_key, Game, GameArchive, GameCollection, GameResult, Takeback, Watching},
+    game_screen::{GameConfig, GameScreen, GameStatus},
+    keybindings::{Command, KeyBindings},
+    notifier::{self, default_notifier, Notifier},
+    rating::Ratings,
+    settings::{Settings, SettingsOverrides},
+    settings_screen::SettingsScreen,
+    sound::{SoundEffect, Sounds},
     start_screen::StartScreen,
+    stats_screen::StatsScreen,
+    theme::Theme,
+    tournament::{Outcome, Pairing, Tournament, TournamentCollection, TournamentFormat},
+    tournament_screen::TournamentScreen,
 };
 
+/// Characters of a public key shown in notifications, dialogs and toasts
+const NOTIFICATION_KEY_LEN: usize = 8;
+
 #[derive(Debug)]
 pub struct ScreenManager {
     config: GameConfig,
@@ -30,7 +40,52 @@ pub struct ScreenManager {
     public_key: CommsPublicKey,
     channel: MessageChannel<ChessOperation>,
     games: GameCollection,
+    tournaments: TournamentCollection,
+    archive: GameArchive,
+    /// The settings in use, which are the saved settings with the command line overrides applied
+    settings: Settings,
+    /// The settings as saved in the settings file, edited on the settings screen
+    saved_settings: Settings,
+    settings_overrides: SettingsOverrides,
+    key_bindings: KeyBindings,
+    themes: Vec<Theme>,
+    sounds: Sounds,
+    notifier: Box<dyn Notifier>,
+    /// Moves that arrive while the window is in the background are announced with a sound
+    is_window_active: bool,
     last_sync: Instant,
+    /// The next frame is redrawn in full, set when switching screens
+    is_screen_changed: bool,
+    /// Shown over the screen, which gets no input until the dialog is closed
+    dialog: Option<(Dialog, DialogAction)>,
+    /// Invites to watch a game, with the inviting player, that have not been shown yet
+    spectate_invites: Vec<(u32, CommsPublicKey)>,
+    /// Games we have asked to watch, with the player who invited us
+    spectate_requests: Vec<(u32, CommsPublicKey)>,
+    /// Tournament messages that did not fit in the channel, sent in order on the following frames
+    unsent: VecDeque<ChessOperation>,
+    /// Shown over the screen like the dialog
+    palette: Option<CommandPalette<PaletteItem>>,
+    toasts: Toasts,
+    is_quit_requested: bool,
+}
+
+/// What to do when the first button of a dialog is chosen
+#[derive(Debug, Clone)]
+enum DialogAction {
+    /// Asks the opponent to take back our last move in a game
+    RequestTakeback(u32),
+    Resign(u32),
+    OfferDraw(u32),
+    AcceptDraw(u32),
+    /// Asks the inviting player to let us watch a game
+    WatchGame(u32, CommsPublicKey),
+}
+
+#[derive(Debug, Clone, Copy)]
+enum PaletteItem {
+    Command(Command),
+    Game(u32),
 }
 
 impl ScreenManager {
@@ -40,38 +95,266 @@ impl ScreenManager {
         public_key: CommsPublicKey,
     ) -> anyhow::Result<Self> {
         let clipboard = Clipboard::initialize()?;
-        let games = load_games(&config.save_path)?;
-        Ok(Self {
+        let games = load_or_default(&config.save_path)?;
+        let tournaments = load_or_default(&config.tournaments_path)?;
+        let archive = load_or_default(&config.archive_path)?;
+        let settings = Settings::load(&config.settings_path)?;
+        let themes = Theme::load_all(&config.themes_path);
+        let start_screen = StartScreen::new(
+            clipboard.clone(),
+            public_key.clone(),
+            config.window_width,
+            config.window_height,
+        );
+        let toasts = Toasts::new(config.window_width, config.window_height);
+        let mut manager = Self {
             config,
-            active_screen: Screen::Start(StartScreen::new(clipboard.clone(), public_key.clone())),
+            active_screen: Screen::Start(Box::new(start_screen)),
             public_key,
             clipboard,
             channel,
             games,
+            tournaments,
+            archive,
+            settings: settings.clone(),
+            saved_settings: settings,
+            settings_overrides: SettingsOverrides::default(),
+            key_bindings: KeyBindings::default(),
+            themes,
+            sounds: Sounds::initialize(),
+            notifier: default_notifier(),
+            is_window_active: true,
             last_sync: Instant::now(),
-        })
+            is_screen_changed: false,
+            dialog: None,
+            spectate_invites: Vec::new(),
+            spectate_requests: Vec::new(),
+            unsent: VecDeque::new(),
+            palette: None,
+            toasts,
+            is_quit_requested: false,
+        };
+
+        manager.apply_settings();
+
+        // Archive games that completed before the archive existed
+        let mut is_archived = false;
+        for game in manager.games.iter() {
+            is_archived |= manager.archive.add(game);
+        }
+        if is_archived {
+            manager.save_archive()?;
+        }
+        Ok(manager)
+    }
+
+    /// Settings given on the command line, used instead of the saved settings but never saved
+    pub fn set_settings_overrides(&mut self, overrides: SettingsOverrides) -> &mut Self {
+        self.settings_overrides = overrides;
+        self.apply_settings();
+        self
+    }
+
+    fn apply_settings(&mut self) {
+        self.settings = self.settings_overrides.apply(&self.saved_settings);
+        let theme = find_theme(&self.themes, &self.settings.theme);
+        if theme.name != self.config.theme.name {
+            if let Screen::Game(ref mut screen) = self.active_screen {
+                screen.set_theme(&theme);
+            }
+        }
+        self.config.theme = theme;
+        self.sounds
+            .set_volume(self.settings.sound_volume)
+            .set_muted(self.settings.is_sound_muted);
+        self.key_bindings = KeyBindings::new(&self.settings.key_bindings);
+    }
+
+    /// True once the quit command was run
+    pub fn is_quit_requested(&self) -> bool {
+        self.is_quit_requested
+    }
+
+    /// Shows the game with `game_id`
+    fn open_game(&mut self, game_id: u32) {
+        let game = match self.games.get(game_id) {
+            Some(game) => game,
+            None => return,
+        };
+        self.is_screen_changed = true;
+        // TODO: clean up game state management in general - just rushing to be able to play this right now
+        let mut screen = GameScreen::new(
+            game.id,
+            game.seq,
+            self.config.clone(),
+            self.clipboard.clone(),
+            game.player,
+            game.opponent.clone(),
+            &game.board_fen,
+        );
+        screen
+            .set_spectating(game.is_spectating())
+            .set_move_hints(self.settings.show_move_hints)
+            .set_confirm_moves(self.settings.confirm_moves);
+        if let Some(mv) = game.pending_move {
+            screen.set_pending_move(BitMove::new(mv));
+        }
+        self.active_screen = Screen::Game(Box::new(screen));
+    }
+
+    /// The game on screen, if any
+    fn active_game(&self) -> Option<&Game> {
+        match self.active_screen {
+            Screen::Game(ref screen) => self.games.get(screen.game_id()),
+            _ => None,
+        }
+    }
+
+    fn run_command(&mut self, command: Command) {
+        match command {
+            Command::CommandPalette => self.open_palette(),
+            Command::FlipBoard => {
+                if let Screen::Game(ref mut screen) = self.active_screen {
+                    screen.flip_board();
+                }
+            },
+            Command::Resign => {
+                let game = match self.active_game() {
+                    Some(game) if !game.has_completed() && !game.is_spectating() => game,
+                    _ => return,
+                };
+                let message = format!(
+                    "Resign game {} against {}?",
+                    game.id,
+                    short_key(&game.opponent, NOTIFICATION_KEY_LEN)
+                );
+                let action = DialogAction::Resign(game.id);
+                self.open_dialog(Dialog::new("Resign", &message, &["Resign", "Cancel"]), action);
+            },
+            Command::OfferDraw => {
+                let game = match self.active_game() {
+                    Some(game) if !game.has_completed() && !game.is_spectating() => game,
+                    _ => return,
+                };
+                if game.draw_offer == Some(game.seq) {
+                    let (dialog, action) = accept_draw_dialog(game);
+                    self.open_dialog(dialog, action);
+                } else if game.sent_draw_offer == Some(game.seq) {
+                    self.toasts.push("A draw has already been offered", ToastKind::Info);
+                } else {
+                    let action = DialogAction::OfferDraw(game.id);
+                    self.open_dialog(
+                        Dialog::new("Draw", "Offer your opponent a draw?", &["Offer", "Cancel"]),
+                        action,
+                    );
+                }
+            },
+            Command::NextGame => self.open_adjacent_game(true),
+            Command::PreviousGame => self.open_adjacent_game(false),
+            Command::CopyPublicKey => match self.clipboard.set_contents(self.public_key.to_base58()) {
+                Ok(()) => {
+                    self.toasts.push("Public key copied", ToastKind::Info);
+                },
+                Err(err) => {
+                    self.toasts.push(format!("Failed to copy: {}", err), ToastKind::Error);
+                },
+            },
+            Command::Quit => self.is_quit_requested = true,
+        }
+    }
+
+    /// Shows the game after or before the one on screen, in the order of the games list
+    fn open_adjacent_game(&mut self, is_next: bool) {
+        let game_ids = self.games.iter().map(|g| g.id).collect::<Vec<_>>();
+        let current = match self.active_game() {
+            Some(game) => game.id,
+            None => return,
+        };
+        if let Some(pos) = game_ids.iter().position(|id| *id == current) {
+            let len = game_ids.len();
+            let next = if is_next {
+                (pos + 1) % len
+            } else {
+                (pos + len - 1) % len
+            };
+            if game_ids[next] != current {
+                self.open_game(game_ids[next]);
+            }
+        }
+    }
+
+    fn open_palette(&mut self) {
+        let commands = Command::ALL
+            .iter()
+            .filter(|command| **command != Command::CommandPalette)
+            .map(|command| PaletteEntry {
+                title: command.title().to_string(),
+                detail: self
+                    .key_bindings
+                    .binding(*command)
+                    .map(|b| b.to_string())
+                    .unwrap_or_default(),
+                value: PaletteItem::Command(*command),
+            });
+        let games = self.games.iter().map(|game| PaletteEntry {
+            title: format!(
+                "Game {} vs {}",
+                game.id,
+                short_key(&game.opponent, NOTIFICATION_KEY_LEN)
+            ),
+            detail: game_detail(game).to_string(),
+            value: PaletteItem::Game(game.id),
+        });
+        let mut palette = CommandPalette::new(commands.chain(games).collect(), self.clipboard.clone());
+        palette.set_window_size(self.config.window_width, self.config.window_height);
+        self.palette = Some(palette);
+    }
+
+    /// Updates and draws the open command palette and runs the chosen entry. Returns true if the screen should get no
+    /// input this frame.
+    fn update_palette(&mut self, window: &Window, buf: &mut FrameBuffer) -> bool {
+        let palette = match self.palette {
+            Some(ref mut palette) => palette,
+            None => return false,
+        };
+        palette.update(window);
+        if !palette.is_closed() {
+            palette.draw(buf);
+            return true;
+        }
+        let chosen = palette.take_chosen();
+        self.palette = None;
+        self.is_screen_changed = true;
+        match chosen {
+            Some(PaletteItem::Command(command)) => self.run_command(command),
+            Some(PaletteItem::Game(game_id)) => self.open_game(game_id),
+            None => {},
+        }
+        true
     }
 
     fn create_new_game(&mut self, opponent: CommsPublicKey) {
         // TODO: allow player to choose black/white
         let id = OsRng.next_u32();
-        self.active_screen = Screen::Game(GameScreen::new(
+        let mut screen = GameScreen::new(
             id,
             0,
             self.config.clone(),
+            self.clipboard.clone(),
             Player::White,
             opponent.clone(),
             board::INITIAL_BOARD,
-        ));
-        self.games.insert(Game {
+        );
+        screen
+            .set_move_hints(self.settings.show_move_hints)
+            .set_confirm_moves(self.settings.confirm_moves);
+        self.active_screen = Screen::Game(Box::new(screen));
+        self.games.insert(Game::new(
             id,
-            opponent: opponent.clone(),
-            board_fen: board::INITIAL_BOARD.to_string(),
-            seq: 0,
-            player: Player::White,
-            result: GameResult::None,
-            last_activity: current_timestamp(),
-        });
+            opponent.clone(),
+            Player::White,
+            board::INITIAL_BOARD.to_string(),
+        ));
         self.save_games().unwrap();
         self.send_message(ChessOperation {
             game_id: id,
@@ -80,43 +363,142 @@ impl ScreenManager {
             from: self.public_key.clone(),
             operation: OperationType::NewGame {
                 player: Player::Black as u8,
+                pairing: None,
             },
         })
         .unwrap();
     }
 
+    pub fn set_window_active(&mut self, is_window_active: bool) {
+        self.is_window_active = is_window_active;
+    }
+
+    /// Lays out the screens for a new window size. Screens opened later are sized from the config.
+    pub fn resize(&mut self, window_width: u32, window_height: u32) {
+        self.config.window_width = window_width;
+        self.config.window_height = window_height;
+        match self.active_screen {
+            Screen::Start(ref mut screen) => {
+                screen.resize(window_width, window_height);
+            },
+            Screen::Game(ref mut screen) => {
+                screen.resize(window_width, window_height);
+            },
+            Screen::Tournaments(ref mut screen) => {
+                screen.resize(window_width, window_height);
+            },
+            Screen::Stats(ref mut screen) => {
+                screen.resize(window_width, window_height);
+            },
+            Screen::Settings(ref mut screen) => {
+                screen.resize(window_width, window_height);
+            },
+        }
+        if let Some((ref mut dialog, _)) = self.dialog {
+            dialog.set_window_size(window_width, window_height);
+        }
+        if let Some(ref mut palette) = self.palette {
+            palette.set_window_size(window_width, window_height);
+        }
+        self.toasts.set_window_size(window_width, window_height);
+    }
+
     pub fn render(&mut self, window: &Window, buf: &mut FrameBuffer) {
+        if self.dialog.is_none() && self.palette.is_none() {
+            if let Some(command) = self.key_bindings.pressed(window) {
+                self.run_command(command);
+            }
+        }
+        if self.dialog.is_none() && self.palette.is_none() && !self.spectate_invites.is_empty() {
+            let (game_id, inviter) = self.spectate_invites.remove(0);
+            let message = format!(
+                "{} invites you to watch game {}",
+                short_key(&inviter, NOTIFICATION_KEY_LEN),
+                game_id
+            );
+            let mut dialog = Dialog::new("Spectate", &message, &["Watch", "Ignore"]);
+            dialog.set_default_button(1);
+            self.open_dialog(dialog, DialogAction::WatchGame(game_id, inviter));
+        }
+        if std::mem::take(&mut self.is_screen_changed) {
+            buf.invalidate_all();
+        }
+        let is_overlay_open = self.update_dialog(window, buf) || self.update_palette(window, buf);
+        if !is_overlay_open {
+            self.toasts.update();
+            if let Some(frame) = self.toasts.take_dirty() {
+                buf.invalidate(frame);
+            }
+        }
+        let mut completed_game = None;
+        let mut tournament_request = None;
+        let mut settle_request = None;
+        let mut dialog = None;
+        let mut is_settings_changed = false;
         match self.active_screen {
+            _ if is_overlay_open => {},
             Screen::Start(ref mut main_screen) => {
                 main_screen.update(window);
                 self.games.sort();
                 main_screen.set_games(&self.games);
                 main_screen.draw(buf);
                 let idx = main_screen.show_game_clicked();
+                let invited_spectator = main_screen.take_invited_spectator();
+                let (is_tournaments_clicked, is_stats_clicked, is_settings_clicked) = (
+                    main_screen.tournaments_clicked(),
+                    main_screen.stats_clicked(),
+                    main_screen.settings_clicked(),
+                );
                 if let Some(pk) = main_screen.new_game_clicked() {
                     match CommsPublicKey::from_base58(pk) {
                         Ok(pk) => {
-                            buf.clear(Color::black());
+                            self.is_screen_changed = true;
                             self.create_new_game(pk);
                         },
                         Err(_) => {
-                            main_screen.set_input_error("Invalid public key");
+                            self.toasts.push("Invalid public key", ToastKind::Error);
                         },
                     }
                 }
+                if let Some((idx, pk)) = invited_spectator {
+                    let result = CommsPublicKey::from_base58(&pk)
+                        .map_err(|_| anyhow!("Invalid public key"))
+                        .and_then(|spectator| {
+                            let game_id = self.games[idx].id;
+                            invite_spectator(&mut self.games, &self.channel, &self.public_key, game_id, spectator)
+                        });
+                    if let Err(err) = result {
+                        self.toasts.push(err.to_string(), ToastKind::Error);
+                    }
+                }
                 if let Some(idx) = idx {
-                    let game = &self.games[idx];
-                    dbg!(game);
-                    buf.clear(Color::black());
-                    // TODO: clean up game state management in general - just rushing to be able to play this right now
-                    self.active_screen = Screen::Game(GameScreen::new(
-                        game.id,
-                        game.seq,
-                        self.config.clone(),
-                        game.player,
-                        game.opponent.clone(),
-                        &game.board_fen,
-                    ));
+                    let game_id = self.games[idx].id;
+                    self.open_game(game_id);
+                } else if is_tournaments_clicked {
+                    self.is_screen_changed = true;
+                    self.active_screen = Screen::Tournaments(Box::new(TournamentScreen::new(
+                        self.clipboard.clone(),
+                        self.public_key.clone(),
+                        self.config.window_width,
+                        self.config.window_height,
+                    )));
+                } else if is_stats_clicked {
+                    self.is_screen_changed = true;
+                    self.active_screen = Screen::Stats(Box::new(StatsScreen::new(
+                        &Ratings::compute(self.archive.iter()),
+                        self.config.window_width,
+                        self.config.window_height,
+                    )));
+                } else if is_settings_clicked {
+                    self.is_screen_changed = true;
+                    self.active_screen = Screen::Settings(Box::new(SettingsScreen::new(
+                        self.saved_settings.clone(),
+                        self.themes.iter().map(|t| t.name.clone()).collect(),
+                        &self.settings_overrides,
+                        self.clipboard.clone(),
+                        self.config.window_width,
+                        self.config.window_height,
+                    )));
                 }
             },
             Screen::Game(ref mut game) => {
@@ -124,31 +506,84 @@ impl ScreenManager {
                 if let Some(mv) = game.take_last_move_played() {
                     game.inc_seq();
                     dbg!("move played", mv, game.seq());
-                    let msg = ChessOperation {
-                        game_id: game.game_id(),
-                        seq: game.seq(),
-                        to: game.opponent().clone(),
-                        from: self.public_key.clone(),
-                        operation: OperationType::MovePlayed {
-                            mv: mv.get_raw(),
-                            board: game.to_board_fen(),
-                        },
-                    };
-                    self.channel.try_send(msg).unwrap();
                     if let Some(game_mut) = self.games.get_mut(game.game_id()) {
+                        game_mut.record_move(mv.get_raw());
                         game_mut.seq = game.seq();
                         game_mut.board_fen = game.to_board_fen();
                         game_mut.last_activity = current_timestamp();
+                        if game_mut.update_result() {
+                            completed_game = Some(game_mut.id);
+                        } else {
+                            self.sounds.play(SoundEffect::for_move(mv, &game_mut.board_fen));
+                        }
+                        send_move(&self.channel, &self.public_key, game_mut, mv);
+                    }
+                }
+
+                if let Some(game_mut) = self.games.get_mut(game.game_id()) {
+                    game_mut.pending_move = game.pending_move().map(|mv| mv.get_raw());
+                    if game.take_takeback_requested() && !game_mut.has_completed() && game_mut.sent_takeback.is_none() {
+                        dialog = Some((
+                            Dialog::new("Takeback", "Ask your opponent to take back your last move?", &[
+                                "Ask", "Cancel",
+                            ]),
+                            DialogAction::RequestTakeback(game_mut.id),
+                        ));
+                    }
+                    if let Some(accepted) = game.take_takeback_decision() {
+                        if let Some(request) = game_mut.takeback_request.take() {
+                            let accepted = accepted && request.seq == game_mut.seq && game_mut.take_back(request.plies);
+                            send_takeback_response(&self.channel, &self.public_key, game_mut, request, accepted);
+                            if accepted {
+                                game.take_back(&game_mut.board_fen, last_move(game_mut), game_mut.seq);
+                            }
+                        }
+                    }
+                    if let Some(line) = game.take_saved_line() {
+                        game_mut.add_conditional_line(&line);
+                    }
+                    if game.take_clear_conditional_requested() {
+                        game_mut.conditional_moves.clear();
+                    }
+                    game.set_conditional_lines(game_mut.conditional_lines());
+                    game.set_move_history(&game_mut.start_fen, &game_mut.moves);
+
+                    if let Some((spectator, accepted)) = game.take_spectator_decision() {
+                        game_mut.spectator_requests.retain(|s| *s != spectator);
+                        if accepted && !game_mut.spectators.contains(&spectator) {
+                            game_mut.spectators.push(spectator.clone());
+                        }
+                        let msg = ChessOperation {
+                            game_id: game_mut.id,
+                            seq: game_mut.seq,
+                            to: game_mut.opponent.clone(),
+                            from: self.public_key.clone(),
+                            operation: OperationType::SpectatorResponse { spectator, accepted },
+                        };
+                        if let Err(err) = self.channel.try_send(msg) {
+                            log::error!("Failed to send spectator response: {}", err);
+                        }
+                    }
+                    // Only one request is shown at a time, the takeback is more urgent
+                    game.set_spectator_request(game_mut.spectator_requests.first().cloned())
+                        .set_takeback_request(game_mut.takeback_request.map(|t| t.plies))
+                        .set_takeback_sent(game_mut.sent_takeback.is_some());
+                    if let Some(status) = off_board_status(game_mut) {
+                        game.set_game_status(status);
                     }
                 }
 
                 game.draw(buf);
 
                 if game.was_back_clicked() {
-                    buf.clear(Color::black());
-                    self.active_screen =
-                        Screen::Start(StartScreen::new(self.clipboard.clone(), self.public_key.clone()));
-                } else if game.seq() > 0 && self.last_sync.elapsed() > Duration::from_secs(30) {
+                    self.is_screen_changed = true;
+                    self.active_screen = Screen::Start(Box::new(StartScreen::new(
+                        self.clipboard.clone(),
+                        self.public_key.clone(),
+                        self.config.window_width,
+                        self.config.window_height,
+                    )));
+                } else if game.seq() > 0 && self.last_sync.elapsed() > self.settings.sync_interval() {
                     let msg = ChessOperation {
                         game_id: game.game_id(),
                         seq: game.seq(),
@@ -162,12 +597,95 @@ impl ScreenManager {
                     self.last_sync = Instant::now();
                 }
             },
+            Screen::Settings(ref mut screen) => {
+                screen.update(window);
+                // Going back commits the port being typed, which must be saved before leaving
+                let is_back_clicked = screen.was_back_clicked();
+                if let Some(settings) = screen.take_changed_settings() {
+                    if let Err(err) = settings.save(&self.config.settings_path) {
+                        log::error!("failed to save settings: {}", err);
+                    }
+                    self.saved_settings = settings;
+                    is_settings_changed = true;
+                }
+                screen.draw(buf);
+                if is_back_clicked {
+                    self.is_screen_changed = true;
+                    self.active_screen = Screen::Start(Box::new(StartScreen::new(
+                        self.clipboard.clone(),
+                        self.public_key.clone(),
+                        self.config.window_width,
+                        self.config.window_height,
+                    )));
+                }
+            },
+            Screen::Stats(ref mut screen) => {
+                screen.update(window);
+                screen.draw(buf);
+                if screen.was_back_clicked() {
+                    self.is_screen_changed = true;
+                    self.active_screen = Screen::Start(Box::new(StartScreen::new(
+                        self.clipboard.clone(),
+                        self.public_key.clone(),
+                        self.config.window_width,
+                        self.config.window_height,
+                    )));
+                }
+            },
+            Screen::Tournaments(ref mut screen) => {
+                screen.update(window);
+                tournament_request = screen.take_create_request();
+                settle_request = screen.take_settle_request();
+                screen.set_tournaments(&self.tournaments);
+                screen.draw(buf);
+                if screen.was_back_clicked() {
+                    self.is_screen_changed = true;
+                    self.active_screen = Screen::Start(Box::new(StartScreen::new(
+                        self.clipboard.clone(),
+                        self.public_key.clone(),
+                        self.config.window_width,
+                        self.config.window_height,
+                    )));
+                }
+            },
+        }
+
+        if !is_overlay_open {
+            self.toasts.draw(buf);
+        }
+        if is_settings_changed {
+            self.apply_settings();
+        }
+        if let Some((dialog, action)) = dialog {
+            self.open_dialog(dialog, action);
+        }
+
+        if let Some(game_id) = completed_game {
+            if let Err(err) = self.on_game_completed(game_id) {
+                log::error!("failed to report game result: {}", err);
+            }
+        }
+
+        if let Some((format, participants)) = tournament_request {
+            if let Err(err) = self.create_tournament(format, participants) {
+                if let Screen::Tournaments(ref mut screen) = self.active_screen {
+                    screen.set_error(err.to_string());
+                }
+            }
+        }
+        if let Some((tournament_id, game_id, outcome)) = settle_request {
+            if let Err(err) = self.settle_tournament_game(tournament_id, game_id, outcome) {
+                if let Screen::Tournaments(ref mut screen) = self.active_screen {
+                    screen.set_error(err.to_string());
+                }
+            }
         }
 
         if let Err(err) = self.save_games() {
             log::error!("save failed: {}", err);
         }
 
+        self.send_unsent();
         match self.channel.try_recv() {
             Ok(op) => {
                 self.last_sync = Instant::now();
@@ -184,25 +702,179 @@ impl ScreenManager {
         }
     }
 
+    fn open_dialog(&mut self, mut dialog: Dialog, action: DialogAction) {
+        dialog.set_window_size(self.config.window_width, self.config.window_height);
+        self.dialog = Some((dialog, action));
+    }
+
+    /// Updates and draws the open dialog and acts on the chosen button. Returns true if the screen should get no
+    /// input this frame.
+    fn update_dialog(&mut self, window: &Window, buf: &mut FrameBuffer) -> bool {
+        let (dialog, action) = match self.dialog {
+            Some((ref mut dialog, ref action)) => (dialog, action.clone()),
+            None => return false,
+        };
+        dialog.update(window);
+        match dialog.result() {
+            Some(choice) => {
+                self.dialog = None;
+                self.is_screen_changed = true;
+                if choice == 0 {
+                    self.run_dialog_action(action);
+                }
+            },
+            None => dialog.draw(buf),
+        }
+        true
+    }
+
+    fn run_dialog_action(&mut self, action: DialogAction) {
+        match action {
+            DialogAction::RequestTakeback(game_id) => {
+                if let Some(game_mut) = self.games.get_mut(game_id) {
+                    if game_mut.has_completed() || game_mut.sent_takeback.is_some() {
+                        return;
+                    }
+                    let takeback = game_mut.takeback();
+                    game_mut.sent_takeback = Some(takeback);
+                    let msg = ChessOperation {
+                        game_id: game_mut.id,
+                        seq: takeback.seq,
+                        to: game_mut.opponent.clone(),
+                        from: self.public_key.clone(),
+                        operation: OperationType::TakebackRequest { plies: takeback.plies },
+                    };
+                    if let Err(err) = self.channel.try_send(msg) {
+                        log::error!("Failed to send takeback request: {}", err);
+                    }
+                }
+            },
+            DialogAction::Resign(game_id) => {
+                if let Some(game_mut) = self.games.get_mut(game_id) {
+                    if game_mut.has_completed() {
+                        return;
+                    }
+                    game_mut.result = GameResult::WeResigned;
+                    send_game_end(&self.channel, &self.public_key, game_mut, OperationType::Resign);
+                    self.complete_game(game_id);
+                }
+            },
+            DialogAction::OfferDraw(game_id) => {
+                if let Some(game_mut) = self.games.get_mut(game_id) {
+                    if game_mut.has_completed() || game_mut.sent_draw_offer == Some(game_mut.seq) {
+                        return;
+                    }
+                    game_mut.sent_draw_offer = Some(game_mut.seq);
+                    let msg = ChessOperation {
+                        game_id,
+                        seq: game_mut.seq,
+                        to: game_mut.opponent.clone(),
+                        from: self.public_key.clone(),
+                        operation: OperationType::DrawOffer,
+                    };
+                    if let Err(err) = self.channel.try_send(msg) {
+                        log::error!("Failed to send draw offer: {}", err);
+                    }
+                    if let Err(err) = self.save_games() {
+                        log::error!("save failed: {}", err);
+                    }
+                }
+            },
+            DialogAction::AcceptDraw(game_id) => {
+                if let Some(game_mut) = self.games.get_mut(game_id) {
+                    // A move has been played since the offer
+                    if game_mut.has_completed() || game_mut.draw_offer != Some(game_mut.seq) {
+                        return;
+                    }
+                    game_mut.result = GameResult::Draw;
+                    send_game_end(&self.channel, &self.public_key, game_mut, OperationType::DrawAccept);
+                    self.complete_game(game_id);
+                }
+            },
+            DialogAction::WatchGame(game_id, inviter) => {
+                if self.games.get(game_id).is_some() {
+                    return;
+                }
+                let msg = ChessOperation {
+                    game_id,
+                    seq: 0,
+                    to: inviter.clone(),
+                    from: self.public_key.clone(),
+                    operation: OperationType::SpectateAccept,
+                };
+                if let Err(err) = self.channel.try_send(msg) {
+                    log::error!("Failed to accept spectate invite: {}", err);
+                    return;
+                }
+                self.spectate_requests.push((game_id, inviter));
+            },
+        }
+    }
+
+    /// Archives and saves a game that ended other than on the board
+    fn complete_game(&mut self, game_id: u32) {
+        if let Err(err) = self.on_game_completed(game_id) {
+            log::error!("failed to report game result: {}", err);
+        }
+        if let Err(err) = self.save_games() {
+            log::error!("save failed: {}", err);
+        }
+    }
+
     pub fn apply_operation(&mut self, op: ChessOperation) -> anyhow::Result<()> {
         match &op.operation {
-            OperationType::NewGame { player } => {
-                let game = Game {
-                    id: op.game_id,
-                    opponent: op.from,
-                    board_fen: board::INITIAL_BOARD.to_string(),
-                    seq: 0,
-                    player: match *player {
-                        0 => Player::White,
-                        1 => Player::Black,
-                        _ => return Err(anyhow!("Invalid player enum")),
-                    },
-                    result: GameResult::None,
-                    last_activity: SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs(),
+            OperationType::NewGame { player, pairing } => {
+                let player = match *player {
+                    0 => Player::White,
+                    1 => Player::Black,
+                    _ => return Err(anyhow!("Invalid player enum")),
                 };
+                if let Some(pairing) = pairing {
+                    // Updates and pairings can arrive in any order, so the game is added from whichever lists it first
+                    let tournament = match self.tournaments.get(pairing.tournament_id) {
+                        Some(tournament) => tournament,
+                        None => {
+                            log::info!(
+                                "Pairing for game {} arrived before tournament {}",
+                                op.game_id,
+                                pairing.tournament_id
+                            );
+                            return Ok(());
+                        },
+                    };
+                    if tournament.organiser != op.from {
+                        return Err(anyhow!("Tournament pairing from a non-organiser"));
+                    }
+                    match tournament.pairing(op.game_id) {
+                        None => {
+                            log::info!(
+                                "Pairing for game {} arrived before its round of tournament {}",
+                                op.game_id,
+                                pairing.tournament_id
+                            );
+                            return Ok(());
+                        },
+                        Some(p) if p.side_of(&self.public_key) != Some((player, &pairing.opponent)) => {
+                            return Err(anyhow!(
+                                "Pairing for game {} does not match tournament {}",
+                                op.game_id,
+                                pairing.tournament_id
+                            ));
+                        },
+                        Some(_) => {},
+                    }
+                    return self.add_tournament_games(pairing.tournament_id);
+                }
 
+                let game = Game::new(op.game_id, op.from.clone(), player, board::INITIAL_BOARD.to_string());
                 self.games.insert(game);
                 self.save_games()?;
+                self.sounds.play(SoundEffect::Challenge);
+                self.notify_unless_on_screen(
+                    op.game_id,
+                    "New game",
+                    &format!("{} challenged you", short_key(&op.from, NOTIFICATION_KEY_LEN)),
+                );
             },
             OperationType::MovePlayed { board, mv } => {
                 dbg!(board, mv);
@@ -211,61 +883,612 @@ impl ScreenManager {
                         dbg!("ignore move", op.seq, game_mut.seq);
                         return Ok(());
                     }
+                    if !game_mut.is_move_sender(&op.from) {
+                        return Err(anyhow!("Received move for game {} from a non-player", op.game_id));
+                    }
                     // TODO: This requires a lot of honesty :P
+                    game_mut.record_move(*mv);
                     game_mut.board_fen = board.clone();
                     game_mut.seq = op.seq;
-                    game_mut.last_activity = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+                    let san = board::san_moves(&game_mut.start_fen, &game_mut.moves)
+                        .pop()
+                        .unwrap_or_default();
+                    game_mut.last_activity = current_timestamp();
+                    let mut has_completed = game_mut.update_result();
+                    let mut last_move = BitMove::new(*mv);
+
+                    // Automatically reply if we prepared a reply to this move
+                    let reply = if has_completed || game_mut.is_spectating() {
+                        None
+                    } else {
+                        game_mut
+                            .take_conditional_reply(*mv)
+                            .and_then(|reply| board::play_move(&game_mut.board_fen, reply))
+                    };
+                    let has_replied = reply.is_some();
+                    if let Some((reply, board)) = reply {
+                        log::info!("Playing prepared reply {} in game {}", reply, op.game_id);
+                        game_mut.record_move(reply.get_raw());
+                        game_mut.board_fen = board;
+                        game_mut.seq += 1;
+                        has_completed = game_mut.update_result();
+                        send_move(&self.channel, &self.public_key, game_mut, reply);
+                        last_move = reply;
+                    }
+                    let (board, seq) = (game_mut.board_fen.clone(), game_mut.seq);
+
+                    if has_completed {
+                        self.on_game_completed(op.game_id)?;
+                    } else if !self.is_window_active {
+                        self.sounds.play(SoundEffect::IncomingMove);
+                    }
                     self.save_games()?;
+                    self.notify_unless_on_screen(
+                        op.game_id,
+                        "Move played",
+                        &format!(
+                            "{} played {} in game {}",
+                            short_key(&op.from, NOTIFICATION_KEY_LEN),
+                            san,
+                            op.game_id
+                        ),
+                    );
                     if let Screen::Game(ref mut screen) = self.active_screen {
                         dbg!(screen.game_id(), op.game_id);
                         if screen.game_id() == op.game_id {
-                            let mv = BitMove::new(*mv);
-                            log::info!("Move played for active game {}", mv);
-                            screen.set_board_state(&board, Some(mv)).set_seq(op.seq);
+                            if !has_completed && self.is_window_active {
+                                self.sounds.play(SoundEffect::for_move(last_move, &board));
+                            }
+                            log::info!("Move played for active game {}", last_move);
+                            if has_replied {
+                                screen.clear_premove();
+                            }
+                            screen.set_board_state(&board, Some(last_move)).set_seq(seq);
                         }
                     }
                 }
             },
             OperationType::Resign => {
                 if let Some(game_mut) = self.games.get_mut(op.game_id) {
-                    game_mut.result = GameResult::TheyResigned;
+                    if !game_mut.is_move_sender(&op.from) {
+                        return Err(anyhow!("Resignation for game {} from a non-player", op.game_id));
+                    }
+                    if !game_mut.has_completed() {
+                        // A spectated game is kept from white's side
+                        game_mut.result = match game_mut.watching {
+                            Some(ref watching) if watching.white == op.from => GameResult::WeResigned,
+                            _ => GameResult::TheyResigned,
+                        };
+                        self.on_game_completed(op.game_id)?;
+                        self.save_games()?;
+                        self.notify_unless_on_screen(
+                            op.game_id,
+                            "Game over",
+                            &format!(
+                                "{} resigned game {}",
+                                short_key(&op.from, NOTIFICATION_KEY_LEN),
+                                op.game_id
+                            ),
+                        );
+                    }
                 }
             },
             OperationType::Sync { board } => {
                 if let Some(game_mut) = self.games.get_mut(op.game_id) {
+                    // When spectating, the opponent is the player that invited us
+                    if op.from != game_mut.opponent && !game_mut.spectators.contains(&op.from) {
+                        return Err(anyhow!("Sync for game {} from a non-player", op.game_id));
+                    }
                     match op.seq {
                         seq if seq < game_mut.seq => {
                             // Send a message back with our state
                             let msg = ChessOperation {
                                 game_id: game_mut.id,
                                 seq: game_mut.seq,
-                                to: game_mut.opponent.clone(),
+                                to: op.from.clone(),
                                 from: self.public_key.clone(),
                                 operation: OperationType::Sync {
                                     board: game_mut.board_fen.clone(),
                                 },
                             };
-                            self.send_message(msg).unwrap();
+                            self.send_message(msg)?;
                         },
                         seq if seq == game_mut.seq => {},
+                        // Only the opponent (or the inviting player when spectating) may update our game state
+                        _ if op.from != game_mut.opponent => {},
                         _ => {
                             // Update our game state
                             game_mut.board_fen = board.clone();
                             game_mut.seq = op.seq;
                             game_mut.last_activity = current_timestamp();
+                            let has_completed = game_mut.update_result();
                             self.active_screen.refresh_game(&*game_mut);
+                            if has_completed {
+                                self.on_game_completed(op.game_id)?;
+                            }
                             self.save_games()?;
                         },
                     }
                 }
             },
+            OperationType::SpectatorRequest { spectator } => {
+                if let Some(game_mut) = self.games.get_mut(op.game_id) {
+                    if game_mut.is_spectating() || op.from != game_mut.opponent {
+                        return Err(anyhow!("Spectator request for game {} from a non-player", op.game_id));
+                    }
+                    if !game_mut.spectators.contains(spectator) && !game_mut.spectator_requests.contains(spectator) {
+                        game_mut.spectator_requests.push(spectator.clone());
+                        self.save_games()?;
+                    }
+                }
+            },
+            OperationType::SpectatorResponse { spectator, accepted } => {
+                if let Some(game_mut) = self.games.get_mut(op.game_id) {
+                    if op.from != game_mut.opponent || !game_mut.pending_spectators.contains(spectator) {
+                        return Err(anyhow!("Unexpected spectator response for game {}", op.game_id));
+                    }
+                    game_mut.pending_spectators.retain(|s| s != spectator);
+                    if !*accepted {
+                        log::info!("Opponent declined spectator for game {}", op.game_id);
+                        self.save_games()?;
+                        return Ok(());
+                    }
+                    if !game_mut.spectators.contains(spectator) {
+                        game_mut.spectators.push(spectator.clone());
+                    }
+                    let (white, black) = match game_mut.player {
+                        Player::White => (self.public_key.clone(), game_mut.opponent.clone()),
+                        Player::Black => (game_mut.opponent.clone(), self.public_key.clone()),
+                    };
+                    let msg = ChessOperation {
+                        game_id: game_mut.id,
+                        seq: game_mut.seq,
+                        to: spectator.clone(),
+                        from: self.public_key.clone(),
+                        operation: OperationType::SpectateGame {
+                            white,
+                            black,
+                            board: game_mut.board_fen.clone(),
+                        },
+                    };
+                    self.send_message(msg)?;
+                    self.save_games()?;
+                }
+            },
+            OperationType::SpectateInvite => {
+                if self.games.get(op.game_id).is_some() {
+                    return Err(anyhow!("Spectate invite for existing game {}", op.game_id));
+                }
+                let invite = (op.game_id, op.from);
+                if !self.spectate_invites.contains(&invite) {
+                    self.spectate_invites.push(invite);
+                }
+            },
+            OperationType::SpectateAccept => {
+                if let Some(game_mut) = self.games.get_mut(op.game_id) {
+                    if game_mut.is_spectating() || !game_mut.pending_spectators.contains(&op.from) {
+                        return Err(anyhow!("Unexpected spectate acceptance for game {}", op.game_id));
+                    }
+                    // Only ask the opponent once the spectator wants to watch
+                    let msg = ChessOperation {
+                        game_id: game_mut.id,
+                        seq: game_mut.seq,
+                        to: game_mut.opponent.clone(),
+                        from: self.public_key.clone(),
+                        operation: OperationType::SpectatorRequest { spectator: op.from },
+                    };
+                    self.send_message(msg)?;
+                }
+            },
+            OperationType::SpectateGame { white, black, board } => {
+                if self.games.get(op.game_id).is_some() {
+                    return Err(anyhow!("Spectate invite for existing game {}", op.game_id));
+                }
+                let request = self
+                    .spectate_requests
+                    .iter()
+                    .position(|(game_id, inviter)| *game_id == op.game_id && *inviter == op.from)
+                    .ok_or_else(|| anyhow!("Spectate game {} that we did not ask to watch", op.game_id))?;
+                self.spectate_requests.remove(request);
+                let mut game = Game::new(op.game_id, op.from, Player::White, board.clone());
+                game.seq = op.seq;
+                game.watching = Some(Watching {
+                    white: white.clone(),
+                    black: black.clone(),
+                });
+                self.games.insert(game);
+                self.save_games()?;
+            },
+            OperationType::TournamentUpdate { state } => {
+                let tournament = serde_json::from_str::<Tournament>(state)?;
+                if tournament.id != op.game_id || tournament.organiser != op.from {
+                    return Err(anyhow!("Tournament update from a non-organiser"));
+                }
+                if !tournament.is_participant(&self.public_key) {
+                    return Err(anyhow!("Tournament update for a tournament we are not part of"));
+                }
+                let is_newer = self
+                    .tournaments
+                    .get(tournament.id)
+                    .map(|t| t.organiser == op.from && tournament.version > t.version)
+                    .unwrap_or(true);
+                if is_newer {
+                    let tournament_id = tournament.id;
+                    self.tournaments.insert(tournament);
+                    self.save_tournaments()?;
+                    self.add_tournament_games(tournament_id)?;
+                }
+            },
+            OperationType::TournamentResult { tournament_id, outcome } => {
+                let tournament = self
+                    .tournaments
+                    .get(*tournament_id)
+                    .ok_or_else(|| anyhow!("Result for unknown tournament {}", tournament_id))?;
+                if tournament.organiser != self.public_key {
+                    return Err(anyhow!(
+                        "Result for tournament {} that we don't organise",
+                        tournament_id
+                    ));
+                }
+                self.report_tournament_outcome(*tournament_id, op.game_id, &op.from, Outcome::try_from(*outcome)?)?;
+            },
+            OperationType::TakebackRequest { plies } => {
+                if let Some(game_mut) = self.games.get_mut(op.game_id) {
+                    if game_mut.is_spectating() || op.from != game_mut.opponent {
+                        return Err(anyhow!("Takeback request for game {} from a non-player", op.game_id));
+                    }
+                    // A move has been played since the request was made
+                    if op.seq != game_mut.seq || game_mut.has_completed() {
+                        log::info!("Ignoring stale takeback request for game {}", op.game_id);
+                        return Ok(());
+                    }
+                    game_mut.takeback_request = Some(Takeback {
+                        seq: op.seq,
+                        plies: *plies,
+                    });
+                    self.save_games()?;
+                }
+            },
+            OperationType::TakebackResponse { plies, accepted } => {
+                if let Some(game_mut) = self.games.get_mut(op.game_id) {
+                    if !game_mut.is_move_sender(&op.from) {
+                        return Err(anyhow!("Takeback response for game {} from a non-player", op.game_id));
+                    }
+                    let request = Takeback {
+                        seq: op.seq,
+                        plies: *plies,
+                    };
+                    // Spectators are only told about accepted takebacks
+                    if !game_mut.is_spectating() && game_mut.sent_takeback.take() != Some(request) {
+                        log::info!("Ignoring response to a stale takeback request for game {}", op.game_id);
+                        return Ok(());
+                    }
+                    if !*accepted || op.seq != game_mut.seq {
+                        log::info!("Takeback declined for game {}", op.game_id);
+                        self.save_games()?;
+                        return Ok(());
+                    }
+                    if !game_mut.take_back(*plies) {
+                        if !game_mut.is_spectating() {
+                            return Err(anyhow!(
+                                "Takeback for game {} does not match our move history",
+                                op.game_id
+                            ));
+                        }
+                        // Accept the next move from the players, which carries the board
+                        game_mut.seq = op.seq.saturating_sub(*plies);
+                    }
+                    let (board, seq, last_move) = (game_mut.board_fen.clone(), game_mut.seq, last_move(game_mut));
+                    self.save_games()?;
+                    if let Screen::Game(ref mut screen) = self.active_screen {
+                        if screen.game_id() == op.game_id {
+                            screen.take_back(&board, last_move, seq);
+                        }
+                    }
+                }
+            },
+            OperationType::DrawOffer => {
+                if let Some(game_mut) = self.games.get_mut(op.game_id) {
+                    if game_mut.is_spectating() || op.from != game_mut.opponent {
+                        return Err(anyhow!("Draw offer for game {} from a non-player", op.game_id));
+                    }
+                    if op.seq != game_mut.seq || game_mut.has_completed() {
+                        log::info!("Ignoring stale draw offer for game {}", op.game_id);
+                        return Ok(());
+                    }
+                    game_mut.draw_offer = Some(op.seq);
+                    let (dialog, action) = accept_draw_dialog(game_mut);
+                    self.save_games()?;
+                    let is_on_screen =
+                        matches!(self.active_screen, Screen::Game(ref screen) if screen.game_id() == op.game_id);
+                    if is_on_screen && self.dialog.is_none() && self.palette.is_none() {
+                        self.open_dialog(dialog, action);
+                    } else {
+                        self.notify_unless_on_screen(
+                            op.game_id,
+                            "Draw offered",
+                            &format!(
+                                "{} offers a draw in game {}",
+                                short_key(&op.from, NOTIFICATION_KEY_LEN),
+                                op.game_id
+                            ),
+                        );
+                    }
+                }
+            },
+            OperationType::DrawAccept => {
+                if let Some(game_mut) = self.games.get_mut(op.game_id) {
+                    if !game_mut.is_move_sender(&op.from) {
+                        return Err(anyhow!("Draw acceptance for game {} from a non-player", op.game_id));
+                    }
+                    // Spectators don't see the offer, only the players agreeing to it
+                    let is_offer_open = game_mut.is_spectating() || game_mut.sent_draw_offer == Some(op.seq);
+                    if !is_offer_open || op.seq != game_mut.seq || game_mut.has_completed() {
+                        log::info!("Ignoring acceptance of a stale draw offer for game {}", op.game_id);
+                        return Ok(());
+                    }
+                    game_mut.result = GameResult::Draw;
+                    self.on_game_completed(op.game_id)?;
+                    self.save_games()?;
+                    self.notify_unless_on_screen(
+                        op.game_id,
+                        "Game over",
+                        &format!(
+                            "{} accepted a draw in game {}",
+                            short_key(&op.from, NOTIFICATION_KEY_LEN),
+                            op.game_id
+                        ),
+                    );
+                }
+            },
+        }
+
+        Ok(())
+    }
+
+    fn create_tournament(&mut self, format: TournamentFormat, participants: Vec<CommsPublicKey>) -> anyhow::Result<()> {
+        if participants.len() < 2 {
+            return Err(anyhow!("At least 2 participants are required"));
+        }
+        let id = OsRng.next_u32();
+        self.tournaments
+            .insert(Tournament::new(id, self.public_key.clone(), format, participants));
+        self.start_next_round(id)
+    }
+
+    /// Pairs the next round and sends a `NewGame` to each paired player
+    fn start_next_round(&mut self, tournament_id: u32) -> anyhow::Result<()> {
+        let tournament = self
+            .tournaments
+            .get_mut(tournament_id)
+            .ok_or_else(|| anyhow!("Tournament {} not found", tournament_id))?;
+        let round = match tournament.next_round(|| OsRng.next_u32()) {
+            Some(round) => round.clone(),
+            None => return Ok(()),
+        };
+        // Participants also add their games from the update, which can arrive after the pairings
+        self.publish_tournament(tournament_id)?;
+
+        for pairing in round.pairings {
+            let sides = [
+                (Player::White, &pairing.white, &pairing.black),
+                (Player::Black, &pairing.black, &pairing.white),
+            ];
+            for (player, public_key, opponent) in sides {
+                if *public_key == self.public_key {
+                    if let Some(game) = tournament_game(tournament_id, &pairing, &self.public_key) {
+                        self.games.insert(game);
+                    }
+                } else {
+                    self.send_or_retry(ChessOperation {
+                        game_id: pairing.game_id,
+                        seq: 0,
+                        to: public_key.clone(),
+                        from: self.public_key.clone(),
+                        operation: OperationType::NewGame {
+                            player: player as u8,
+                            pairing: Some(TournamentPairing {
+                                tournament_id,
+                                opponent: opponent.clone(),
+                            }),
+                        },
+                    });
+                }
+            }
+        }
+        self.save_games()
+    }
+
+    /// Adds the games we play in a tournament that we don't have yet, from its latest pairings
+    fn add_tournament_games(&mut self, tournament_id: u32) -> anyhow::Result<()> {
+        let tournament = match self.tournaments.get(tournament_id) {
+            Some(tournament) => tournament,
+            None => return Ok(()),
+        };
+        let new_games = tournament
+            .rounds
+            .iter()
+            .flat_map(|r| r.pairings.iter())
+            .filter(|p| p.outcome.is_none() && self.games.get(p.game_id).is_none() && !self.archive.contains(p.game_id))
+            .filter_map(|p| tournament_game(tournament_id, p, &self.public_key))
+            .collect::<Vec<_>>();
+        if new_games.is_empty() {
+            return Ok(());
         }
+        for game in new_games {
+            let (game_id, body) = (
+                game.id,
+                format!(
+                    "Tournament game against {}",
+                    short_key(&game.opponent, NOTIFICATION_KEY_LEN)
+                ),
+            );
+            self.games.insert(game);
+            self.notify_unless_on_screen(game_id, "New game", &body);
+        }
+        self.sounds.play(SoundEffect::Challenge);
+        self.save_games()
+    }
+
+    /// Sends the latest tournament state to every participant
+    fn publish_tournament(&mut self, tournament_id: u32) -> anyhow::Result<()> {
+        let tournament = self
+            .tournaments
+            .get(tournament_id)
+            .ok_or_else(|| anyhow!("Tournament {} not found", tournament_id))?;
+        let state = serde_json::to_string(tournament)?;
+        let msgs = tournament
+            .participants
+            .iter()
+            .filter(|p| **p != self.public_key)
+            .map(|p| ChessOperation {
+                game_id: tournament.id,
+                seq: tournament.version,
+                to: p.clone(),
+                from: self.public_key.clone(),
+                operation: OperationType::TournamentUpdate { state: state.clone() },
+            })
+            .collect::<Vec<_>>();
+        for msg in msgs {
+            self.send_or_retry(msg);
+        }
+        self.save_tournaments()
+    }
 
+    /// Records a result reported by one of the players, once both players have reported the same result
+    fn report_tournament_outcome(
+        &mut self,
+        tournament_id: u32,
+        game_id: u32,
+        reporter: &CommsPublicKey,
+        outcome: Outcome,
+    ) -> anyhow::Result<()> {
+        let tournament = self
+            .tournaments
+            .get_mut(tournament_id)
+            .ok_or_else(|| anyhow!("Tournament {} not found", tournament_id))?;
+        if !tournament.report_outcome(game_id, reporter, outcome)? {
+            return self.save_tournaments();
+        }
+        self.continue_tournament(tournament_id)
+    }
+
+    /// Records the organiser's decision for a game the players reported different outcomes for
+    fn settle_tournament_game(&mut self, tournament_id: u32, game_id: u32, outcome: Outcome) -> anyhow::Result<()> {
+        let tournament = self
+            .tournaments
+            .get_mut(tournament_id)
+            .ok_or_else(|| anyhow!("Tournament {} not found", tournament_id))?;
+        if tournament.organiser != self.public_key {
+            return Err(anyhow!("Only the organiser can settle a game"));
+        }
+        if !tournament.record_outcome(game_id, outcome) {
+            return Ok(());
+        }
+        self.continue_tournament(tournament_id)
+    }
+
+    /// Starts the next round once every game of the current round has an outcome, or publishes the new outcome
+    fn continue_tournament(&mut self, tournament_id: u32) -> anyhow::Result<()> {
+        let tournament = self
+            .tournaments
+            .get(tournament_id)
+            .ok_or_else(|| anyhow!("Tournament {} not found", tournament_id))?;
+        if tournament.is_round_complete() && !tournament.is_finished() {
+            self.start_next_round(tournament_id)
+        } else {
+            self.publish_tournament(tournament_id)
+        }
+    }
+
+    /// Tells the player about an event in a game that isn't on screen, with a toast while the window has focus and
+    /// a desktop notification otherwise, if they are turned on
+    fn notify_unless_on_screen(&mut self, game_id: u32, summary: &str, body: &str) {
+        let is_game_open = matches!(self.active_screen, Screen::Game(ref screen) if screen.game_id() == game_id);
+        if notifier::notify_unless_on_screen(
+            self.notifier.as_ref(),
+            self.is_window_active,
+            self.settings.show_notifications,
+            is_game_open,
+            summary,
+            body,
+        ) {
+            self.toasts.push(body, ToastKind::Info);
+        }
+    }
+
+    /// Archives a completed game and reports the result to the organiser if it is a tournament game. Must be called
+    /// before the game is cleaned up.
+    fn on_game_completed(&mut self, game_id: u32) -> anyhow::Result<()> {
+        let game = match self.games.get(game_id) {
+            Some(game) => game,
+            None => return Ok(()),
+        };
+        self.sounds.play(SoundEffect::GameEnd);
+        if self.archive.add(game) {
+            self.save_archive()?;
+            if let Screen::Stats(ref mut screen) = self.active_screen {
+                screen.set_ratings(&Ratings::compute(self.archive.iter()));
+            }
+        }
+        let (tournament_id, outcome) = match (game.tournament_id, Outcome::from_game(game)) {
+            (Some(tournament_id), Some(outcome)) => (tournament_id, outcome),
+            _ => return Ok(()),
+        };
+        let organiser = self
+            .tournaments
+            .get(tournament_id)
+            .map(|t| t.organiser.clone())
+            .ok_or_else(|| anyhow!("Tournament {} not found", tournament_id))?;
+        if organiser == self.public_key {
+            let public_key = self.public_key.clone();
+            return self.report_tournament_outcome(tournament_id, game_id, &public_key, outcome);
+        }
+        self.send_or_retry(ChessOperation {
+            game_id,
+            seq: 0,
+            to: organiser,
+            from: self.public_key.clone(),
+            operation: OperationType::TournamentResult {
+                tournament_id,
+                outcome: outcome as u8,
+            },
+        });
+        Ok(())
+    }
+
+    fn send_message(&mut self, msg: ChessOperation) -> anyhow::Result<()> {
+        self.channel.try_send(msg)?;
         Ok(())
     }
 
-    fn send_message(&mut self, msg: ChessOperation) -> Result<(), TrySendError<ChessOperation>> {
-        self.channel.try_send(msg)
+    /// Sends a message that a tournament can't continue without. If the channel is full it is kept and sent again
+    /// on a later frame.
+    fn send_or_retry(&mut self, msg: ChessOperation) {
+        // Queue behind earlier messages so they are sent in order
+        if !self.unsent.is_empty() {
+            self.unsent.push_back(msg);
+            return;
+        }
+        match self.channel.try_send(msg) {
+            Ok(()) => {},
+            Err(TrySendError::Full(msg)) => self.unsent.push_back(msg),
+            Err(err) => log::error!("Failed to send message: {}", err),
+        }
+    }
+
+    /// Sends the messages kept by `send_or_retry` until the channel is full again
+    fn send_unsent(&mut self) {
+        while let Some(msg) = self.unsent.pop_front() {
+            match self.channel.try_send(msg) {
+                Ok(()) => {},
+                Err(TrySendError::Full(msg)) => {
+                    self.unsent.push_front(msg);
+                    return;
+                },
+                Err(err) => log::error!("Failed to send message: {}", err),
+            }
+        }
     }
 
     fn save_games(&mut self) -> anyhow::Result<()> {
@@ -275,18 +1498,33 @@ impl ScreenManager {
         fs::write(&self.config.save_path, json)?;
         Ok(())
     }
+
+    fn save_archive(&self) -> anyhow::Result<()> {
+        let json = serde_json::to_string(&self.archive)?;
+        fs::write(&self.config.archive_path, json)?;
+        Ok(())
+    }
+
+    fn save_tournaments(&self) -> anyhow::Result<()> {
+        let json = serde_json::to_string(&self.tournaments)?;
+        fs::write(&self.config.tournaments_path, json)?;
+        Ok(())
+    }
 }
 
 #[derive(Debug)]
 enum Screen {
-    Start(StartScreen),
-    Game(GameScreen),
+    Start(Box<StartScreen>),
+    Game(Box<GameScreen>),
+    Tournaments(Box<TournamentScreen>),
+    Stats(Box<StatsScreen>),
+    Settings(Box<SettingsScreen>),
 }
 
 impl Screen {
     pub fn refresh_game(&mut self, game: &Game) {
         match self {
-            Screen::Start(_) => {},
+            Screen::Start(_) | Screen::Tournaments(_) | Screen::Stats(_) | Screen::Settings(_) => {},
             Screen::Game(g) => {
                 if g.game_id() == game.id {
                     g.set_seq(game.seq);
@@ -297,15 +1535,175 @@ impl Screen {
     }
 }
 
-fn load_games<P: AsRef<Path>>(path: P) -> anyhow::Result<GameCollection> {
-    let mut games = GameCollection::default();
-    if path.as_ref().exists() {
-        let mut read = fs::File::open(path)?;
-        games = serde_json::from_reader(&mut read)?;
+/// Sends our move to the opponent and any spectators
+fn send_move(channel: &MessageChannel<ChessOperation>, public_key: &CommsPublicKey, game: &Game, mv: BitMove) {
+    let msg = ChessOperation {
+        game_id: game.id,
+        seq: game.seq,
+        to: game.opponent.clone(),
+        from: public_key.clone(),
+        operation: OperationType::MovePlayed {
+            mv: mv.get_raw(),
+            board: game.board_fen.clone(),
+        },
+    };
+    if let Err(err) = channel.try_send(msg.clone()) {
+        log::error!("Failed to send move: {}", err);
+        return;
+    }
+    for to in game.spectators.iter().cloned() {
+        if let Err(err) = channel.try_send(ChessOperation { to, ..msg.clone() }) {
+            log::error!("Failed to send move to spectator: {}", err);
+        }
+    }
+}
+
+/// Tells the opponent and any spectators that we resigned or accepted a draw
+fn send_game_end(
+    channel: &MessageChannel<ChessOperation>,
+    public_key: &CommsPublicKey,
+    game: &Game,
+    operation: OperationType,
+) {
+    let msg = ChessOperation {
+        game_id: game.id,
+        seq: game.seq,
+        to: game.opponent.clone(),
+        from: public_key.clone(),
+        operation,
+    };
+    if let Err(err) = channel.try_send(msg.clone()) {
+        log::error!("Failed to send the end of game {}: {}", game.id, err);
+    }
+    for to in game.spectators.iter().cloned() {
+        if let Err(err) = channel.try_send(ChessOperation { to, ..msg.clone() }) {
+            log::error!("Failed to send the end of game {} to spectator: {}", game.id, err);
+        }
+    }
+}
+
+/// Answers the opponent's takeback request. Spectators are told if it was accepted so they can roll back too.
+fn send_takeback_response(
+    channel: &MessageChannel<ChessOperation>,
+    public_key: &CommsPublicKey,
+    game: &Game,
+    request: Takeback,
+    accepted: bool,
+) {
+    let msg = ChessOperation {
+        game_id: game.id,
+        seq: request.seq,
+        to: game.opponent.clone(),
+        from: public_key.clone(),
+        operation: OperationType::TakebackResponse {
+            plies: request.plies,
+            accepted,
+        },
+    };
+    if let Err(err) = channel.try_send(msg.clone()) {
+        log::error!("Failed to send takeback response: {}", err);
+    }
+    if !accepted {
+        return;
+    }
+    for to in game.spectators.iter().cloned() {
+        if let Err(err) = channel.try_send(ChessOperation { to, ..msg.clone() }) {
+            log::error!("Failed to send takeback to spectator: {}", err);
+        }
     }
-    Ok(games)
 }
 
-fn current_timestamp() -> u64 {
-    SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs()
+/// Creates our game for a tournament pairing, if we play in it
+fn tournament_game(tournament_id: u32, pairing: &Pairing, public_key: &CommsPublicKey) -> Option<Game> {
+    let (player, opponent) = pairing.side_of(public_key)?;
+    let mut game = Game::new(
+        pairing.game_id,
+        opponent.clone(),
+        player,
+        board::INITIAL_BOARD.to_string(),
+    );
+    game.tournament_id = Some(tournament_id);
+    Some(game)
+}
+
+/// Returns the theme with the given name, or the built in theme if it no longer exists
+fn find_theme(themes: &[Theme], name: &str) -> Theme {
+    themes.iter().find(|t| t.name == name).cloned().unwrap_or_default()
+}
+
+/// Asks whether to accept the draw the opponent offered
+fn accept_draw_dialog(game: &Game) -> (Dialog, DialogAction) {
+    let message = format!(
+        "{} offers a draw in game {}. Accept?",
+        short_key(&game.opponent, NOTIFICATION_KEY_LEN),
+        game.id
+    );
+    let mut dialog = Dialog::new("Draw offered", &message, &["Accept", "Decline"]);
+    dialog.set_default_button(1);
+    (dialog, DialogAction::AcceptDraw(game.id))
+}
+
+/// The result of a game that ended by agreement or resignation, which the board can't show
+fn off_board_status(game: &Game) -> Option<GameStatus> {
+    match game.result {
+        GameResult::WeResigned => Some(GameStatus::Resign(game.player)),
+        GameResult::TheyResigned => Some(GameStatus::Resign(game.player.other_player())),
+        GameResult::Draw if matches!(board::game_result(&game.board_fen), GameResult::None) => {
+            Some(GameStatus::DrawAgreed)
+        },
+        _ => None,
+    }
+}
+
+/// A short status for a game in the command palette
+fn game_detail(game: &Game) -> &'static str {
+    if game.is_spectating() {
+        "Watching"
+    } else if game.has_completed() {
+        "Finished"
+    } else if board::move_number(&game.board_fen).1 == game.player {
+        "Your move"
+    } else {
+        "Their move"
+    }
+}
+
+fn last_move(game: &Game) -> Option<BitMove> {
+    game.moves.last().map(|mv| BitMove::new(*mv))
+}
+
+fn invite_spectator(
+    games: &mut GameCollection,
+    channel: &MessageChannel<ChessOperation>,
+    public_key: &CommsPublicKey,
+    game_id: u32,
+    spectator: CommsPublicKey,
+) -> anyhow::Result<()> {
+    let game_mut = games
+        .get_mut(game_id)
+        .ok_or_else(|| anyhow!("Game {} not found", game_id))?;
+    if game_mut.is_spectating() {
+        return Err(anyhow!("Only players can invite spectators"));
+    }
+    if game_mut.spectators.contains(&spectator) || game_mut.pending_spectators.contains(&spectator) {
+        return Ok(());
+    }
+    game_mut.pending_spectators.push(spectator.clone());
+    channel.try_send(ChessOperation {
+        game_id: game_mut.id,
+        seq: game_mut.seq,
+        to: spectator,
+        from: public_key.clone(),
+        operation: OperationType::SpectateInvite,
+    })?;
+    Ok(())
+}
+
+fn load_or_default<T: DeserializeOwned + Default, P: AsRef<Path>>(path: P) -> anyhow::Result<T> {
+    let mut value = T::default();
+    if path.as_ref().exists() {
+        let mut read = fs::File::open(path)?;
+        value = serde_json::from_reader(&mut read)?;
+    }
+    Ok(value)
 }
@@ -0,0 +1,199 @@
+use std::{collections::BTreeMap, fs, path::Path, time::Duration};
+
+use serde::{Deserialize, Serialize};
+
+use crate::theme::DEFAULT_THEME;
+
+/// The settings file name, in the base directory. Settings are shared by all profiles.
+pub const SETTINGS_FILE: &str = "p2pc-settings.json";
+pub const MIN_SYNC_INTERVAL_SECS: u64 = 10;
+pub const MAX_SYNC_INTERVAL_SECS: u64 = 600;
+
+/// User preferences, saved as JSON. Missing fields take their default value so older files still load.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    /// Show the legal moves of a picked up piece. Can be turned off for serious games.
+    pub show_move_hints: bool,
+    /// Name of the board theme
+    pub theme: String,
+    /// Show moves on the board with Confirm and Undo buttons before sending them
+    pub confirm_moves: bool,
+    pub is_sound_muted: bool,
+    /// Sound effect volume as a percentage
+    pub sound_volume: u8,
+    /// Show desktop notifications for game events while the window is in the background
+    pub show_notifications: bool,
+    /// Seconds between sending the open game's board to the opponent to recover from lost messages
+    pub sync_interval_secs: u64,
+    /// Only read on startup
+    pub network: NetworkSettings,
+    /// Keys for commands by command id, e.g. `"resign": "Ctrl+R"`, replacing the defaults. An empty string unbinds the
+    /// command.
+    pub key_bindings: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NetworkSettings {
+    /// Control port of an already running Tor. Tor is started in process if not set.
+    pub tor_control_port: Option<u16>,
+    /// Peers used to join the network, as "<public key hex>::<address>". The built in seed peers are used if empty.
+    pub seed_peers: Vec<String>,
+}
+
+/// Settings given on the command line. They take precedence over the settings file for one run and are never saved.
+#[derive(Debug, Clone, Default)]
+pub struct SettingsOverrides {
+    pub theme: Option<String>,
+    pub is_sound_muted: Option<bool>,
+    pub show_notifications: Option<bool>,
+    pub sync_interval_secs: Option<u64>,
+    pub tor_control_port: Option<u16>,
+    pub seed_peers: Vec<String>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            show_move_hints: true,
+            theme: DEFAULT_THEME.to_string(),
+            confirm_moves: false,
+            is_sound_muted: false,
+            sound_volume: 70,
+            show_notifications: true,
+            sync_interval_secs: 30,
+            network: NetworkSettings::default(),
+            key_bindings: BTreeMap::new(),
+        }
+    }
+}
+
+impl Settings {
+    pub fn load<P: AsRef<Path>>(path: P) -> anyhow::Result<Self> {
+        if !path.as_ref().exists() {
+            return Ok(Self::default());
+        }
+        let mut read = fs::File::open(path)?;
+        Ok(serde_json::from_reader(&mut read)?)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> anyhow::Result<()> {
+        let json = serde_json::to_string_pretty(self)?;
+        fs::write(path, json)?;
+        Ok(())
+    }
+
+    pub fn sync_interval(&self) -> Duration {
+        Duration::from_secs(
+            self.sync_interval_secs
+                .clamp(MIN_SYNC_INTERVAL_SECS, MAX_SYNC_INTERVAL_SECS),
+        )
+    }
+}
+
+impl NetworkSettings {
+    /// Parses seed peers separated by commas, as typed on the settings screen. Only the form is checked, the
+    /// address is parsed when networking starts. Returns None if any peer is malformed.
+    pub fn parse_seed_peers(value: &str) -> Option<Vec<String>> {
+        value
+            .split(',')
+            .map(str::trim)
+            .filter(|peer| !peer.is_empty())
+            .map(|peer| {
+                let (public_key, address) = peer.split_once("::")?;
+                let is_valid = public_key.len() == 64 &&
+                    public_key.chars().all(|ch| ch.is_ascii_hexdigit()) &&
+                    !address.is_empty();
+                is_valid.then(|| peer.to_string())
+            })
+            .collect()
+    }
+}
+
+impl SettingsOverrides {
+    /// Returns `settings` with every override that was given replacing the file value
+    pub fn apply(&self, settings: &Settings) -> Settings {
+        let mut settings = settings.clone();
+        if let Some(ref theme) = self.theme {
+            settings.theme = theme.clone();
+        }
+        if let Some(is_sound_muted) = self.is_sound_muted {
+            settings.is_sound_muted = is_sound_muted;
+        }
+        if let Some(show_notifications) = self.show_notifications {
+            settings.show_notifications = show_notifications;
+        }
+        if let Some(sync_interval_secs) = self.sync_interval_secs {
+            settings.sync_interval_secs = sync_interval_secs;
+        }
+        if let Some(tor_control_port) = self.tor_control_port {
+            settings.network.tor_control_port = Some(tor_control_port);
+        }
+        if !self.seed_peers.is_empty() {
+            settings.network.seed_peers = self.seed_peers.clone();
+        }
+        settings
+    }
+
+    /// Names of the settings that are overridden, for showing on the settings screen
+    pub fn overridden(&self) -> Vec<&'static str> {
+        [
+            ("theme", self.theme.is_some()),
+            ("sound", self.is_sound_muted.is_some()),
+            ("notifications", self.show_notifications.is_some()),
+            ("sync interval", self.sync_interval_secs.is_some()),
+            ("Tor control port", self.tor_control_port.is_some()),
+            ("seed peers", !self.seed_peers.is_empty()),
+        ]
+        .into_iter()
+        .filter(|(_, is_overridden)| *is_overridden)
+        .map(|(name, _)| name)
+        .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn loads_older_files_with_defaults() {
+        let settings = serde_json::from_str::<Settings>(r#"{"theme": "Green", "sound_volume": 30}"#).unwrap();
+        assert_eq!(settings.theme, "Green");
+        assert_eq!(settings.sound_volume, 30);
+        assert_eq!(settings.sync_interval_secs, 30);
+        assert!(settings.show_notifications);
+        assert_eq!(settings.network, NetworkSettings::default());
+    }
+
+    #[test]
+    fn overrides_replace_file_values() {
+        let overrides = SettingsOverrides {
+            is_sound_muted: Some(true),
+            sync_interval_secs: Some(1),
+            ..Default::default()
+        };
+        let settings = overrides.apply(&Settings::default());
+        assert!(settings.is_sound_muted);
+        assert_eq!(settings.theme, DEFAULT_THEME);
+        assert_eq!(settings.sync_interval(), Duration::from_secs(MIN_SYNC_INTERVAL_SECS));
+        assert_eq!(overrides.overridden(), vec!["sound", "sync interval"]);
+    }
+
+    #[test]
+    fn parses_seed_peers() {
+        let key = "a".repeat(64);
+        let peers = format!("{}::/onion3/abc:18141, ,{}::/ip4/1.2.3.4/tcp/18189", key, key);
+        assert_eq!(
+            NetworkSettings::parse_seed_peers(&peers),
+            Some(vec![
+                format!("{}::/onion3/abc:18141", key),
+                format!("{}::/ip4/1.2.3.4/tcp/18189", key)
+            ])
+        );
+        assert_eq!(NetworkSettings::parse_seed_peers(""), Some(vec![]));
+        assert_eq!(NetworkSettings::parse_seed_peers("abc::/onion3/abc:18141"), None);
+        assert_eq!(NetworkSettings::parse_seed_peers(&format!("{}::", key)), None);
+    }
+}
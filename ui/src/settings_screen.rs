@@ -0,0 +1,439 @@
+use minifb::{Key, KeyRepeat, Window};
+
+use crate::{
+    clipboard::Clipboard,
+    color::Color,
+    components::{Button, Label, TextBox},
+    drawable::{Drawable, FrameBuffer},
+    font::{DEFAULT_SIZE, FONT},
+    layout::{Anchor, Padding, Stack},
+    rect::{Frame, Rect},
+    settings::{NetworkSettings, Settings, SettingsOverrides, MAX_SYNC_INTERVAL_SECS, MIN_SYNC_INTERVAL_SECS},
+    start_screen::Drawables,
+};
+
+const VOLUME_STEP: u8 = 10;
+const SYNC_INTERVAL_STEP: u64 = 10;
+/// Width of the setting names, with the controls to their right
+const NAME_WIDTH: u32 = 300;
+const ROW_HEIGHT: u32 = 30;
+
+#[derive(Debug)]
+pub struct SettingsScreen {
+    settings: Settings,
+    back_button: Button,
+    move_hints_button: Button,
+    confirm_moves_button: Button,
+    theme_names: Vec<String>,
+    theme_button: Button,
+    sound_button: Button,
+    volume_down_button: Button,
+    volume_up_button: Button,
+    volume_label: Label,
+    notifications_button: Button,
+    sync_interval_down_button: Button,
+    sync_interval_up_button: Button,
+    sync_interval_label: Label,
+    tor_control_port_input: TextBox,
+    tor_control_port_error_label: Label,
+    seed_peers_input: TextBox,
+    seed_peers_error_label: Label,
+    /// Whether the seed peers text box was active on the last update
+    was_seed_peers_active: bool,
+    /// Whether the port text box was active on the last update, to commit the port when it loses focus
+    was_tor_control_port_active: bool,
+    labels: Drawables<Label>,
+    is_changed: bool,
+}
+
+impl SettingsScreen {
+    /// Edits `settings` as saved in the settings file. The overrides are only shown.
+    pub fn new(
+        settings: Settings,
+        theme_names: Vec<String>,
+        overrides: &SettingsOverrides,
+        clipboard: Clipboard,
+        window_width: u32,
+        window_height: u32,
+    ) -> Self {
+        let mut title_label = Label::new(Frame::default());
+        title_label.set_text("Settings").set_text_color(Color::dark_blue());
+
+        let mut move_hints_label = Label::new(Frame::default());
+        move_hints_label.set_text("Show legal moves");
+
+        let mut confirm_moves_label = Label::new(Frame::default());
+        confirm_moves_label.set_text("Confirm moves");
+
+        let mut sound_label = Label::new(Frame::default());
+        sound_label.set_text("Sound");
+
+        let mut volume_title_label = Label::new(Frame::default());
+        volume_title_label.set_text("Volume");
+
+        let mut theme_label = Label::new(Frame::default());
+        theme_label.set_text("Theme");
+
+        let mut notifications_label = Label::new(Frame::default());
+        notifications_label.set_text("Desktop notifications");
+
+        let mut sync_interval_title_label = Label::new(Frame::default());
+        sync_interval_title_label.set_text("Sync interval");
+
+        let mut tor_control_port_label = Label::new(Frame::default());
+        tor_control_port_label.set_text("Tor control port");
+
+        let mut seed_peers_label = Label::new(Frame::default());
+        seed_peers_label.set_text("Seed peers");
+
+        let mut restart_label = Label::new(Frame::default());
+        restart_label
+            .set_text("Network settings apply after a restart")
+            .set_text_color(Color::light_grey());
+
+        let mut overrides_label = Label::new(Frame::default());
+        let overridden = overrides.overridden();
+        if !overridden.is_empty() {
+            overrides_label
+                .set_text(format!(
+                    "Set on the command line for this run: {}",
+                    overridden.join(", ")
+                ))
+                .set_text_color(Color::light_grey());
+        }
+
+        let mut back_button = Button::new(Rect::from_frame(Frame::default(), Color::white()));
+        back_button.set_text("Back");
+
+        let move_hints_button = Button::new(Rect::from_frame(Frame::default(), Color::white()));
+        let confirm_moves_button = Button::new(Rect::from_frame(Frame::default(), Color::white()));
+        let theme_button = Button::new(Rect::from_frame(Frame::default(), Color::white()));
+        let sound_button = Button::new(Rect::from_frame(Frame::default(), Color::white()));
+        let mut volume_down_button = Button::new(Rect::from_frame(Frame::default(), Color::white()));
+        volume_down_button.set_text("-");
+        let mut volume_up_button = Button::new(Rect::from_frame(Frame::default(), Color::white()));
+        volume_up_button.set_text("+");
+        let notifications_button = Button::new(Rect::from_frame(Frame::default(), Color::white()));
+        let mut sync_interval_down_button = Button::new(Rect::from_frame(Frame::default(), Color::white()));
+        sync_interval_down_button.set_text("-");
+        let mut sync_interval_up_button = Button::new(Rect::from_frame(Frame::default(), Color::white()));
+        sync_interval_up_button.set_text("+");
+
+        let mut tor_control_port_input = TextBox::new(Frame::default(), clipboard.clone());
+        tor_control_port_input
+            .set_placeholder("Start Tor")
+            .set_max_length(Some(5))
+            .set_value(
+                settings
+                    .network
+                    .tor_control_port
+                    .map(|port| port.to_string())
+                    .unwrap_or_default(),
+            );
+        let mut tor_control_port_error_label = Label::new(Frame::default());
+        tor_control_port_error_label.set_text_color(Color::red());
+
+        let mut seed_peers_input = TextBox::new(Frame::default(), clipboard);
+        seed_peers_input
+            .set_placeholder("Built in peers, or <public key hex>::<address>, ...")
+            .set_value(settings.network.seed_peers.join(", "));
+        let mut seed_peers_error_label = Label::new(Frame::default());
+        seed_peers_error_label.set_text_color(Color::red());
+
+        let mut screen = Self {
+            settings,
+            back_button,
+            move_hints_button,
+            confirm_moves_button,
+            theme_names,
+            theme_button,
+            sound_button,
+            volume_down_button,
+            volume_up_button,
+            volume_label: Label::new(Frame::default()),
+            notifications_button,
+            sync_interval_down_button,
+            sync_interval_up_button,
+            sync_interval_label: Label::new(Frame::default()),
+            tor_control_port_input,
+            tor_control_port_error_label,
+            seed_peers_input,
+            seed_peers_error_label,
+            was_seed_peers_active: false,
+            was_tor_control_port_active: false,
+            labels: Drawables::new(vec![
+                title_label,
+                move_hints_label,
+                confirm_moves_label,
+                sound_label,
+                volume_title_label,
+                theme_label,
+                notifications_label,
+                sync_interval_title_label,
+                tor_control_port_label,
+                seed_peers_label,
+                restart_label,
+                overrides_label,
+            ]),
+            is_changed: false,
+        };
+        screen.refresh();
+        screen.resize(window_width, window_height);
+        screen
+    }
+
+    /// Lays the settings out one per row below the title, with the name on the left and its controls after it
+    pub fn resize(&mut self, window_width: u32, window_height: u32) -> &mut Self {
+        let mut column = Stack::column(Padding::all(10).apply(Frame::new(0, 0, window_width, window_height)));
+        column.set_spacing(10);
+        let header = column.next(40);
+        let title_width = FONT.measure(self.labels.items[0].text(), DEFAULT_SIZE) + 4;
+        self.labels.items[0].set_frame(Anchor::Top.place(header, title_width, 40));
+        self.back_button.set_frame(Anchor::TopRight.place(header, 100, 30));
+        column.skip(20);
+
+        let mut row = setting_row(&mut column, &mut self.labels.items[1]);
+        self.move_hints_button.set_frame(row.next(100));
+        let mut row = setting_row(&mut column, &mut self.labels.items[2]);
+        self.confirm_moves_button.set_frame(row.next(100));
+        let mut row = setting_row(&mut column, &mut self.labels.items[3]);
+        self.sound_button.set_frame(row.next(100));
+        let mut row = setting_row(&mut column, &mut self.labels.items[4]);
+        self.volume_down_button.set_frame(row.next(30));
+        self.volume_label.set_frame(row.next(60));
+        self.volume_up_button.set_frame(row.next(30));
+        let mut row = setting_row(&mut column, &mut self.labels.items[5]);
+        self.theme_button.set_frame(row.next(300));
+        let mut row = setting_row(&mut column, &mut self.labels.items[6]);
+        self.notifications_button.set_frame(row.next(100));
+        let mut row = setting_row(&mut column, &mut self.labels.items[7]);
+        self.sync_interval_down_button.set_frame(row.next(30));
+        self.sync_interval_label.set_frame(row.next(60));
+        self.sync_interval_up_button.set_frame(row.next(30));
+        let mut row = setting_row(&mut column, &mut self.labels.items[8]);
+        self.tor_control_port_input.set_frame(row.next(150));
+        self.tor_control_port_error_label.set_frame(row.rest());
+        let mut row = setting_row(&mut column, &mut self.labels.items[9]);
+        self.seed_peers_input.set_frame(row.rest());
+        let mut row = Stack::row(column.next(ROW_HEIGHT));
+        row.skip(NAME_WIDTH + 10);
+        self.seed_peers_error_label.set_frame(row.rest());
+
+        self.labels.items[10].set_frame(column.next(ROW_HEIGHT));
+        self.labels.items[11].set_frame(column.next(ROW_HEIGHT));
+        self
+    }
+
+    pub fn update(&mut self, window: &Window) {
+        self.back_button.update(window);
+        self.move_hints_button.update(window);
+        self.confirm_moves_button.update(window);
+        self.sound_button.update(window);
+        self.theme_button.update(window);
+        self.volume_down_button.update(window);
+        self.volume_up_button.update(window);
+        self.notifications_button.update(window);
+        self.sync_interval_down_button.update(window);
+        self.sync_interval_up_button.update(window);
+        self.tor_control_port_input.update(window);
+        self.seed_peers_input.update(window);
+
+        if self.move_hints_button.was_clicked() {
+            self.settings.show_move_hints = !self.settings.show_move_hints;
+            self.is_changed = true;
+            self.refresh();
+        }
+        if self.confirm_moves_button.was_clicked() {
+            self.settings.confirm_moves = !self.settings.confirm_moves;
+            self.is_changed = true;
+            self.refresh();
+        }
+        if self.theme_button.was_clicked() {
+            // Cycle through the available themes
+            let current = self.theme_names.iter().position(|name| *name == self.settings.theme);
+            let next = current.map(|i| (i + 1) % self.theme_names.len()).unwrap_or(0);
+            if let Some(name) = self.theme_names.get(next) {
+                self.settings.theme = name.clone();
+                self.is_changed = true;
+                self.refresh();
+            }
+        }
+        if self.sound_button.was_clicked() {
+            self.settings.is_sound_muted = !self.settings.is_sound_muted;
+            self.is_changed = true;
+            self.refresh();
+        }
+        if self.volume_down_button.was_clicked() {
+            self.settings.sound_volume = self.settings.sound_volume.saturating_sub(VOLUME_STEP);
+            self.is_changed = true;
+            self.refresh();
+        }
+        if self.volume_up_button.was_clicked() {
+            self.settings.sound_volume = (self.settings.sound_volume + VOLUME_STEP).min(100);
+            self.is_changed = true;
+            self.refresh();
+        }
+        if self.notifications_button.was_clicked() {
+            self.settings.show_notifications = !self.settings.show_notifications;
+            self.is_changed = true;
+            self.refresh();
+        }
+        if self.sync_interval_down_button.was_clicked() {
+            self.settings.sync_interval_secs = self
+                .settings
+                .sync_interval_secs
+                .saturating_sub(SYNC_INTERVAL_STEP)
+                .max(MIN_SYNC_INTERVAL_SECS);
+            self.is_changed = true;
+            self.refresh();
+        }
+        if self.sync_interval_up_button.was_clicked() {
+            self.settings.sync_interval_secs =
+                (self.settings.sync_interval_secs + SYNC_INTERVAL_STEP).min(MAX_SYNC_INTERVAL_SECS);
+            self.is_changed = true;
+            self.refresh();
+        }
+        let is_port_active = self.tor_control_port_input.is_active();
+        let is_port_entered = is_port_active && window.is_key_pressed(Key::Enter, KeyRepeat::No);
+        let is_port_blurred = self.was_tor_control_port_active && !is_port_active;
+        self.was_tor_control_port_active = is_port_active;
+        if is_port_entered || is_port_blurred {
+            self.commit_tor_control_port();
+        }
+        let is_peers_active = self.seed_peers_input.is_active();
+        let is_peers_entered = is_peers_active && window.is_key_pressed(Key::Enter, KeyRepeat::No);
+        let is_peers_blurred = self.was_seed_peers_active && !is_peers_active;
+        self.was_seed_peers_active = is_peers_active;
+        if is_peers_entered || is_peers_blurred {
+            self.commit_seed_peers();
+        }
+    }
+
+    /// Saves the typed port on Enter, when the text box loses focus or on Back, so partly typed ports are never
+    /// saved. An empty port starts Tor in process.
+    fn commit_tor_control_port(&mut self) {
+        let value = self.tor_control_port_input.value().trim();
+        let port = if value.is_empty() {
+            Some(None)
+        } else {
+            value.parse::<u16>().ok().filter(|port| *port > 0).map(Some)
+        };
+        match port {
+            Some(port) => {
+                self.tor_control_port_error_label.set_text("");
+                if port != self.settings.network.tor_control_port {
+                    self.settings.network.tor_control_port = port;
+                    self.is_changed = true;
+                }
+            },
+            None => {
+                self.tor_control_port_error_label.set_text("Not a valid port");
+            },
+        }
+    }
+
+    /// Saves the typed seed peers like the port. No peers uses the built in seed peers.
+    fn commit_seed_peers(&mut self) {
+        match NetworkSettings::parse_seed_peers(self.seed_peers_input.value()) {
+            Some(peers) => {
+                self.seed_peers_error_label.set_text("");
+                if peers != self.settings.network.seed_peers {
+                    self.settings.network.seed_peers = peers;
+                    self.is_changed = true;
+                }
+            },
+            None => {
+                self.seed_peers_error_label
+                    .set_text("Peers must be <public key hex>::<address>, separated by commas");
+            },
+        }
+    }
+
+    pub fn was_back_clicked(&mut self) -> bool {
+        let is_clicked = self.back_button.was_clicked();
+        if is_clicked {
+            self.commit_tor_control_port();
+            self.commit_seed_peers();
+        }
+        is_clicked
+    }
+
+    /// Returns the settings if they were changed since the last call
+    pub fn take_changed_settings(&mut self) -> Option<Settings> {
+        if std::mem::take(&mut self.is_changed) {
+            Some(self.settings.clone())
+        } else {
+            None
+        }
+    }
+
+    fn refresh(&mut self) {
+        self.move_hints_button.set_text(on_off(self.settings.show_move_hints));
+        self.confirm_moves_button.set_text(on_off(self.settings.confirm_moves));
+        self.theme_button.set_text(self.settings.theme.as_str());
+        self.sound_button.set_text(on_off(!self.settings.is_sound_muted));
+        self.volume_label.set_text(self.settings.sound_volume.to_string());
+        self.notifications_button
+            .set_text(on_off(self.settings.show_notifications));
+        self.sync_interval_label
+            .set_text(format!("{}s", self.settings.sync_interval_secs));
+    }
+}
+
+impl Drawable for SettingsScreen {
+    fn draw(&mut self, buf: &mut FrameBuffer) {
+        buf.invalidate_widgets(&mut [
+            &mut self.labels,
+            &mut self.back_button,
+            &mut self.move_hints_button,
+            &mut self.confirm_moves_button,
+            &mut self.sound_button,
+            &mut self.theme_button,
+            &mut self.volume_down_button,
+            &mut self.volume_up_button,
+            &mut self.volume_label,
+            &mut self.notifications_button,
+            &mut self.sync_interval_down_button,
+            &mut self.sync_interval_up_button,
+            &mut self.sync_interval_label,
+            &mut self.tor_control_port_input,
+            &mut self.tor_control_port_error_label,
+            &mut self.seed_peers_input,
+            &mut self.seed_peers_error_label,
+        ]);
+        buf.clear(Color::black());
+        self.labels.draw(buf);
+        self.back_button.draw(buf);
+        self.move_hints_button.draw(buf);
+        self.confirm_moves_button.draw(buf);
+        self.sound_button.draw(buf);
+        self.theme_button.draw(buf);
+        self.volume_down_button.draw(buf);
+        self.volume_up_button.draw(buf);
+        self.volume_label.draw(buf);
+        self.notifications_button.draw(buf);
+        self.sync_interval_down_button.draw(buf);
+        self.sync_interval_up_button.draw(buf);
+        self.sync_interval_label.draw(buf);
+        self.tor_control_port_input.draw(buf);
+        self.tor_control_port_error_label.draw(buf);
+        self.seed_peers_input.draw(buf);
+        self.seed_peers_error_label.draw(buf);
+    }
+}
+
+/// Places `name_label` at the start of the next row and returns the rest of the row for the setting's controls
+fn setting_row(column: &mut Stack, name_label: &mut Label) -> Stack {
+    let mut row = Stack::row(column.next(ROW_HEIGHT));
+    row.set_spacing(10);
+    name_label.set_frame(row.next(NAME_WIDTH));
+    row
+}
+
+fn on_off(value: bool) -> &'static str {
+    if value {
+        "On"
+    } else {
+        "Off"
+    }
+}
@@ -0,0 +1,226 @@
+use std::f32::consts::PI;
+
+use tanton::{BitMove, Board};
+
+pub const SAMPLE_RATE: u32 = 44_100;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SoundEffect {
+    Move,
+    Capture,
+    Check,
+    GameEnd,
+    Challenge,
+    /// A move arrived while the window is in the background
+    IncomingMove,
+}
+
+impl SoundEffect {
+    pub const ALL: [SoundEffect; 6] = [
+        SoundEffect::Move,
+        SoundEffect::Capture,
+        SoundEffect::Check,
+        SoundEffect::GameEnd,
+        SoundEffect::Challenge,
+        SoundEffect::IncomingMove,
+    ];
+
+    /// The sound for `mv`, given the position after it was played
+    pub fn for_move(mv: BitMove, board_fen: &str) -> Self {
+        let is_check = Board::from_fen(board_fen).map(|b| b.in_check()).unwrap_or(false);
+        if is_check {
+            SoundEffect::Check
+        } else if mv.is_capture() {
+            SoundEffect::Capture
+        } else {
+            SoundEffect::Move
+        }
+    }
+
+    /// The notes making up the effect as (frequency in Hz, duration in seconds). A frequency of 0 is a rest.
+    fn notes(&self) -> &'static [(f32, f32)] {
+        match self {
+            SoundEffect::Move => &[(440.0, 0.06)],
+            SoundEffect::Capture => &[(330.0, 0.05), (220.0, 0.08)],
+            SoundEffect::Check => &[(660.0, 0.08), (0.0, 0.04), (660.0, 0.08)],
+            SoundEffect::GameEnd => &[(523.3, 0.15), (659.3, 0.15), (784.0, 0.3)],
+            SoundEffect::Challenge => &[(784.0, 0.1), (0.0, 0.05), (988.0, 0.15)],
+            SoundEffect::IncomingMove => &[(587.3, 0.1), (880.0, 0.15)],
+        }
+    }
+
+    /// Synthesises the effect as mono samples in the range -1 to 1
+    pub fn samples(&self) -> Vec<f32> {
+        // Fading each note in and out avoids clicks
+        const FADE_SECS: f32 = 0.005;
+        let fade_len = (FADE_SECS * SAMPLE_RATE as f32) as usize;
+        let mut samples = Vec::new();
+        for &(frequency, duration) in self.notes() {
+            let len = (duration * SAMPLE_RATE as f32) as usize;
+            samples.extend((0..len).map(|i| {
+                let envelope = (i.min(len - i) as f32 / fade_len as f32).min(1.0);
+                let t = i as f32 / SAMPLE_RATE as f32;
+                (2.0 * PI * frequency * t).sin() * envelope * 0.5
+            }));
+        }
+        samples
+    }
+}
+
+/// Somewhere to play sound effects
+pub trait AudioSink {
+    /// Plays the effect at `volume`, between 0 and 1
+    fn play(&mut self, effect: SoundEffect, volume: f64) -> anyhow::Result<()>;
+}
+
+/// Discards every sound, used when audio is disabled or no output device is available
+#[derive(Debug, Default)]
+pub struct NullSink;
+
+impl AudioSink for NullSink {
+    fn play(&mut self, _: SoundEffect, _: f64) -> anyhow::Result<()> {
+        Ok(())
+    }
+}
+
+/// Plays sound effects at the user's volume
+pub struct Sounds {
+    sink: Box<dyn AudioSink>,
+    volume: f64,
+    is_muted: bool,
+}
+
+impl Sounds {
+    pub fn new(sink: Box<dyn AudioSink>) -> Self {
+        Self {
+            sink,
+            volume: 1.0,
+            is_muted: false,
+        }
+    }
+
+    /// Uses the default output device, or plays nothing if audio is unavailable
+    pub fn initialize() -> Self {
+        #[cfg(feature = "sound")]
+        match kira_sink::KiraSink::new() {
+            Ok(sink) => return Self::new(Box::new(sink)),
+            Err(err) => log::warn!("Sound is unavailable: {}", err),
+        }
+        Self::new(Box::new(NullSink))
+    }
+
+    /// Sets the volume as a percentage
+    pub fn set_volume(&mut self, volume: u8) -> &mut Self {
+        self.volume = f64::from(volume.min(100)) / 100.0;
+        self
+    }
+
+    pub fn set_muted(&mut self, is_muted: bool) -> &mut Self {
+        self.is_muted = is_muted;
+        self
+    }
+
+    pub fn play(&mut self, effect: SoundEffect) {
+        if self.is_muted || self.volume <= 0.0 {
+            return;
+        }
+        if let Err(err) = self.sink.play(effect, self.volume) {
+            log::error!("Failed to play {:?}: {}", effect, err);
+        }
+    }
+}
+
+impl std::fmt::Debug for Sounds {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Sounds")
+            .field("volume", &self.volume)
+            .field("is_muted", &self.is_muted)
+            .finish()
+    }
+}
+
+#[cfg(feature = "sound")]
+mod kira_sink {
+    use std::{collections::HashMap, sync::Arc};
+
+    use kira::{
+        dsp::Frame,
+        manager::{backend::cpal::CpalBackend, AudioManager, AudioManagerSettings},
+        sound::static_sound::{StaticSoundData, StaticSoundSettings},
+    };
+
+    use super::{AudioSink, SoundEffect, SAMPLE_RATE};
+
+    pub struct KiraSink {
+        manager: AudioManager<CpalBackend>,
+        sounds: HashMap<SoundEffect, StaticSoundData>,
+    }
+
+    impl KiraSink {
+        pub fn new() -> anyhow::Result<Self> {
+            let manager = AudioManager::new(AudioManagerSettings::default())?;
+            let sounds = SoundEffect::ALL
+                .iter()
+                .map(|effect| {
+                    let frames = effect.samples().into_iter().map(Frame::from_mono).collect();
+                    let data = StaticSoundData {
+                        sample_rate: SAMPLE_RATE,
+                        frames: Arc::new(frames),
+                        settings: StaticSoundSettings::new(),
+                    };
+                    (*effect, data)
+                })
+                .collect();
+            Ok(Self { manager, sounds })
+        }
+    }
+
+    impl AudioSink for KiraSink {
+        fn play(&mut self, effect: SoundEffect, volume: f64) -> anyhow::Result<()> {
+            if let Some(data) = self.sounds.get(&effect) {
+                let data = data.with_modified_settings(|s| s.volume(volume));
+                self.manager.play(data)?;
+            }
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{cell::RefCell, rc::Rc};
+
+    use super::*;
+
+    #[derive(Default)]
+    struct RecordingSink {
+        played: Rc<RefCell<Vec<(SoundEffect, f64)>>>,
+    }
+
+    impl AudioSink for RecordingSink {
+        fn play(&mut self, effect: SoundEffect, volume: f64) -> anyhow::Result<()> {
+            self.played.borrow_mut().push((effect, volume));
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn volume_and_mute() {
+        let sink = RecordingSink::default();
+        let played = sink.played.clone();
+        let mut sounds = Sounds::new(Box::new(sink));
+        sounds.set_volume(50).play(SoundEffect::Move);
+        sounds.set_muted(true).play(SoundEffect::Capture);
+        sounds.set_muted(false).set_volume(0).play(SoundEffect::Check);
+        assert_eq!(*played.borrow(), vec![(SoundEffect::Move, 0.5)]);
+    }
+
+    #[test]
+    fn samples_are_in_range() {
+        for effect in SoundEffect::ALL {
+            let samples = effect.samples();
+            assert!(!samples.is_empty());
+            assert!(samples.iter().all(|s| s.abs() <= 0.5));
+        }
+    }
+}
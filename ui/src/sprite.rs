@@ -40,6 +40,8 @@ impl<K: Hash + Eq, I: GetRgba> SpriteSheet<K, I> {
             y,
             ignore_color: self.ignore_color,
             subst_color: None,
+            scale: None,
+            alpha: 0xff,
         })
     }
 }
@@ -51,6 +53,17 @@ pub struct DrawableSprite<'a, I> {
     y: u32,
     ignore_color: Option<Color>,
     subst_color: Option<(Color, Color)>,
+    scale: Option<(u32, u32, ScaleFilter)>,
+    alpha: u8,
+}
+
+/// How a sprite is resampled when it is drawn at a different size to its area
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScaleFilter {
+    /// Copies the closest pixel, keeping hard edges
+    Nearest,
+    /// Blends the four closest pixels, smoother for small or uneven scales
+    Bilinear,
 }
 
 impl<'a, I> DrawableSprite<'a, I> {
@@ -58,10 +71,106 @@ impl<'a, I> DrawableSprite<'a, I> {
         self.subst_color = Some((from, to));
         self
     }
+
+    /// Draws the sprite `w` by `h` pixels instead of the size of its area
+    pub fn with_scale(&mut self, w: u32, h: u32, filter: ScaleFilter) -> &mut Self {
+        self.scale = Some((w, h, filter));
+        self
+    }
+
+    /// Draws the sprite semi-transparent, where 0 is invisible and 255 is opaque
+    pub fn with_alpha(&mut self, alpha: u8) -> &mut Self {
+        self.alpha = alpha;
+        self
+    }
+
+    fn put_pixel(&self, x: u32, y: u32, px: u32, buf: &mut FrameBuffer) {
+        if self.alpha == 0xff {
+            buf.put_pixel(x, y, px);
+        } else {
+            buf.blend_pixel(x, y, Color::from_rgba(px), self.alpha);
+        }
+    }
+}
+
+impl<I: GetRgba> DrawableSprite<'_, I> {
+    /// The image pixel at (x, y) after colour substitution, or None if it is masked out
+    fn source_pixel(&self, x: u32, y: u32) -> Option<u32> {
+        let px = self.image.get_rgba(x as usize, y as usize);
+        if self.ignore_color.map(|c| c.to_rgba() == px).unwrap_or(false) {
+            return None;
+        }
+        match self.subst_color {
+            Some((from, to)) if px == from.to_rgba() => Some(to.to_rgba()),
+            _ => Some(px),
+        }
+    }
+
+    /// Samples the area at a fractional position relative to its top left corner
+    fn sample_bilinear(&self, x: f32, y: f32) -> Option<u32> {
+        let x = x.clamp(0.0, (self.area.w - 1) as f32);
+        let y = y.clamp(0.0, (self.area.h - 1) as f32);
+        let (fx, fy) = (x.fract(), y.fract());
+        let (x0, y0) = (x as u32, y as u32);
+        let (x1, y1) = ((x0 + 1).min(self.area.w - 1), (y0 + 1).min(self.area.h - 1));
+        let samples = [
+            (x0, y0, (1.0 - fx) * (1.0 - fy)),
+            (x1, y0, fx * (1.0 - fy)),
+            (x0, y1, (1.0 - fx) * fy),
+            (x1, y1, fx * fy),
+        ];
+
+        let mut channels = [0.0f32; 3];
+        let mut weight = 0.0;
+        for (sx, sy, w) in samples {
+            if let Some(px) = self.source_pixel(self.area.x + sx, self.area.y + sy) {
+                let c = Color::from_rgba(px);
+                channels[0] += f32::from(c.r) * w;
+                channels[1] += f32::from(c.g) * w;
+                channels[2] += f32::from(c.b) * w;
+                weight += w;
+            }
+        }
+        // Blending with the mask colour would leave a fringe, so mostly masked pixels are not drawn at all
+        if weight < 0.5 {
+            return None;
+        }
+        let channel = |v: f32| (v / weight).round() as u8;
+        Some(Color::new(channel(channels[0]), channel(channels[1]), channel(channels[2]), 0xff).to_rgba())
+    }
+
+    fn draw_scaled(&self, w: u32, h: u32, filter: ScaleFilter, buf: &mut FrameBuffer) {
+        if self.area.w == 0 || self.area.h == 0 {
+            return;
+        }
+        let scale_x = self.area.w as f32 / w as f32;
+        let scale_y = self.area.h as f32 / h as f32;
+        for dy in 0..h {
+            for dx in 0..w {
+                let px = match filter {
+                    ScaleFilter::Nearest => {
+                        self.source_pixel(self.area.x + dx * self.area.w / w, self.area.y + dy * self.area.h / h)
+                    },
+                    ScaleFilter::Bilinear => {
+                        self.sample_bilinear((dx as f32 + 0.5) * scale_x - 0.5, (dy as f32 + 0.5) * scale_y - 0.5)
+                    },
+                };
+                if let Some(px) = px {
+                    self.put_pixel(self.x + dx, self.y + dy, px, buf);
+                }
+            }
+        }
+    }
 }
 
 impl<I: GetRgba> Drawable for DrawableSprite<'_, I> {
     fn draw(&mut self, buf: &mut FrameBuffer) {
+        let scale = self.scale.filter(|&(w, h, _)| w != self.area.w || h != self.area.h);
+        if let Some((w, h, filter)) = scale {
+            self.draw_scaled(w, h, filter, buf);
+            return;
+        }
+
         let mut offset_x = 0;
         let mut offset_y = 0;
         self.area.scan(|x, y| {
@@ -84,7 +193,7 @@ impl<I: GetRgba> Drawable for DrawableSprite<'_, I> {
                     px = to.to_rgba();
                 }
             }
-            buf.put_pixel(offset_x + self.x, offset_y + self.y, px);
+            self.put_pixel(offset_x + self.x, offset_y + self.y, px, buf);
 
             true
         });
@@ -94,3 +203,53 @@ impl<I: GetRgba> Drawable for DrawableSprite<'_, I> {
 pub trait GetRgba {
     fn get_rgba(&self, x: usize, y: usize) -> u32;
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// A 2x2 image of red, white, masked green and blue
+    struct Image;
+
+    impl GetRgba for Image {
+        fn get_rgba(&self, x: usize, y: usize) -> u32 {
+            [[Color::red(), Color::white()], [Color::green(), Color::dark_blue()]][y][x].to_rgba()
+        }
+    }
+
+    fn draw(filter: ScaleFilter) -> FrameBuffer {
+        let mut sheet = SpriteSheet::new(Image);
+        sheet
+            .ignore_color(Color::green())
+            .add_area("sprite", Frame::new(0, 0, 2, 2));
+        let mut buf = FrameBuffer::new(4, 4, Color::black());
+        sheet
+            .get_sprite_drawable(&"sprite", 0, 0)
+            .unwrap()
+            .with_scale(4, 4, filter)
+            .draw(&mut buf);
+        buf
+    }
+
+    #[test]
+    fn nearest_scaling_repeats_pixels() {
+        let buf = draw(ScaleFilter::Nearest);
+        let px = |x: usize, y: usize| buf.as_slice()[x + y * 4];
+        assert_eq!(px(0, 0), Color::red().to_rgba());
+        assert_eq!(px(1, 1), Color::red().to_rgba());
+        assert_eq!(px(3, 0), Color::white().to_rgba());
+        assert_eq!(px(0, 3), Color::black().to_rgba());
+        assert_eq!(px(3, 3), Color::dark_blue().to_rgba());
+    }
+
+    #[test]
+    fn bilinear_scaling_skips_masked_pixels() {
+        let buf = draw(ScaleFilter::Bilinear);
+        let px = |x: usize, y: usize| buf.as_slice()[x + y * 4];
+        assert_eq!(px(0, 0), Color::red().to_rgba());
+        assert_eq!(px(0, 3), Color::black().to_rgba());
+        // Between red and white
+        let blended = Color::from_rgba(px(1, 0));
+        assert!(blended.g > 0 && blended.g < 253);
+    }
+}
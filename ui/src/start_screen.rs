@@ -1,19 +1,44 @@
 use minifb::Window;
+use tanton::Player;
 use tari_comms::types::CommsPublicKey;
 use tari_crypto::tari_utilities::encoding::Base58;
 
 use crate::{
+    board,
     clipboard::Clipboard,
     color::Color,
-    components::{Button, Label, ListBox, TextBox},
-    drawable::{Drawable, FrameBuffer},
-    game::GameCollection,
+    components::{Button, Cell, Column, Label, ListBox, TextBox},
+    drawable::{DirtyRegion, Drawable, FrameBuffer, Widget},
+    focus::{FocusManager, Focusable},
+    font::{DEFAULT_SIZE, FONT},
+    game::{current_timestamp, Game, GameCollection},
+    layout::{Anchor, Padding, Size, Stack},
+    rating::Score,
     rect::{Frame, Rect},
 };
 
 #[derive(Debug)]
 pub struct Drawables<T> {
     pub items: Vec<T>,
+    dirty: DirtyRegion,
+}
+
+impl<T: Widget> Drawables<T> {
+    pub fn new(items: Vec<T>) -> Self {
+        Self {
+            items,
+            dirty: DirtyRegion::default(),
+        }
+    }
+
+    /// Replaces the items, redrawing the area the old ones covered
+    pub fn set_items(&mut self, items: Vec<T>) -> &mut Self {
+        for item in self.items.iter() {
+            self.dirty.add(item.frame());
+        }
+        self.items = items;
+        self
+    }
 }
 
 impl<T: Drawable> Drawable for Drawables<T> {
@@ -24,77 +49,190 @@ impl<T: Drawable> Drawable for Drawables<T> {
     }
 }
 
+impl<T: Widget> Widget for Drawables<T> {
+    fn frame(&self) -> Frame {
+        self.items
+            .iter()
+            .map(|item| item.frame())
+            .reduce(|a, b| a.union(&b))
+            .unwrap_or_default()
+    }
+
+    fn take_dirty(&mut self) -> Option<Frame> {
+        for item in self.items.iter_mut() {
+            if let Some(frame) = item.take_dirty() {
+                self.dirty.add(frame);
+            }
+        }
+        self.dirty.take()
+    }
+}
+
 #[derive(Debug)]
 pub struct StartScreen {
     public_key_input: TextBox,
     start_button: Button,
+    invite_spectator_button: Button,
+    tournaments_button: Button,
+    stats_button: Button,
+    settings_button: Button,
     copy_button: Button,
     show_game: Button,
     selected_game: Option<usize>,
     labels: Drawables<Label>,
     submitted_public_key: Option<String>,
+    invited_spectator: Option<(usize, String)>,
+    tournaments_clicked: bool,
+    stats_clicked: bool,
+    settings_clicked: bool,
     games_selector: ListBox,
+    focus: FocusManager,
 }
 
 impl StartScreen {
-    pub fn new(clipboard: Clipboard, public_key: CommsPublicKey) -> Self {
-        let mut title_label = Label::new(Frame::new(400, 10, 500, 40));
+    pub fn new(clipboard: Clipboard, public_key: CommsPublicKey, window_width: u32, window_height: u32) -> Self {
+        let mut title_label = Label::new(Frame::default());
         title_label.set_text("Privacy Chess").set_text_color(Color::dark_blue());
 
-        let mut my_pk_label = Label::new(Frame::new(10, 50, 500, 40));
+        let mut my_pk_label = Label::new(Frame::default());
         my_pk_label
             .set_text(format!("Player public key {}", public_key.to_base58()))
             .set_text_color(Color::light_grey());
 
-        let mut enter_pk_label = Label::new(Frame::new(10, 150, 500, 40));
+        let mut enter_pk_label = Label::new(Frame::default());
         enter_pk_label.set_text("Enter player public key");
 
-        let mut error_label = Label::new(Frame::new(10, 350, 500, 40));
-        error_label.set_text("").set_text_color(Color::red());
-        let labels = Drawables {
-            items: vec![title_label, my_pk_label, enter_pk_label, error_label],
-        };
+        let labels = Drawables::new(vec![title_label, my_pk_label, enter_pk_label]);
 
-        let public_key_input = TextBox::new(Frame::new(10, 200, 750, 40), clipboard);
-        let mut start_button = Button::new(Rect::new(10, 280, 100, 30, Color::white()));
+        let mut public_key_input = TextBox::new(Frame::default(), clipboard);
+        public_key_input.set_placeholder("Paste a public key");
+        let mut start_button = Button::new(Rect::from_frame(Frame::default(), Color::white()));
         start_button.set_text("New Game");
+        let mut invite_spectator_button = Button::new(Rect::from_frame(Frame::default(), Color::white()));
+        invite_spectator_button.set_text("Invite Spectator");
+        let mut tournaments_button = Button::new(Rect::from_frame(Frame::default(), Color::white()));
+        tournaments_button.set_text("Tournaments");
+        let mut stats_button = Button::new(Rect::from_frame(Frame::default(), Color::white()));
+        stats_button.set_text("Stats");
+        let mut settings_button = Button::new(Rect::from_frame(Frame::default(), Color::white()));
+        settings_button.set_text("Settings");
 
-        let mut copy_button = Button::new(Rect::new(10, 100, 100, 30, Color::white()));
+        let mut copy_button = Button::new(Rect::from_frame(Frame::default(), Color::white()));
         copy_button.set_text("Copy").on_click(move || {
             Clipboard::initialize()
                 .unwrap()
                 .set_contents(public_key.to_base58())
                 .unwrap()
         });
-        let mut show_game = Button::new(Rect::new(10, 580, 100, 30, Color::white()));
+        let mut show_game = Button::new(Rect::from_frame(Frame::default(), Color::white()));
         show_game.set_text("Open Game");
 
-        let mut games_selector = ListBox::new(Frame::new(10, 350, 900, 200));
-        // Always capture keys
-        games_selector.set_active(true);
+        let mut games_selector = ListBox::new(Frame::default());
+        games_selector.set_columns(vec![
+            Column::new("Game", Size::Fixed(60)),
+            Column::new("Opponent", Size::Fill(1)),
+            Column::new("Status", Size::Fixed(90)),
+            Column::new("Turn", Size::Fixed(70)),
+            Column::new("Last move", Size::Fixed(90)),
+        ]);
+        // The games list takes the arrow keys until something else is focused. The focus manager adopts it on the
+        // first update, wherever it is in the focus order.
+        games_selector.set_focused(true);
+        let focus = FocusManager::new();
 
-        Self {
+        let mut screen = Self {
             public_key_input,
             start_button,
+            invite_spectator_button,
+            tournaments_button,
+            stats_button,
+            settings_button,
             copy_button,
             labels,
             selected_game: None,
             submitted_public_key: None,
+            invited_spectator: None,
+            tournaments_clicked: false,
+            stats_clicked: false,
+            settings_clicked: false,
             games_selector,
             show_game,
-        }
+            focus,
+        };
+        screen.resize(window_width, window_height);
+        screen
+    }
+
+    /// Lays the screen out top to bottom, centring the title and giving the games list the rest of the height
+    pub fn resize(&mut self, window_width: u32, window_height: u32) -> &mut Self {
+        let mut column = Stack::column(Padding::all(10).apply(Frame::new(0, 0, window_width, window_height)));
+        let title_width = FONT.measure(self.labels.items[0].text(), DEFAULT_SIZE) + 4;
+        self.labels.items[0].set_frame(Anchor::Top.place(column.next(40), title_width, 40));
+        self.labels.items[1].set_frame(column.next(40));
+        self.copy_button
+            .set_frame(Anchor::TopLeft.place(column.next(30), 100, 30));
+        column.skip(20);
+        self.labels.items[2].set_frame(column.next(40));
+        self.public_key_input
+            .set_frame(Anchor::Left.place(column.next(40), 750, 40));
+        column.skip(20);
+
+        let mut buttons = Stack::row(column.next(30));
+        buttons.set_spacing(10);
+        self.start_button.set_frame(buttons.next(100));
+        self.invite_spectator_button.set_frame(buttons.next(200));
+        self.tournaments_button.set_frame(buttons.next(150));
+        self.stats_button.set_frame(buttons.next(100));
+        self.settings_button.set_frame(buttons.next(120));
+
+        column.skip(20);
+        column.set_spacing(20);
+        let games = column.split(&[Size::Fill(1), Size::Fixed(30)]);
+        self.games_selector.set_frame(games[0]);
+        self.show_game.set_frame(Anchor::Left.place(games[1], 100, 30));
+        self
     }
 
     pub fn update(&mut self, window: &Window) {
         self.public_key_input.update(window);
         self.start_button.update(window);
+        self.invite_spectator_button.update(window);
+        self.tournaments_button.update(window);
+        self.stats_button.update(window);
+        self.settings_button.update(window);
         self.copy_button.update(window);
         self.games_selector.update(window);
         self.show_game.update(window);
+        self.focus.update(window, &mut [
+            &mut self.copy_button,
+            &mut self.public_key_input,
+            &mut self.start_button,
+            &mut self.invite_spectator_button,
+            &mut self.tournaments_button,
+            &mut self.stats_button,
+            &mut self.settings_button,
+            &mut self.games_selector,
+            &mut self.show_game,
+        ]);
         if self.start_button.was_clicked() {
             self.submitted_public_key = Some(self.public_key_input.value().to_string())
         }
-        if self.show_game.was_clicked() {
+        if self.invite_spectator_button.was_clicked() {
+            self.invited_spectator = self
+                .games_selector
+                .selected_index()
+                .map(|idx| (idx, self.public_key_input.value().to_string()));
+        }
+        if self.tournaments_button.was_clicked() {
+            self.tournaments_clicked = true;
+        }
+        if self.stats_button.was_clicked() {
+            self.stats_clicked = true;
+        }
+        if self.settings_button.was_clicked() {
+            self.settings_clicked = true;
+        }
+        if self.show_game.was_clicked() || self.games_selector.was_activated() {
             dbg!("SHOW GAME CLICKED");
             self.selected_game = self.games_selector.selected_index();
         }
@@ -108,26 +246,97 @@ impl StartScreen {
         self.selected_game
     }
 
-    pub fn set_input_error<T: Into<String>>(&mut self, msg: T) -> &mut Self {
-        // TODO: bleh
-        self.labels.items.last_mut().unwrap().set_text(msg);
-        self
+    pub fn tournaments_clicked(&self) -> bool {
+        self.tournaments_clicked
+    }
+
+    pub fn stats_clicked(&self) -> bool {
+        self.stats_clicked
+    }
+
+    pub fn settings_clicked(&self) -> bool {
+        self.settings_clicked
+    }
+
+    /// Returns the index of the selected game and the public key entered for the spectator
+    pub fn take_invited_spectator(&mut self) -> Option<(usize, String)> {
+        self.invited_spectator.take()
     }
 
     pub fn set_games(&mut self, games: &GameCollection) {
-        self.games_selector.set_values(
-            games
-                .iter()
-                .map(|g| format!("{} {}", g.id, g.opponent.to_base58()))
-                .collect(),
-        );
+        let now = current_timestamp();
+        self.games_selector
+            .set_rows(games.iter().map(|game| game_row(game, now)).collect());
+    }
+}
+
+/// The opponent, a badge for how the game stands, whose turn it is and how long since the last move
+fn game_row(game: &Game, now: u64) -> Vec<Cell> {
+    let (_, turn) = board::move_number(&game.board_fen);
+    let (opponent, status, turn) = match game.watching {
+        Some(ref watching) => (
+            format!("{} vs {}", watching.white.to_base58(), watching.black.to_base58()),
+            Cell::badge("Watching", Color::dark_blue()),
+            Cell::new(if turn == Player::White { "White" } else { "Black" }),
+        ),
+        None => {
+            let status = match Score::from_game(game) {
+                Some(Score::Win) => Cell::badge("Won", Color::dark_green()),
+                Some(Score::Loss) => Cell::badge("Lost", Color::red()),
+                Some(Score::Draw) => Cell::badge("Draw", Color::grey(0x60)),
+                None => Cell::badge("Playing", Color::grey(0x40)),
+            };
+            let turn = if game.has_completed() {
+                Cell::new("")
+            } else if turn == game.player {
+                Cell::new("Yours").with_color(Color::yellow())
+            } else {
+                Cell::new("Theirs")
+            };
+            (game.opponent.to_base58(), status, turn)
+        },
+    };
+    let age = now.saturating_sub(game.last_activity);
+    vec![
+        Cell::new(game.id.to_string()).with_sort_key(i64::from(game.id)),
+        Cell::new(opponent),
+        status,
+        turn,
+        Cell::new(format_age(age)).with_sort_key(age as i64),
+    ]
+}
+
+/// A short, human readable time since something happened, e.g. "5m"
+fn format_age(secs: u64) -> String {
+    match secs {
+        0..=59 => "now".to_string(),
+        60..=3599 => format!("{}m", secs / 60),
+        3600..=86399 => format!("{}h", secs / 3600),
+        _ => format!("{}d", secs / 86400),
     }
 }
 
 impl Drawable for StartScreen {
     fn draw(&mut self, buf: &mut FrameBuffer) {
+        buf.invalidate_widgets(&mut [
+            &mut self.public_key_input,
+            &mut self.start_button,
+            &mut self.invite_spectator_button,
+            &mut self.tournaments_button,
+            &mut self.stats_button,
+            &mut self.settings_button,
+            &mut self.copy_button,
+            &mut self.labels,
+            &mut self.games_selector,
+            &mut self.show_game,
+        ]);
+        buf.clear(Color::black());
         self.public_key_input.draw(buf);
         self.start_button.draw(buf);
+        self.invite_spectator_button.draw(buf);
+        self.tournaments_button.draw(buf);
+        self.stats_button.draw(buf);
+        self.settings_button.draw(buf);
         self.copy_button.draw(buf);
         self.labels.draw(buf);
         self.games_selector.draw(buf);
@@ -0,0 +1,163 @@
+use minifb::Window;
+
+use crate::{
+    color::Color,
+    components::{Button, Label},
+    drawable::{Drawable, FrameBuffer},
+    font::{DEFAULT_SIZE, FONT},
+    game::short_key,
+    layout::{Anchor, Padding, Size, Stack},
+    rating::{HeadToHead, Rating, Ratings},
+    rect::{Frame, Rect},
+    start_screen::Drawables,
+};
+
+const MAX_OPPONENT_ROWS: usize = 20;
+const ROW_HEIGHT: u32 = 20;
+const ROW_SPACING: u32 = 5;
+/// The opponent column takes the width the others leave
+const COLUMNS: [Size; 7] = [
+    Size::Fixed(30),
+    Size::Fill(1),
+    Size::Fixed(80),
+    Size::Fixed(80),
+    Size::Fixed(60),
+    Size::Fixed(60),
+    Size::Fixed(60),
+];
+
+#[derive(Debug)]
+pub struct StatsScreen {
+    back_button: Button,
+    labels: Drawables<Label>,
+    rating_label: Label,
+    opponent_labels: Drawables<Label>,
+    /// The area the head to head table is laid out in
+    table_frame: Frame,
+}
+
+impl StatsScreen {
+    pub fn new(ratings: &Ratings, window_width: u32, window_height: u32) -> Self {
+        let mut title_label = Label::new(Frame::default());
+        title_label.set_text("Stats").set_text_color(Color::dark_blue());
+
+        let mut opponents_label = Label::new(Frame::default());
+        opponents_label.set_text("Head to head");
+
+        let mut back_button = Button::new(Rect::from_frame(Frame::default(), Color::white()));
+        back_button.set_text("Back");
+
+        let mut screen = Self {
+            back_button,
+            labels: Drawables::new(vec![title_label, opponents_label]),
+            rating_label: Label::new(Frame::default()),
+            opponent_labels: Drawables::new(Vec::new()),
+            table_frame: Frame::default(),
+        };
+        screen.set_ratings(ratings);
+        screen.resize(window_width, window_height);
+        screen
+    }
+
+    /// Lays the screen out top to bottom, giving the head to head table the rest of the height
+    pub fn resize(&mut self, window_width: u32, window_height: u32) -> &mut Self {
+        let mut column = Stack::column(Padding::all(10).apply(Frame::new(0, 0, window_width, window_height)));
+        column.set_spacing(10);
+        let header = column.next(40);
+        let title_width = FONT.measure(self.labels.items[0].text(), DEFAULT_SIZE) + 4;
+        self.labels.items[0].set_frame(Anchor::Top.place(header, title_width, 40));
+        self.back_button.set_frame(Anchor::TopRight.place(header, 100, 30));
+        self.rating_label.set_frame(column.next(20));
+        column.skip(10);
+        self.labels.items[1].set_frame(column.next(20));
+        self.table_frame = column.rest();
+        self.layout_table();
+        self
+    }
+
+    pub fn update(&mut self, window: &Window) {
+        self.back_button.update(window);
+    }
+
+    pub fn was_back_clicked(&mut self) -> bool {
+        self.back_button.was_clicked()
+    }
+
+    pub fn set_ratings(&mut self, ratings: &Ratings) {
+        self.rating_label.set_text(format!(
+            "Your rating {}  Played {}  W {}  D {}  L {}",
+            format_rating(&ratings.ours),
+            ratings.record.played(),
+            ratings.record.wins,
+            ratings.record.draws,
+            ratings.record.losses
+        ));
+
+        let mut items = vec![opponent_row(
+            ["#", "Opponent", "Rating", "RD", "W", "D", "L"],
+            Color::light_grey(),
+        )];
+        for (i, opponent) in ratings.opponents.iter().take(MAX_OPPONENT_ROWS).enumerate() {
+            let HeadToHead { wins, draws, losses } = opponent.record;
+            items.push(opponent_row(
+                [
+                    &(i + 1).to_string(),
+                    &short_key(&opponent.public_key, 40),
+                    &format!("{:.0}", opponent.rating.rating),
+                    &format!("{:.0}", opponent.rating.deviation),
+                    &wins.to_string(),
+                    &draws.to_string(),
+                    &losses.to_string(),
+                ],
+                Color::white(),
+            ));
+        }
+        self.opponent_labels.set_items(items.into_iter().flatten().collect());
+        self.layout_table();
+    }
+
+    /// Places the table's rows one below the other. Rows that don't fit in the window are hidden.
+    fn layout_table(&mut self) {
+        let mut rows = Stack::column(self.table_frame);
+        rows.set_spacing(ROW_SPACING);
+        for row in self.opponent_labels.items.chunks_mut(COLUMNS.len()) {
+            let frame = rows.next(ROW_HEIGHT);
+            let mut cells = Stack::row(frame);
+            for (label, cell) in row.iter_mut().zip(cells.split(&COLUMNS)) {
+                label.set_frame(cell).set_visible(frame.h == ROW_HEIGHT);
+            }
+        }
+    }
+}
+
+impl Drawable for StatsScreen {
+    fn draw(&mut self, buf: &mut FrameBuffer) {
+        buf.invalidate_widgets(&mut [
+            &mut self.labels,
+            &mut self.back_button,
+            &mut self.rating_label,
+            &mut self.opponent_labels,
+        ]);
+        buf.clear(Color::black());
+        self.labels.draw(buf);
+        self.back_button.draw(buf);
+        self.rating_label.draw(buf);
+        self.opponent_labels.draw(buf);
+    }
+}
+
+/// The labels of one table row, laid out by `layout_table`
+fn opponent_row(columns: [&str; 7], color: Color) -> Vec<Label> {
+    columns
+        .iter()
+        .map(|text| {
+            let mut label = Label::new(Frame::default());
+            label.set_text(*text).set_text_color(color);
+            label
+        })
+        .collect()
+}
+
+fn format_rating(rating: &Rating) -> String {
+    format!("{:.0} RD {:.0}", rating.rating, rating.deviation)
+}
@@ -0,0 +1,228 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::anyhow;
+use serde::{de::Error, Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::{bitmap::Bitmap, color::Color, rect::Frame, sprite::SpriteSheet};
+
+pub const DEFAULT_THEME: &str = "Classic";
+
+/// The sprite names a piece sprite sheet must have an area for
+pub const PIECE_SPRITES: [&str; 12] = [
+    "king-white",
+    "queen-white",
+    "rook-white",
+    "bishop-white",
+    "knight-white",
+    "pawn-white",
+    "king-black",
+    "queen-black",
+    "rook-black",
+    "bishop-black",
+    "knight-black",
+    "pawn-black",
+];
+
+/// Board colours and pieces, loaded from a JSON file in the themes directory. Colours are written as "#rrggbb".
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Theme {
+    pub name: String,
+    #[serde(flatten)]
+    pub colours: BoardColours,
+    /// Uses the built in pieces if not set
+    #[serde(default)]
+    pub pieces: Option<PieceSet>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct BoardColours {
+    #[serde(serialize_with = "serialize_color", deserialize_with = "deserialize_color")]
+    pub light: Color,
+    #[serde(serialize_with = "serialize_color", deserialize_with = "deserialize_color")]
+    pub dark: Color,
+    #[serde(
+        default = "Color::yellow",
+        serialize_with = "serialize_color",
+        deserialize_with = "deserialize_color"
+    )]
+    pub last_move: Color,
+    #[serde(
+        default = "Color::dark_green",
+        serialize_with = "serialize_color",
+        deserialize_with = "deserialize_color"
+    )]
+    pub premove: Color,
+    #[serde(
+        default = "Color::green",
+        serialize_with = "serialize_color",
+        deserialize_with = "deserialize_color"
+    )]
+    pub selected: Color,
+    #[serde(
+        default = "Color::red",
+        serialize_with = "serialize_color",
+        deserialize_with = "deserialize_color"
+    )]
+    pub check: Color,
+}
+
+/// A piece sprite sheet. `image` is a BMP file relative to the theme file and `areas` has an entry for each of
+/// `PIECE_SPRITES`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PieceSet {
+    pub image: PathBuf,
+    /// Pixels of this colour are not drawn
+    #[serde(
+        default = "Color::green",
+        serialize_with = "serialize_color",
+        deserialize_with = "deserialize_color"
+    )]
+    pub transparent: Color,
+    pub areas: HashMap<String, Frame>,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self {
+            name: DEFAULT_THEME.to_string(),
+            colours: BoardColours {
+                light: Color::white(),
+                dark: Color::dark_blue(),
+                last_move: Color::yellow(),
+                premove: Color::dark_green(),
+                selected: Color::green(),
+                check: Color::red(),
+            },
+            pieces: None,
+        }
+    }
+}
+
+impl Theme {
+    pub fn load<P: AsRef<Path>>(path: P) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let mut theme = serde_json::from_slice::<Theme>(&fs::read(path)?)?;
+        if let Some(ref mut pieces) = theme.pieces {
+            if let Some(dir) = path.parent() {
+                pieces.image = dir.join(&pieces.image);
+            }
+        }
+        Ok(theme)
+    }
+
+    /// Returns the built in theme followed by every valid theme file in `dir`, sorted by name. Invalid files are
+    /// logged and skipped.
+    pub fn load_all<P: AsRef<Path>>(dir: P) -> Vec<Self> {
+        let mut themes = Vec::new();
+        if let Ok(entries) = fs::read_dir(dir) {
+            for path in entries.filter_map(|e| e.ok()).map(|e| e.path()) {
+                if path.extension().map(|ext| ext != "json").unwrap_or(true) {
+                    continue;
+                }
+                match Theme::load(&path) {
+                    Ok(theme) if theme.name != DEFAULT_THEME => themes.push(theme),
+                    Ok(_) => log::warn!("Theme {} uses a reserved name", path.display()),
+                    Err(err) => log::warn!("Failed to load theme {}: {}", path.display(), err),
+                }
+            }
+        }
+        themes.sort_by(|a, b| a.name.cmp(&b.name));
+        themes.insert(0, Theme::default());
+        themes
+    }
+
+    /// Loads the theme's pieces, falling back to the built in pieces if they can't be loaded
+    pub fn piece_sprites(&self) -> SpriteSheet<&'static str, Bitmap> {
+        match self.pieces {
+            Some(ref pieces) => pieces.load().unwrap_or_else(|err| {
+                log::warn!("Failed to load pieces for theme {}: {}", self.name, err);
+                default_pieces()
+            }),
+            None => default_pieces(),
+        }
+    }
+}
+
+impl PieceSet {
+    pub fn load(&self) -> anyhow::Result<SpriteSheet<&'static str, Bitmap>> {
+        let image = Bitmap::from_reader(&mut fs::File::open(&self.image)?)?;
+        let mut sprite_sheet = SpriteSheet::new(image);
+        sprite_sheet.ignore_color(self.transparent);
+        for name in PIECE_SPRITES {
+            let area = self.areas.get(name).ok_or_else(|| anyhow!("No area for {}", name))?;
+            sprite_sheet.add_area(name, *area);
+        }
+        Ok(sprite_sheet)
+    }
+}
+
+fn default_pieces() -> SpriteSheet<&'static str, Bitmap> {
+    let image = Bitmap::from_reader(&mut include_bytes!("../assets/pieces.bmp").as_slice()).unwrap();
+    let mut sprite_sheet = SpriteSheet::new(image);
+    let pieces = Frame {
+        x: 0,
+        y: 0,
+        w: 90,
+        h: 90,
+    };
+    sprite_sheet
+        .ignore_color(Color::green())
+        .add_area("king-white", pieces)
+        .add_area("queen-white", pieces.offset_xy(90, 0))
+        .add_area("rook-white", pieces.offset_xy(180, 0))
+        .add_area("bishop-white", pieces.offset_xy(270, 0))
+        .add_area("knight-white", pieces.offset_xy(360, 0))
+        .add_area("pawn-white", pieces.offset_xy(0, 90))
+        .add_area("king-black", pieces.offset_xy(90, 90))
+        .add_area("queen-black", pieces.offset_xy(180, 90))
+        .add_area("rook-black", pieces.offset_xy(270, 90))
+        .add_area("bishop-black", pieces.offset_xy(360, 90))
+        .add_area("knight-black", pieces.offset_xy(0, 180))
+        .add_area("pawn-black", pieces.offset_xy(90, 180));
+    sprite_sheet
+}
+
+fn serialize_color<S: Serializer>(color: &Color, ser: S) -> Result<S::Ok, S::Error> {
+    ser.serialize_str(&format!("#{:02x}{:02x}{:02x}", color.r, color.g, color.b))
+}
+
+fn deserialize_color<'de, D>(des: D) -> Result<Color, D::Error>
+where D: Deserializer<'de> {
+    let s = String::deserialize(des)?;
+    parse_color(&s).ok_or_else(|| D::Error::custom(format!("invalid colour '{}', expected #rrggbb", s)))
+}
+
+fn parse_color(s: &str) -> Option<Color> {
+    let hex = s.strip_prefix('#')?;
+    if hex.len() != 6 {
+        return None;
+    }
+    let channel = |i: usize| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok();
+    Some(Color::new(channel(0)?, channel(2)?, channel(4)?, 0xff))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parses_theme_with_default_highlights() {
+        let theme =
+            serde_json::from_str::<Theme>(r##"{"name": "Green", "light": "#eeeed2", "dark": "#769656"}"##).unwrap();
+        assert_eq!(theme.colours.light, Color::new(0xee, 0xee, 0xd2, 0xff));
+        assert_eq!(theme.colours.dark, Color::new(0x76, 0x96, 0x56, 0xff));
+        assert_eq!(theme.colours.last_move, Color::yellow());
+        assert!(theme.pieces.is_none());
+    }
+
+    #[test]
+    fn rejects_invalid_colour() {
+        assert!(parse_color("eeeed2").is_none());
+        assert!(parse_color("#eeee").is_none());
+        assert!(parse_color("#gggggg").is_none());
+    }
+}
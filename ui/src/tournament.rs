@@ -0,0 +1,605 @@
+use std::{
+    cmp::Reverse,
+    fmt::{Display, Formatter},
+};
+
+use anyhow::anyhow;
+use serde::{Deserialize, Serialize};
+use tanton::Player;
+use tari_comms::types::CommsPublicKey;
+
+use crate::game::{Game, GameResult};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TournamentFormat {
+    RoundRobin,
+    Swiss { rounds: u32 },
+}
+
+impl Display for TournamentFormat {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TournamentFormat::RoundRobin => write!(f, "Round robin"),
+            TournamentFormat::Swiss { rounds } => write!(f, "Swiss {} rounds", rounds),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Outcome {
+    WhiteWins = 0,
+    BlackWins = 1,
+    Draw = 2,
+}
+
+impl Display for Outcome {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Outcome::WhiteWins => write!(f, "White wins"),
+            Outcome::BlackWins => write!(f, "Black wins"),
+            Outcome::Draw => write!(f, "Draw"),
+        }
+    }
+}
+
+impl Outcome {
+    pub fn from_game(game: &Game) -> Option<Self> {
+        let winner = match game.result {
+            GameResult::None => return None,
+            GameResult::Draw => return Some(Outcome::Draw),
+            GameResult::Checkmate(winner) => winner,
+            GameResult::WeResigned => game.player.other_player(),
+            GameResult::TheyResigned => game.player,
+        };
+        match winner {
+            Player::White => Some(Outcome::WhiteWins),
+            Player::Black => Some(Outcome::BlackWins),
+        }
+    }
+}
+
+impl TryFrom<u8> for Outcome {
+    type Error = anyhow::Error;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(Outcome::WhiteWins),
+            1 => Ok(Outcome::BlackWins),
+            2 => Ok(Outcome::Draw),
+            _ => Err(anyhow!("Invalid outcome {}", value)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Pairing {
+    pub game_id: u32,
+    pub white: CommsPublicKey,
+    pub black: CommsPublicKey,
+    pub outcome: Option<Outcome>,
+    /// Outcomes reported by each player, the outcome is only recorded once they agree
+    #[serde(default)]
+    pub white_report: Option<Outcome>,
+    #[serde(default)]
+    pub black_report: Option<Outcome>,
+}
+
+impl Pairing {
+    pub fn includes(&self, public_key: &CommsPublicKey) -> bool {
+        self.white == *public_key || self.black == *public_key
+    }
+
+    /// Returns true if both players reported an outcome and they don't agree
+    pub fn is_disputed(&self) -> bool {
+        match (self.outcome, self.white_report, self.black_report) {
+            (None, Some(white_report), Some(black_report)) => white_report != black_report,
+            _ => false,
+        }
+    }
+
+    /// Returns the colour `public_key` plays and their opponent, if they play in this pairing
+    pub fn side_of(&self, public_key: &CommsPublicKey) -> Option<(Player, &CommsPublicKey)> {
+        if self.white == *public_key {
+            Some((Player::White, &self.black))
+        } else if self.black == *public_key {
+            Some((Player::Black, &self.white))
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Round {
+    pub pairings: Vec<Pairing>,
+    /// Player sitting out this round, if there is an odd number of participants
+    pub bye: Option<CommsPublicKey>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Tournament {
+    pub id: u32,
+    pub organiser: CommsPublicKey,
+    pub format: TournamentFormat,
+    pub participants: Vec<CommsPublicKey>,
+    pub rounds: Vec<Round>,
+    /// Incremented by the organiser on every change so that participants can ignore stale updates
+    pub version: u32,
+}
+
+impl Tournament {
+    pub fn new(
+        id: u32,
+        organiser: CommsPublicKey,
+        format: TournamentFormat,
+        participants: Vec<CommsPublicKey>,
+    ) -> Self {
+        Self {
+            id,
+            organiser,
+            format,
+            participants,
+            rounds: Vec::new(),
+            version: 0,
+        }
+    }
+
+    pub fn total_rounds(&self) -> usize {
+        match self.format {
+            TournamentFormat::RoundRobin => {
+                let n = self.participants.len();
+                if n.is_multiple_of(2) {
+                    n.saturating_sub(1)
+                } else {
+                    n
+                }
+            },
+            TournamentFormat::Swiss { rounds } => rounds as usize,
+        }
+    }
+
+    pub fn is_participant(&self, public_key: &CommsPublicKey) -> bool {
+        self.participants.contains(public_key)
+    }
+
+    pub fn is_round_complete(&self) -> bool {
+        self.rounds
+            .last()
+            .map(|r| r.pairings.iter().all(|p| p.outcome.is_some()))
+            .unwrap_or(true)
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.rounds.len() >= self.total_rounds() && self.is_round_complete()
+    }
+
+    pub fn pairing(&self, game_id: u32) -> Option<&Pairing> {
+        self.rounds
+            .iter()
+            .flat_map(|r| r.pairings.iter())
+            .find(|p| p.game_id == game_id)
+    }
+
+    /// Records the outcome of a game. Returns false if the game is not part of this tournament or already has an
+    /// outcome.
+    pub fn record_outcome(&mut self, game_id: u32, outcome: Outcome) -> bool {
+        let pairing = self
+            .rounds
+            .iter_mut()
+            .flat_map(|r| r.pairings.iter_mut())
+            .find(|p| p.game_id == game_id);
+        match pairing {
+            Some(pairing) if pairing.outcome.is_none() => {
+                pairing.outcome = Some(outcome);
+                self.version += 1;
+                true
+            },
+            _ => false,
+        }
+    }
+
+    /// Stores the outcome reported by one of the players of a game, replacing their earlier report, and records it
+    /// once both players have reported the same outcome. Returns true if the outcome was recorded. If the reports
+    /// don't agree the game stays open until a player corrects theirs or the organiser settles it with
+    /// `record_outcome`.
+    pub fn report_outcome(
+        &mut self,
+        game_id: u32,
+        reporter: &CommsPublicKey,
+        outcome: Outcome,
+    ) -> anyhow::Result<bool> {
+        let pairing = self
+            .rounds
+            .iter_mut()
+            .flat_map(|r| r.pairings.iter_mut())
+            .find(|p| p.game_id == game_id)
+            .ok_or_else(|| anyhow!("Game {} is not part of tournament {}", game_id, self.id))?;
+        let (report, other_report) = if pairing.white == *reporter {
+            (&mut pairing.white_report, pairing.black_report)
+        } else if pairing.black == *reporter {
+            (&mut pairing.black_report, pairing.white_report)
+        } else {
+            return Err(anyhow!("Result for game {} from a non-player", game_id));
+        };
+        *report = Some(outcome);
+        if other_report != Some(outcome) {
+            return Ok(false);
+        }
+        Ok(self.record_outcome(game_id, outcome))
+    }
+
+    /// Pairs the next round if the current round is complete. Returns None if no round could be started.
+    pub fn next_round<F: FnMut() -> u32>(&mut self, mut next_game_id: F) -> Option<&Round> {
+        if !self.is_round_complete() || self.rounds.len() >= self.total_rounds() {
+            return None;
+        }
+
+        let (pairs, bye) = match self.format {
+            TournamentFormat::RoundRobin => round_robin_pairs(self.participants.len(), self.rounds.len()),
+            TournamentFormat::Swiss { .. } => {
+                let ranked = self
+                    .standings()
+                    .iter()
+                    .map(|s| self.index_of(&s.player))
+                    .collect::<Vec<_>>();
+                swiss_pairs(
+                    &ranked,
+                    |a, b| self.have_played(a, b),
+                    |p| self.white_count(p),
+                    |p| self.has_had_bye(p),
+                )
+            },
+        };
+
+        let round = Round {
+            pairings: pairs
+                .into_iter()
+                .map(|(white, black)| Pairing {
+                    game_id: next_game_id(),
+                    white: self.participants[white].clone(),
+                    black: self.participants[black].clone(),
+                    outcome: None,
+                    white_report: None,
+                    black_report: None,
+                })
+                .collect(),
+            bye: bye.map(|p| self.participants[p].clone()),
+        };
+        self.rounds.push(round);
+        self.version += 1;
+        self.rounds.last()
+    }
+
+    /// Standings ordered by points. A win scores 1 point, a draw ½ and a bye 1.
+    pub fn standings(&self) -> Vec<Standing> {
+        let mut standings = self
+            .participants
+            .iter()
+            .map(|p| Standing::new(p.clone()))
+            .collect::<Vec<_>>();
+
+        for round in &self.rounds {
+            for pairing in &round.pairings {
+                let outcome = match pairing.outcome {
+                    Some(outcome) => outcome,
+                    None => continue,
+                };
+                for standing in standings.iter_mut().filter(|s| pairing.includes(&s.player)) {
+                    let is_white = standing.player == pairing.white;
+                    standing.played += 1;
+                    match (outcome, is_white) {
+                        (Outcome::Draw, _) => {
+                            standing.draws += 1;
+                            standing.half_points += 1;
+                        },
+                        (Outcome::WhiteWins, true) | (Outcome::BlackWins, false) => {
+                            standing.wins += 1;
+                            standing.half_points += 2;
+                        },
+                        _ => {
+                            standing.losses += 1;
+                        },
+                    }
+                }
+            }
+            if let Some(ref bye) = round.bye {
+                if let Some(standing) = standings.iter_mut().find(|s| s.player == *bye) {
+                    standing.half_points += 2;
+                }
+            }
+        }
+
+        // Stable sort keeps the participant order for equal scores
+        standings.sort_by_key(|s| Reverse(s.half_points));
+        standings
+    }
+
+    fn index_of(&self, public_key: &CommsPublicKey) -> usize {
+        self.participants.iter().position(|p| p == public_key).unwrap()
+    }
+
+    fn have_played(&self, a: usize, b: usize) -> bool {
+        let (a, b) = (&self.participants[a], &self.participants[b]);
+        self.rounds
+            .iter()
+            .flat_map(|r| r.pairings.iter())
+            .any(|p| p.includes(a) && p.includes(b))
+    }
+
+    fn white_count(&self, player: usize) -> usize {
+        let player = &self.participants[player];
+        self.rounds
+            .iter()
+            .flat_map(|r| r.pairings.iter())
+            .filter(|p| p.white == *player)
+            .count()
+    }
+
+    fn has_had_bye(&self, player: usize) -> bool {
+        let player = &self.participants[player];
+        self.rounds.iter().any(|r| r.bye.as_ref() == Some(player))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Standing {
+    pub player: CommsPublicKey,
+    pub played: u32,
+    pub wins: u32,
+    pub draws: u32,
+    pub losses: u32,
+    pub half_points: u32,
+}
+
+impl Standing {
+    fn new(player: CommsPublicKey) -> Self {
+        Self {
+            player,
+            played: 0,
+            wins: 0,
+            draws: 0,
+            losses: 0,
+            half_points: 0,
+        }
+    }
+
+    pub fn points(&self) -> String {
+        if self.half_points.is_multiple_of(2) {
+            format!("{}", self.half_points / 2)
+        } else {
+            format!("{}.5", self.half_points / 2)
+        }
+    }
+}
+
+/// Pairs `num_players` players for the given round using the circle method. Every player meets every other player
+/// exactly once over all rounds. Returns (white, black) index pairs and the player with a bye, if any.
+pub fn round_robin_pairs(num_players: usize, round: usize) -> (Vec<(usize, usize)>, Option<usize>) {
+    let mut players = (0..num_players).map(Some).collect::<Vec<_>>();
+    if !players.len().is_multiple_of(2) {
+        players.push(None);
+    }
+    let n = players.len();
+    if n < 2 {
+        return (Vec::new(), None);
+    }
+    // The first player is fixed and the rest rotate each round
+    players[1..].rotate_right(round % (n - 1));
+
+    let mut pairs = Vec::with_capacity(n / 2);
+    let mut bye = None;
+    for i in 0..n / 2 {
+        match (players[i], players[n - 1 - i]) {
+            (Some(a), Some(b)) => {
+                // Alternate colours between rounds
+                if (round + i).is_multiple_of(2) {
+                    pairs.push((a, b));
+                } else {
+                    pairs.push((b, a));
+                }
+            },
+            (Some(p), None) | (None, Some(p)) => bye = Some(p),
+            (None, None) => {},
+        }
+    }
+    (pairs, bye)
+}
+
+/// Pairs players ranked by score, avoiding rematches where possible. The lowest ranked player that has not yet had a
+/// bye sits out if there is an odd number of players. Returns (white, black) index pairs and the player with a bye.
+pub fn swiss_pairs<P, W, B>(
+    ranked: &[usize],
+    have_played: P,
+    white_count: W,
+    has_had_bye: B,
+) -> (Vec<(usize, usize)>, Option<usize>)
+where
+    P: Fn(usize, usize) -> bool,
+    W: Fn(usize) -> usize,
+    B: Fn(usize) -> bool,
+{
+    let mut remaining = ranked.to_vec();
+    let mut bye = None;
+    if !remaining.len().is_multiple_of(2) {
+        let idx = remaining
+            .iter()
+            .rposition(|p| !has_had_bye(*p))
+            .unwrap_or(remaining.len() - 1);
+        bye = Some(remaining.remove(idx));
+    }
+
+    let mut pairs = Vec::with_capacity(remaining.len() / 2);
+    while !remaining.is_empty() {
+        let player = remaining.remove(0);
+        let idx = remaining.iter().position(|p| !have_played(player, *p)).unwrap_or(0);
+        let opponent = remaining.remove(idx);
+        // Whoever has played white less often gets white
+        if white_count(player) <= white_count(opponent) {
+            pairs.push((player, opponent));
+        } else {
+            pairs.push((opponent, player));
+        }
+    }
+    (pairs, bye)
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct TournamentCollection {
+    tournaments: Vec<Tournament>,
+}
+
+impl TournamentCollection {
+    pub fn get(&self, id: u32) -> Option<&Tournament> {
+        self.tournaments.iter().find(|t| t.id == id)
+    }
+
+    pub fn get_mut(&mut self, id: u32) -> Option<&mut Tournament> {
+        self.tournaments.iter_mut().find(|t| t.id == id)
+    }
+
+    pub fn insert(&mut self, tournament: Tournament) {
+        match self.get_mut(tournament.id) {
+            Some(t) => *t = tournament,
+            None => self.tournaments.push(tournament),
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Tournament> {
+        self.tournaments.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.tournaments.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tournaments.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashSet;
+
+    use tari_crypto::tari_utilities::ByteArray;
+
+    use super::*;
+
+    /// The compressed Ristretto base point, any valid point will do
+    const BASE_POINT: [u8; 32] = [
+        0xe2, 0xf2, 0xae, 0x0a, 0x6a, 0xbc, 0x4e, 0x71, 0xa8, 0x84, 0xa9, 0x61, 0xc5, 0x00, 0x51, 0x5f, 0x58, 0xe3,
+        0x0b, 0x6a, 0xa5, 0x82, 0xdd, 0x8d, 0xb6, 0xa6, 0x59, 0x45, 0xe0, 0x8d, 0x2d, 0x76,
+    ];
+
+    #[test]
+    fn round_robin_pairs_everyone_once() {
+        for n in 2..=7usize {
+            let num_rounds = if n.is_multiple_of(2) { n - 1 } else { n };
+            let mut seen = HashSet::new();
+            let mut byes = Vec::new();
+            for round in 0..num_rounds {
+                let (pairs, bye) = round_robin_pairs(n, round);
+                byes.extend(bye);
+                for (a, b) in pairs {
+                    assert!(
+                        seen.insert((a.min(b), a.max(b))),
+                        "rematch {} {} in round {}",
+                        a,
+                        b,
+                        round
+                    );
+                }
+            }
+            assert_eq!(seen.len(), n * (n - 1) / 2);
+            if !n.is_multiple_of(2) {
+                byes.sort_unstable();
+                assert_eq!(byes, (0..n).collect::<Vec<_>>());
+            }
+        }
+    }
+
+    #[test]
+    fn swiss_pairs_avoids_rematches() {
+        let played = [(0, 1), (2, 3)];
+        let have_played = |a: usize, b: usize| played.contains(&(a.min(b), a.max(b)));
+        let (pairs, bye) = swiss_pairs(&[0, 1, 2, 3], have_played, |_| 0, |_| false);
+        assert_eq!(bye, None);
+        assert_eq!(pairs, vec![(0, 2), (1, 3)]);
+    }
+
+    #[test]
+    fn report_outcome_requires_both_players_to_agree() {
+        let white = CommsPublicKey::default();
+        let black = CommsPublicKey::from_bytes(&BASE_POINT).unwrap();
+        let mut tournament = Tournament::new(1, white.clone(), TournamentFormat::RoundRobin, vec![
+            white.clone(),
+            black.clone(),
+        ]);
+        let game_id = tournament.next_round(|| 7).unwrap().pairings[0].game_id;
+        let (white, black) = {
+            let pairing = tournament.pairing(game_id).unwrap();
+            (pairing.white.clone(), pairing.black.clone())
+        };
+
+        assert!(!tournament.report_outcome(game_id, &white, Outcome::WhiteWins).unwrap());
+        assert!(!tournament.report_outcome(game_id, &black, Outcome::Draw).unwrap());
+        assert!(tournament.pairing(game_id).unwrap().is_disputed());
+        assert_eq!(tournament.pairing(game_id).unwrap().outcome, None);
+        // A player can correct their report
+        assert!(tournament.report_outcome(game_id, &black, Outcome::WhiteWins).unwrap());
+        assert!(!tournament.pairing(game_id).unwrap().is_disputed());
+        assert_eq!(tournament.pairing(game_id).unwrap().outcome, Some(Outcome::WhiteWins));
+    }
+
+    #[test]
+    fn organiser_settles_disputed_outcome() {
+        let white = CommsPublicKey::default();
+        let black = CommsPublicKey::from_bytes(&BASE_POINT).unwrap();
+        let mut tournament = Tournament::new(1, white.clone(), TournamentFormat::RoundRobin, vec![
+            white.clone(),
+            black.clone(),
+        ]);
+        let pairing = tournament.next_round(|| 7).unwrap().pairings[0].clone();
+        tournament
+            .report_outcome(pairing.game_id, &pairing.white, Outcome::WhiteWins)
+            .unwrap();
+        tournament
+            .report_outcome(pairing.game_id, &pairing.black, Outcome::BlackWins)
+            .unwrap();
+        assert!(!tournament.is_round_complete());
+        assert!(tournament.record_outcome(pairing.game_id, Outcome::Draw));
+        assert!(tournament.is_round_complete());
+        assert_eq!(
+            tournament.pairing(pairing.game_id).unwrap().outcome,
+            Some(Outcome::Draw)
+        );
+    }
+
+    #[test]
+    fn pairing_side_of_gives_colour_and_opponent() {
+        let white = CommsPublicKey::default();
+        let black = CommsPublicKey::from_bytes(&BASE_POINT).unwrap();
+        let pairing = Pairing {
+            game_id: 1,
+            white: white.clone(),
+            black: black.clone(),
+            outcome: None,
+            white_report: None,
+            black_report: None,
+        };
+        assert_eq!(pairing.side_of(&white), Some((Player::White, &black)));
+        assert_eq!(pairing.side_of(&black), Some((Player::Black, &white)));
+        let other = Pairing {
+            white: black.clone(),
+            ..pairing.clone()
+        };
+        assert_eq!(other.side_of(&white), None);
+    }
+
+    #[test]
+    fn swiss_pairs_gives_bye_to_lowest_without_one() {
+        let (pairs, bye) = swiss_pairs(&[0, 1, 2], |_, _| false, |_| 0, |p| p == 2);
+        assert_eq!(bye, Some(1));
+        assert_eq!(pairs, vec![(0, 2)]);
+    }
+}
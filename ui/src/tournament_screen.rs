@@ -0,0 +1,477 @@
+use minifb::Window;
+use tari_comms::types::CommsPublicKey;
+use tari_crypto::tari_utilities::encoding::Base58;
+
+use crate::{
+    clipboard::Clipboard,
+    color::Color,
+    components::{Button, Label, ListBox, TextBox},
+    drawable::{Drawable, FrameBuffer},
+    focus::FocusManager,
+    font::{DEFAULT_SIZE, FONT},
+    game::short_key,
+    layout::{Anchor, Padding, Size, Stack},
+    rect::{Frame, Rect},
+    start_screen::Drawables,
+    tournament::{Outcome, TournamentCollection, TournamentFormat},
+};
+
+const MAX_STANDINGS_ROWS: usize = 12;
+const ROW_HEIGHT: u32 = 20;
+const ROW_SPACING: u32 = 5;
+/// Room for the standings and their heading row
+const STANDINGS_HEIGHT: u32 = (MAX_STANDINGS_ROWS as u32 + 1) * (ROW_HEIGHT + ROW_SPACING);
+/// The player column takes the width the others leave
+const STANDINGS_COLUMNS: [Size; 7] = [
+    Size::Fixed(30),
+    Size::Fill(1),
+    Size::Fixed(40),
+    Size::Fixed(40),
+    Size::Fixed(40),
+    Size::Fixed(40),
+    Size::Fixed(60),
+];
+
+#[derive(Debug)]
+pub struct TournamentScreen {
+    public_key: CommsPublicKey,
+    back_button: Button,
+    participant_input: TextBox,
+    add_button: Button,
+    round_robin_button: Button,
+    swiss_button: Button,
+    create_button: Button,
+    participants: Vec<CommsPublicKey>,
+    participants_list: ListBox,
+    tournament_ids: Vec<u32>,
+    tournaments_list: ListBox,
+    labels: Drawables<Label>,
+    error_label: Label,
+    standings_labels: Drawables<Label>,
+    /// The area the standings table is laid out in
+    standings_frame: Frame,
+    /// Shown to the organiser for a game the players reported different outcomes for
+    dispute_label: Label,
+    white_wins_button: Button,
+    draw_button: Button,
+    black_wins_button: Button,
+    /// The tournament and game the settle buttons are for
+    disputed_game: Option<(u32, u32)>,
+    is_swiss: bool,
+    create_request: Option<(TournamentFormat, Vec<CommsPublicKey>)>,
+    settle_request: Option<(u32, u32, Outcome)>,
+    focus: FocusManager,
+}
+
+impl TournamentScreen {
+    pub fn new(clipboard: Clipboard, public_key: CommsPublicKey, window_width: u32, window_height: u32) -> Self {
+        let mut title_label = Label::new(Frame::default());
+        title_label.set_text("Tournaments").set_text_color(Color::dark_blue());
+
+        let mut standings_label = Label::new(Frame::default());
+        standings_label.set_text("Standings");
+
+        let mut tournaments_label = Label::new(Frame::default());
+        tournaments_label.set_text("Your tournaments");
+
+        let mut participant_label = Label::new(Frame::default());
+        participant_label.set_text("Participant public key");
+
+        let mut participants_label = Label::new(Frame::default());
+        participants_label.set_text("Participants");
+
+        let mut error_label = Label::new(Frame::default());
+        error_label
+            .set_text("")
+            .set_text_color(Color::red())
+            .set_bg_color(Color::black());
+
+        let mut back_button = Button::new(Rect::from_frame(Frame::default(), Color::white()));
+        back_button.set_text("Back");
+
+        let mut participant_input = TextBox::new(Frame::default(), clipboard);
+        participant_input.set_placeholder("Participant public key");
+        let mut add_button = Button::new(Rect::from_frame(Frame::default(), Color::white()));
+        add_button.set_text("Add");
+
+        let mut round_robin_button = Button::new(Rect::from_frame(Frame::default(), Color::light_grey()));
+        round_robin_button.set_text("Round robin");
+        let mut swiss_button = Button::new(Rect::from_frame(Frame::default(), Color::white()));
+        swiss_button.set_text("Swiss");
+        let mut create_button = Button::new(Rect::from_frame(Frame::default(), Color::white()));
+        create_button.set_text("Create");
+
+        let participants_list = ListBox::new(Frame::default());
+
+        let dispute_label = Label::new(Frame::default());
+        let mut white_wins_button = Button::new(Rect::from_frame(Frame::default(), Color::white()));
+        white_wins_button.set_text("White wins").set_visible(false);
+        let mut draw_button = Button::new(Rect::from_frame(Frame::default(), Color::white()));
+        draw_button.set_text("Draw").set_visible(false);
+        let mut black_wins_button = Button::new(Rect::from_frame(Frame::default(), Color::white()));
+        black_wins_button.set_text("Black wins").set_visible(false);
+
+        let mut tournaments_list = ListBox::new(Frame::default());
+        // Always capture keys
+        tournaments_list.set_active(true);
+
+        let mut screen = Self {
+            public_key,
+            back_button,
+            participant_input,
+            add_button,
+            round_robin_button,
+            swiss_button,
+            create_button,
+            participants: Vec::new(),
+            participants_list,
+            tournament_ids: Vec::new(),
+            tournaments_list,
+            labels: Drawables::new(vec![
+                title_label,
+                standings_label,
+                tournaments_label,
+                participant_label,
+                participants_label,
+            ]),
+            error_label,
+            standings_labels: Drawables::new(Vec::new()),
+            standings_frame: Frame::default(),
+            dispute_label,
+            white_wins_button,
+            draw_button,
+            black_wins_button,
+            disputed_game: None,
+            is_swiss: false,
+            create_request: None,
+            settle_request: None,
+            focus: FocusManager::new(),
+        };
+        screen.resize(window_width, window_height);
+        screen
+    }
+
+    /// Lays the standings and tournaments out on the left half of the screen and creating a tournament on the right
+    pub fn resize(&mut self, window_width: u32, window_height: u32) -> &mut Self {
+        let mut column = Stack::column(Padding::all(10).apply(Frame::new(0, 0, window_width, window_height)));
+        column.set_spacing(10);
+        let header = column.next(40);
+        let title_width = FONT.measure(self.labels.items[0].text(), DEFAULT_SIZE) + 4;
+        self.labels.items[0].set_frame(Anchor::Top.place(header, title_width, 40));
+        self.back_button.set_frame(Anchor::TopRight.place(header, 100, 30));
+
+        let mut halves = Stack::row(column.rest());
+        halves.set_spacing(20);
+        let halves = halves.split(&[Size::Fill(1), Size::Fill(1)]);
+
+        let mut left = Stack::column(halves[0]);
+        left.set_spacing(10);
+        let left = left.split(&[
+            Size::Fixed(20),
+            Size::Fixed(STANDINGS_HEIGHT),
+            Size::Fixed(20),
+            Size::Fill(1),
+        ]);
+        self.labels.items[1].set_frame(left[0]);
+        self.standings_frame = left[1];
+        self.labels.items[2].set_frame(left[2]);
+        self.tournaments_list.set_frame(left[3]);
+        self.layout_standings();
+
+        let mut right = Stack::column(halves[1]);
+        right.set_spacing(10);
+        self.labels.items[3].set_frame(right.next(20));
+        self.participant_input.set_frame(right.next(40));
+        self.add_button.set_frame(Anchor::Left.place(right.next(30), 100, 30));
+        let mut format_row = Stack::row(right.next(30));
+        format_row.set_spacing(10);
+        self.round_robin_button.set_frame(format_row.next(150));
+        self.swiss_button.set_frame(format_row.next(100));
+        self.create_button.set_frame(format_row.next(100));
+        self.error_label.set_frame(right.next(30));
+        self.labels.items[4].set_frame(right.next(20));
+        let rest = right.split(&[Size::Fill(1), Size::Fixed(20), Size::Fixed(30)]);
+        self.participants_list.set_frame(rest[0]);
+        self.dispute_label.set_frame(rest[1]);
+        let mut settle_row = Stack::row(rest[2]);
+        settle_row.set_spacing(10);
+        self.white_wins_button.set_frame(settle_row.next(150));
+        self.draw_button.set_frame(settle_row.next(100));
+        self.black_wins_button.set_frame(settle_row.next(150));
+        self
+    }
+
+    pub fn update(&mut self, window: &Window) {
+        self.back_button.update(window);
+        self.participant_input.update(window);
+        self.add_button.update(window);
+        self.round_robin_button.update(window);
+        self.swiss_button.update(window);
+        self.create_button.update(window);
+        self.participants_list.update(window);
+        self.tournaments_list.update(window);
+        self.white_wins_button.update(window);
+        self.draw_button.update(window);
+        self.black_wins_button.update(window);
+        self.focus.update(window, &mut [
+            &mut self.participant_input,
+            &mut self.add_button,
+            &mut self.round_robin_button,
+            &mut self.swiss_button,
+            &mut self.create_button,
+            &mut self.participants_list,
+            &mut self.tournaments_list,
+            &mut self.white_wins_button,
+            &mut self.draw_button,
+            &mut self.black_wins_button,
+            &mut self.back_button,
+        ]);
+
+        if self.add_button.was_clicked() {
+            match CommsPublicKey::from_base58(self.participant_input.value().trim()) {
+                Ok(pk) => {
+                    if !self.participants.contains(&pk) {
+                        self.participants.push(pk);
+                        self.refresh_participants();
+                    }
+                    self.participant_input.set_value(String::new());
+                    self.set_error("");
+                },
+                Err(_) => {
+                    if !self.participant_input.value().is_empty() {
+                        self.set_error("Invalid public key");
+                    }
+                },
+            }
+        }
+
+        if self.round_robin_button.was_clicked() {
+            self.set_swiss(false);
+        }
+        if self.swiss_button.was_clicked() {
+            self.set_swiss(true);
+        }
+
+        let settled = [
+            (&mut self.white_wins_button, Outcome::WhiteWins),
+            (&mut self.draw_button, Outcome::Draw),
+            (&mut self.black_wins_button, Outcome::BlackWins),
+        ]
+        .into_iter()
+        .find_map(|(button, outcome)| button.was_clicked().then_some(outcome));
+        if let (Some(outcome), Some((tournament_id, game_id))) = (settled, self.disputed_game) {
+            self.settle_request = Some((tournament_id, game_id, outcome));
+        }
+
+        if self.create_button.was_clicked() {
+            match self.participants.len() {
+                0 | 1 => {
+                    self.set_error("At least 2 participants are required");
+                },
+                n => {
+                    let format = if self.is_swiss {
+                        // Enough rounds to determine a clear winner
+                        let rounds = (usize::BITS - (n - 1).leading_zeros()).max(1);
+                        TournamentFormat::Swiss { rounds }
+                    } else {
+                        TournamentFormat::RoundRobin
+                    };
+                    self.create_request = Some((format, self.participants.drain(..).collect()));
+                    self.refresh_participants();
+                    self.set_error("");
+                },
+            }
+        }
+    }
+
+    pub fn was_back_clicked(&mut self) -> bool {
+        self.back_button.was_clicked()
+    }
+
+    pub fn take_create_request(&mut self) -> Option<(TournamentFormat, Vec<CommsPublicKey>)> {
+        self.create_request.take()
+    }
+
+    /// Returns the tournament, game and outcome the organiser chose for a disputed game
+    pub fn take_settle_request(&mut self) -> Option<(u32, u32, Outcome)> {
+        self.settle_request.take()
+    }
+
+    pub fn set_error<T: Into<String>>(&mut self, msg: T) -> &mut Self {
+        self.error_label.set_text(msg);
+        self
+    }
+
+    pub fn set_tournaments(&mut self, tournaments: &TournamentCollection) {
+        self.tournament_ids = tournaments.iter().map(|t| t.id).collect();
+        self.tournaments_list.set_values(
+            tournaments
+                .iter()
+                .map(|t| {
+                    let status = if t.is_finished() { " finished" } else { "" };
+                    format!(
+                        "{} {} round {} of {}{}",
+                        t.id,
+                        t.format,
+                        t.rounds.len(),
+                        t.total_rounds(),
+                        status
+                    )
+                })
+                .collect(),
+        );
+
+        let selected = self
+            .tournaments_list
+            .selected_index()
+            .and_then(|idx| self.tournament_ids.get(idx))
+            .and_then(|id| tournaments.get(*id));
+
+        let disputed = selected.filter(|t| t.organiser == self.public_key).and_then(|t| {
+            t.rounds
+                .iter()
+                .flat_map(|r| r.pairings.iter())
+                .find(|p| p.is_disputed())
+                .map(|p| (t.id, p))
+        });
+        self.disputed_game = disputed.map(|(tournament_id, p)| (tournament_id, p.game_id));
+        match disputed {
+            Some((_, pairing)) => {
+                self.dispute_label.set_text(format!(
+                    "Game {}: white reported {}, black reported {}",
+                    pairing.game_id,
+                    pairing.white_report.map(|o| o.to_string()).unwrap_or_default(),
+                    pairing.black_report.map(|o| o.to_string()).unwrap_or_default(),
+                ));
+            },
+            None => {
+                self.dispute_label.set_text("");
+            },
+        }
+        for button in [
+            &mut self.white_wins_button,
+            &mut self.draw_button,
+            &mut self.black_wins_button,
+        ] {
+            button.set_visible(disputed.is_some());
+        }
+
+        let mut items = vec![standings_row(
+            ["#", "Player", "P", "W", "D", "L", "Pts"],
+            Color::light_grey(),
+        )];
+        if let Some(tournament) = selected {
+            for (i, standing) in tournament.standings().iter().take(MAX_STANDINGS_ROWS).enumerate() {
+                items.push(standings_row(
+                    [
+                        &(i + 1).to_string(),
+                        &self.display_name(&standing.player),
+                        &standing.played.to_string(),
+                        &standing.wins.to_string(),
+                        &standing.draws.to_string(),
+                        &standing.losses.to_string(),
+                        &standing.points(),
+                    ],
+                    Color::white(),
+                ));
+            }
+        }
+        let items = items.into_iter().flatten().collect::<Vec<_>>();
+        // Rebuilt every frame, only redraw the table when it changes
+        if items
+            .iter()
+            .map(Label::text)
+            .ne(self.standings_labels.items.iter().map(Label::text))
+        {
+            self.standings_labels.set_items(items);
+            self.layout_standings();
+        }
+    }
+
+    /// Places the standings rows one below the other. Rows that don't fit in the window are hidden.
+    fn layout_standings(&mut self) {
+        let mut rows = Stack::column(self.standings_frame);
+        rows.set_spacing(ROW_SPACING);
+        for row in self.standings_labels.items.chunks_mut(STANDINGS_COLUMNS.len()) {
+            let frame = rows.next(ROW_HEIGHT);
+            let mut cells = Stack::row(frame);
+            for (label, cell) in row.iter_mut().zip(cells.split(&STANDINGS_COLUMNS)) {
+                label.set_frame(cell).set_visible(frame.h == ROW_HEIGHT);
+            }
+        }
+    }
+
+    fn display_name(&self, public_key: &CommsPublicKey) -> String {
+        if *public_key == self.public_key {
+            "You".to_string()
+        } else {
+            short_key(public_key, 16)
+        }
+    }
+
+    fn set_swiss(&mut self, is_swiss: bool) {
+        self.is_swiss = is_swiss;
+        let (selected, other) = if is_swiss {
+            (&mut self.swiss_button, &mut self.round_robin_button)
+        } else {
+            (&mut self.round_robin_button, &mut self.swiss_button)
+        };
+        selected.set_bg_color(Color::light_grey());
+        other.set_bg_color(Color::white());
+    }
+
+    fn refresh_participants(&mut self) {
+        let values = self.participants.iter().map(|pk| pk.to_base58()).collect();
+        self.participants_list.set_values(values);
+    }
+}
+
+/// The labels of one standings row, laid out by `layout_standings`
+fn standings_row(columns: [&str; 7], color: Color) -> Vec<Label> {
+    columns
+        .iter()
+        .map(|text| {
+            let mut label = Label::new(Frame::default());
+            label.set_text(*text).set_text_color(color);
+            label
+        })
+        .collect()
+}
+
+impl Drawable for TournamentScreen {
+    fn draw(&mut self, buf: &mut FrameBuffer) {
+        buf.invalidate_widgets(&mut [
+            &mut self.labels,
+            &mut self.back_button,
+            &mut self.participant_input,
+            &mut self.add_button,
+            &mut self.round_robin_button,
+            &mut self.swiss_button,
+            &mut self.create_button,
+            &mut self.error_label,
+            &mut self.participants_list,
+            &mut self.tournaments_list,
+            &mut self.standings_labels,
+            &mut self.dispute_label,
+            &mut self.white_wins_button,
+            &mut self.draw_button,
+            &mut self.black_wins_button,
+        ]);
+        buf.clear(Color::black());
+        self.labels.draw(buf);
+        self.back_button.draw(buf);
+        self.participant_input.draw(buf);
+        self.add_button.draw(buf);
+        self.round_robin_button.draw(buf);
+        self.swiss_button.draw(buf);
+        self.create_button.draw(buf);
+        self.error_label.draw(buf);
+        self.participants_list.draw(buf);
+        self.tournaments_list.draw(buf);
+        self.standings_labels.draw(buf);
+        self.dispute_label.draw(buf);
+        self.white_wins_button.draw(buf);
+        self.draw_button.draw(buf);
+        self.black_wins_button.draw(buf);
+    }
+}
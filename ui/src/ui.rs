@@ -1,12 +1,27 @@
 use std::{path::PathBuf, time::Duration};
 
-use minifb::{Key, Window, WindowOptions};
+use minifb::{Window, WindowOptions};
 use p2p_chess_channel::{ChessOperation, MessageChannel};
 use tari_comms::types::CommsPublicKey;
 
-use crate::{color::Color, drawable::FrameBuffer, game_screen::GameConfig, screen_manager::ScreenManager};
+use crate::{
+    clipboard::Clipboard,
+    color::Color,
+    drawable::{Drawable, FrameBuffer},
+    game_screen::GameConfig,
+    input,
+    profile_screen::ProfileScreen,
+    screen_manager::ScreenManager,
+    settings::{SettingsOverrides, SETTINGS_FILE},
+    theme::Theme,
+};
 
 const BACKGROUND_COLOUR: Color = Color::black();
+/// ~60fps while the screen is changing
+const ACTIVE_FRAME_TIME: Duration = Duration::from_micros(16600);
+/// Input and messages are polled less often once nothing has changed for `IDLE_AFTER_FRAMES`
+const IDLE_FRAME_TIME: Duration = Duration::from_millis(50);
+const IDLE_AFTER_FRAMES: u32 = 60;
 
 pub struct ChessUi {
     title: &'static str,
@@ -16,6 +31,9 @@ pub struct ChessUi {
     channel: MessageChannel<ChessOperation>,
     public_key: CommsPublicKey,
     base_path: PathBuf,
+    themes_path: PathBuf,
+    settings_path: PathBuf,
+    settings_overrides: SettingsOverrides,
 }
 
 impl ChessUi {
@@ -35,16 +53,37 @@ impl ChessUi {
             window_height,
             opts,
             channel,
+            themes_path: base_path.join("themes"),
+            settings_path: base_path.join(SETTINGS_FILE),
             base_path,
             public_key,
+            settings_overrides: SettingsOverrides::default(),
         }
     }
 
+    /// Sets the directory theme files are loaded from. Defaults to `themes` in the base path.
+    pub fn set_themes_path(&mut self, themes_path: PathBuf) -> &mut Self {
+        self.themes_path = themes_path;
+        self
+    }
+
+    /// Sets the settings file. Defaults to the settings file in the base path.
+    pub fn set_settings_path(&mut self, settings_path: PathBuf) -> &mut Self {
+        self.settings_path = settings_path;
+        self
+    }
+
+    /// Sets settings given on the command line, which are used instead of the settings file
+    pub fn set_settings_overrides(&mut self, overrides: SettingsOverrides) -> &mut Self {
+        self.settings_overrides = overrides;
+        self
+    }
+
     pub fn run(self) -> anyhow::Result<()> {
         let mut window = Window::new(self.title, self.window_width, self.window_height, self.opts)?;
 
-        // ~60fps
-        window.limit_update_rate(Some(Duration::from_micros(16600)));
+        window.limit_update_rate(Some(ACTIVE_FRAME_TIME));
+        input::capture_typed_chars(&mut window);
 
         self.ui_loop(window)?;
 
@@ -57,24 +96,112 @@ impl ChessUi {
         let config = GameConfig {
             window_width: self.window_width as u32,
             window_height: self.window_height as u32,
-            light_color: Color::white(),
-            dark_color: Color::dark_blue(),
+            theme: Theme::default(),
             save_path: self.base_path.join("p2pc-games.json"),
+            tournaments_path: self.base_path.join("p2pc-tournaments.json"),
+            archive_path: self.base_path.join("p2pc-archive.json"),
+            settings_path: self.settings_path.clone(),
+            themes_path: self.themes_path.clone(),
         };
 
         let mut screen_manager = ScreenManager::initialize(config, self.channel, self.public_key)?;
+        screen_manager.set_settings_overrides(self.settings_overrides);
 
+        let mut idle_frames = 0;
         while window.is_open() {
+            if let Some((width, height)) = resized(&window, &mut buf) {
+                screen_manager.resize(width, height);
+            }
+            screen_manager.set_window_active(window.is_active());
             screen_manager.render(&window, &mut buf);
 
-            if let Some(keys) = window.get_keys() {
-                if (keys.contains(&Key::LeftCtrl) || keys.contains(&Key::RightCtrl)) && keys.contains(&Key::Q) {
-                    break;
-                }
+            if screen_manager.is_quit_requested() {
+                break;
             }
 
-            window.update_with_buffer(buf.as_slice(), self.window_width, self.window_height)?;
+            present(&mut window, &mut buf, &mut idle_frames)?;
         }
         Ok(())
     }
 }
+
+/// Shows the frame if anything was redrawn, otherwise only processes window events. Slows down the loop once the
+/// screen has been idle for a while.
+fn present(window: &mut Window, buf: &mut FrameBuffer, idle_frames: &mut u32) -> anyhow::Result<()> {
+    input::clear_typed_chars();
+    if !buf.take_damage().is_empty() {
+        window.update_with_buffer(buf.as_slice(), buf.width() as usize, buf.height() as usize)?;
+        *idle_frames = 0;
+    } else {
+        window.update();
+        *idle_frames = idle_frames.saturating_add(1);
+    }
+    let frame_time = if *idle_frames < IDLE_AFTER_FRAMES {
+        ACTIVE_FRAME_TIME
+    } else {
+        IDLE_FRAME_TIME
+    };
+    window.limit_update_rate(Some(frame_time));
+    Ok(())
+}
+
+/// Resizes the frame buffer to match the window, returning the new size if it changed
+fn resized(window: &Window, buf: &mut FrameBuffer) -> Option<(u32, u32)> {
+    let (width, height) = window.get_size();
+    let (width, height) = (width as u32, height as u32);
+    // Minimised windows report a zero size
+    if width == 0 || height == 0 || (width, height) == (buf.width(), buf.height()) {
+        return None;
+    }
+    buf.resize(width, height, BACKGROUND_COLOUR);
+    Some((width, height))
+}
+
+pub struct ProfilePicker {
+    title: &'static str,
+    window_width: usize,
+    window_height: usize,
+    opts: WindowOptions,
+    profiles: Vec<String>,
+}
+
+impl ProfilePicker {
+    pub fn new(
+        title: &'static str,
+        window_width: usize,
+        window_height: usize,
+        opts: WindowOptions,
+        profiles: Vec<String>,
+    ) -> Self {
+        Self {
+            title,
+            window_width,
+            window_height,
+            opts,
+            profiles,
+        }
+    }
+
+    /// Shows the profile picker and returns the chosen profile name, or None if the window was closed.
+    pub fn run(self) -> anyhow::Result<Option<String>> {
+        let mut window = Window::new(self.title, self.window_width, self.window_height, self.opts)?;
+        window.limit_update_rate(Some(ACTIVE_FRAME_TIME));
+        input::capture_typed_chars(&mut window);
+
+        let mut buf = FrameBuffer::new(self.window_width as u32, self.window_height as u32, BACKGROUND_COLOUR);
+        let mut screen = ProfileScreen::new(Clipboard::initialize()?, self.profiles);
+
+        let mut idle_frames = 0;
+        while window.is_open() {
+            resized(&window, &mut buf);
+            screen.update(&window);
+            screen.draw(&mut buf);
+            if let Some(profile) = screen.take_chosen_profile() {
+                return Ok(Some(profile));
+            }
+            present(&mut window, &mut buf, &mut idle_frames)?;
+        }
+
+        Ok(None)
+    }
+}